@@ -0,0 +1,431 @@
+use std::ops::Range;
+
+use anyhow::{bail, ensure};
+use itertools::Itertools;
+use tracing::debug;
+
+/// Access to the TTL words and ANNIS segmentation nodes of a document to be aligned, both given by
+/// their 0-based positions
+pub(crate) trait Tokens {
+    fn ttl_len(&self) -> usize;
+
+    fn annis_len(&self) -> usize;
+
+    /// Name of the TTL word at the given position, used in error messages
+    fn ttl_name(&self, ttl_index: usize) -> String;
+
+    /// Description of the first mismatch between the annotations of the given TTL word and ANNIS
+    /// node, if any
+    fn mismatch(&self, ttl_index: usize, annis_index: usize) -> anyhow::Result<Option<String>>;
+
+    /// Whether the given ANNIS node may be skipped if it doesn't correspond to the current TTL word
+    fn is_skippable(&self, annis_index: usize) -> anyhow::Result<bool>;
+
+    /// Whether the given TTL word and ANNIS node agree closely enough to re-synchronize the
+    /// alignment on them after a gap
+    fn resyncs(&self, ttl_index: usize, annis_index: usize) -> anyhow::Result<bool>;
+}
+
+/// Result of aligning TTL words with ANNIS segmentation nodes
+#[derive(Debug, Default)]
+pub(crate) struct Alignment {
+    /// Positions of the aligned TTL words and ANNIS nodes, ordered by position
+    pub(crate) pairs: Vec<(usize, usize)>,
+    /// Positions of the ANNIS nodes that were skipped
+    pub(crate) skipped: Vec<usize>,
+    /// Positions of consecutive ANNIS nodes without counterpart in TTL that were tolerated as gaps
+    pub(crate) gaps: Vec<Range<usize>>,
+    /// Aligned pairs whose annotations mismatch, which is only tolerated between anchors and when
+    /// re-synchronizing after a gap
+    pub(crate) conflicts: Vec<Conflict>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Conflict {
+    pub(crate) ttl_index: usize,
+    pub(crate) annis_index: usize,
+    pub(crate) mismatch: String,
+}
+
+/// Aligns the TTL words with the ANNIS nodes, respecting the given anchors (pairs of positions of
+/// TTL words and ANNIS nodes known to correspond to each other)
+///
+/// The region before the first anchor and the region after the last anchor (or everything if there
+/// are no anchors) are aligned sequentially, the regions between two anchors by interpolation.
+/// When aligning sequentially, gaps of up to `max_gap` ANNIS nodes without counterpart in TTL are
+/// tolerated.
+pub(crate) fn align(
+    tokens: &impl Tokens,
+    anchors: &[(usize, usize)],
+    max_gap: usize,
+) -> anyhow::Result<Alignment> {
+    let mut anchors = anchors.to_vec();
+    anchors.sort_unstable();
+
+    for &(ttl_index, annis_index) in &anchors {
+        ensure!(
+            ttl_index < tokens.ttl_len() && annis_index < tokens.annis_len(),
+            "alignment anchor ({ttl_index}, {annis_index}) is out of range"
+        );
+    }
+
+    for (a, b) in anchors.iter().tuple_windows() {
+        ensure!(
+            b.0 > a.0 && b.1 > a.1,
+            "alignment anchors for ttl words {} and {} cross or overlap",
+            tokens.ttl_name(a.0),
+            tokens.ttl_name(b.0),
+        );
+    }
+
+    debug!(
+        ttl_len = tokens.ttl_len(),
+        annis_len = tokens.annis_len(),
+        anchors = anchors.len(),
+        "aligning document"
+    );
+
+    let mut alignment = Alignment::default();
+    let mut ttl_start = 0;
+    let mut annis_start = 0;
+
+    for (i, &(ttl_index, annis_index)) in anchors.iter().enumerate() {
+        let ttl_region = ttl_start..ttl_index;
+        let annis_region = annis_start..annis_index;
+
+        if i == 0 {
+            alignment.align_sequentially(tokens, ttl_region, annis_region, max_gap)?;
+        } else {
+            alignment.align_interpolated(tokens, ttl_region, annis_region)?;
+        }
+
+        // anchored pairs are aligned without any sanity check
+        alignment.pairs.push((ttl_index, annis_index));
+
+        ttl_start = ttl_index + 1;
+        annis_start = annis_index + 1;
+    }
+
+    alignment.align_sequentially(
+        tokens,
+        ttl_start..tokens.ttl_len(),
+        annis_start..tokens.annis_len(),
+        max_gap,
+    )?;
+
+    Ok(alignment)
+}
+
+impl Alignment {
+    /// Aligns the TTL words with the ANNIS nodes one by one, skipping ANNIS nodes that don't
+    /// correspond to the current TTL word but are skippable
+    ///
+    /// If an ANNIS node doesn't correspond to the current TTL word, but one of the following
+    /// `max_gap` ANNIS nodes re-synchronizes with it, the ANNIS nodes in between are treated as a
+    /// gap without counterpart in TTL. ANNIS nodes remaining after the last TTL word are left
+    /// unaligned, since there may be incomplete sentences in ANNIS, which have no counterpart in
+    /// TTL.
+    fn align_sequentially(
+        &mut self,
+        tokens: &impl Tokens,
+        ttl_region: Range<usize>,
+        annis_region: Range<usize>,
+        max_gap: usize,
+    ) -> anyhow::Result<()> {
+        let mut ttl_indices = ttl_region.peekable();
+        let mut annis_index = annis_region.start;
+
+        while annis_index < annis_region.end {
+            let Some(&ttl_index) = ttl_indices.peek() else {
+                break;
+            };
+
+            if let Some(mismatch) = tokens.mismatch(ttl_index, annis_index)? {
+                if tokens.is_skippable(annis_index)? {
+                    debug!(
+                        ttl = tokens.ttl_name(ttl_index),
+                        annis_index,
+                        %mismatch,
+                        "skipping ANNIS node"
+                    );
+                    self.skipped.push(annis_index);
+                    annis_index += 1;
+                    continue;
+                }
+
+                let gap_end = (annis_index + 1 + max_gap).min(annis_region.end);
+                let mut resync_index = None;
+
+                for i in annis_index + 1..gap_end {
+                    if tokens.resyncs(ttl_index, i)? {
+                        resync_index = Some(i);
+                        break;
+                    }
+                }
+
+                let Some(resync_index) = resync_index else {
+                    bail!("sanity check failed: {mismatch}");
+                };
+
+                debug!(
+                    ttl = tokens.ttl_name(ttl_index),
+                    annis_index,
+                    resync_index,
+                    %mismatch,
+                    "re-synchronizing after gap"
+                );
+
+                self.gaps.push(annis_index..resync_index);
+
+                if let Some(mismatch) = tokens.mismatch(ttl_index, resync_index)? {
+                    self.conflicts.push(Conflict {
+                        ttl_index,
+                        annis_index: resync_index,
+                        mismatch,
+                    });
+                }
+
+                annis_index = resync_index;
+            }
+
+            self.pairs.push((ttl_index, annis_index));
+            ttl_indices.next();
+            annis_index += 1;
+        }
+
+        if let Some(ttl_index) = ttl_indices.next() {
+            bail!(
+                "ttl node {} has no counterpart in ANNIS",
+                tokens.ttl_name(ttl_index)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Aligns the TTL words with the ANNIS nodes between two anchors by linear interpolation of
+    /// their positions, where mismatching annotations are recorded as conflicts
+    fn align_interpolated(
+        &mut self,
+        tokens: &impl Tokens,
+        ttl_region: Range<usize>,
+        annis_region: Range<usize>,
+    ) -> anyhow::Result<()> {
+        if ttl_region.is_empty() {
+            return Ok(());
+        }
+
+        ensure!(
+            !annis_region.is_empty(),
+            "ttl node {} has no counterpart in ANNIS between alignment anchors",
+            tokens.ttl_name(ttl_region.start)
+        );
+
+        let (ttl_len, annis_len) = (ttl_region.len(), annis_region.len());
+
+        for (i, ttl_index) in ttl_region.enumerate() {
+            let annis_index = annis_region.start + i * annis_len / ttl_len;
+
+            if let Some(mismatch) = tokens.mismatch(ttl_index, annis_index)? {
+                self.conflicts.push(Conflict {
+                    ttl_index,
+                    annis_index,
+                    mismatch,
+                });
+            }
+
+            self.pairs.push((ttl_index, annis_index));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tokens given by their values, where an empty ANNIS value is skippable and values that
+    /// differ only in case re-synchronize (but still mismatch)
+    struct FakeTokens {
+        ttl: Vec<&'static str>,
+        annis: Vec<&'static str>,
+    }
+
+    impl FakeTokens {
+        fn new(ttl: &[&'static str], annis: &[&'static str]) -> Self {
+            Self {
+                ttl: ttl.to_vec(),
+                annis: annis.to_vec(),
+            }
+        }
+    }
+
+    impl Tokens for FakeTokens {
+        fn ttl_len(&self) -> usize {
+            self.ttl.len()
+        }
+
+        fn annis_len(&self) -> usize {
+            self.annis.len()
+        }
+
+        fn ttl_name(&self, ttl_index: usize) -> String {
+            format!("w{ttl_index}")
+        }
+
+        fn mismatch(&self, ttl_index: usize, annis_index: usize) -> anyhow::Result<Option<String>> {
+            let (ttl, annis) = (self.ttl[ttl_index], self.annis[annis_index]);
+            Ok((ttl != annis).then(|| format!("{ttl} != {annis}")))
+        }
+
+        fn is_skippable(&self, annis_index: usize) -> anyhow::Result<bool> {
+            Ok(self.annis[annis_index].is_empty())
+        }
+
+        fn resyncs(&self, ttl_index: usize, annis_index: usize) -> anyhow::Result<bool> {
+            Ok(self.ttl[ttl_index].eq_ignore_ascii_case(self.annis[annis_index]))
+        }
+    }
+
+    fn conflict_positions(alignment: &Alignment) -> Vec<(usize, usize)> {
+        alignment
+            .conflicts
+            .iter()
+            .map(|conflict| (conflict.ttl_index, conflict.annis_index))
+            .collect()
+    }
+
+    #[test]
+    fn aligns_sequentially() {
+        let tokens = FakeTokens::new(&["a", "b", "c"], &["a", "b", "c"]);
+        let alignment = align(&tokens, &[], 0).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 0), (1, 1), (2, 2)]);
+        assert!(alignment.skipped.is_empty());
+        assert!(alignment.gaps.is_empty());
+        assert!(alignment.conflicts.is_empty());
+    }
+
+    #[test]
+    fn leaves_trailing_annis_nodes_unaligned() {
+        let tokens = FakeTokens::new(&["a", "b"], &["a", "b", "c", "d"]);
+        let alignment = align(&tokens, &[], 0).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn fails_for_trailing_ttl_words() {
+        let tokens = FakeTokens::new(&["a", "b", "c"], &["a", "b"]);
+        let err = align(&tokens, &[], 0).unwrap_err();
+
+        assert_eq!(err.to_string(), "ttl node w2 has no counterpart in ANNIS");
+    }
+
+    #[test]
+    fn skips_skippable_annis_nodes() {
+        let tokens = FakeTokens::new(&["a", "b"], &["", "a", "", "", "b"]);
+        let alignment = align(&tokens, &[], 0).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 1), (1, 4)]);
+        assert_eq!(alignment.skipped, [0, 2, 3]);
+        assert!(alignment.gaps.is_empty());
+    }
+
+    #[test]
+    fn resyncs_after_gap() {
+        let tokens = FakeTokens::new(&["a", "b", "c"], &["a", "x", "y", "b", "c"]);
+        let alignment = align(&tokens, &[], 2).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 0), (1, 3), (2, 4)]);
+        assert_eq!(alignment.gaps, [Range { start: 1, end: 3 }]);
+        assert!(alignment.conflicts.is_empty());
+    }
+
+    #[test]
+    fn records_conflict_when_resyncing_on_mismatch() {
+        let tokens = FakeTokens::new(&["a", "b"], &["a", "x", "B"]);
+        let alignment = align(&tokens, &[], 1).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 0), (1, 2)]);
+        assert_eq!(alignment.gaps, [Range { start: 1, end: 2 }]);
+        assert_eq!(conflict_positions(&alignment), [(1, 2)]);
+        assert_eq!(alignment.conflicts[0].mismatch, "b != B");
+    }
+
+    #[test]
+    fn fails_for_gap_longer_than_max_gap() {
+        let tokens = FakeTokens::new(&["a", "b"], &["a", "x", "y", "b"]);
+        let err = align(&tokens, &[], 1).unwrap_err();
+
+        assert_eq!(err.to_string(), "sanity check failed: b != x");
+    }
+
+    #[test]
+    fn uses_anchor_without_sanity_check() {
+        let tokens = FakeTokens::new(&["a", "b", "c"], &["a", "x", "c"]);
+        let alignment = align(&tokens, &[(1, 1)], 0).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 0), (1, 1), (2, 2)]);
+        assert!(alignment.conflicts.is_empty());
+    }
+
+    #[test]
+    fn sorts_anchors() {
+        let tokens = FakeTokens::new(&["a", "b", "c", "d"], &["a", "x", "y", "d"]);
+        let alignment = align(&tokens, &[(2, 2), (1, 1)], 0).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 0), (1, 1), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn fails_for_anchor_out_of_range() {
+        let tokens = FakeTokens::new(&["a"], &["a"]);
+        let err = align(&tokens, &[(0, 1)], 0).unwrap_err();
+
+        assert_eq!(err.to_string(), "alignment anchor (0, 1) is out of range");
+    }
+
+    #[test]
+    fn fails_for_crossing_anchors() {
+        let tokens = FakeTokens::new(&["a", "b", "c"], &["a", "b", "c"]);
+        let err = align(&tokens, &[(0, 2), (1, 1)], 0).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "alignment anchors for ttl words w0 and w1 cross or overlap"
+        );
+    }
+
+    #[test]
+    fn aligns_interpolated_between_anchors() {
+        let tokens = FakeTokens::new(&["a", "b", "c", "d"], &["a", "b", "", "c", "", "d"]);
+        let alignment = align(&tokens, &[(0, 0), (3, 5)], 0).unwrap();
+
+        // TTL words 1..3 are interpolated onto ANNIS nodes 1..5
+        assert_eq!(alignment.pairs, [(0, 0), (1, 1), (2, 3), (3, 5)]);
+        assert!(alignment.skipped.is_empty());
+        assert!(alignment.conflicts.is_empty());
+    }
+
+    #[test]
+    fn records_conflicts_when_interpolating() {
+        let tokens = FakeTokens::new(&["a", "b", "c", "d"], &["a", "x", "c", "d"]);
+        let alignment = align(&tokens, &[(0, 0), (3, 3)], 0).unwrap();
+
+        assert_eq!(alignment.pairs, [(0, 0), (1, 1), (2, 2), (3, 3)]);
+        assert_eq!(conflict_positions(&alignment), [(1, 1)]);
+    }
+
+    #[test]
+    fn fails_to_interpolate_without_annis_nodes() {
+        let tokens = FakeTokens::new(&["a", "b", "c"], &["a", "c"]);
+        let err = align(&tokens, &[(0, 0), (2, 1)], 0).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "ttl node w1 has no counterpart in ANNIS between alignment anchors"
+        );
+    }
+}
@@ -1,5 +1,16 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Deref;
+use std::sync::Arc;
 
+use anyhow::anyhow;
+use graphannis::corpusstorage::{CorpusInfo, ResultOrder, SearchQuery};
+use graphannis::model::AnnotationComponent;
+use graphannis::AnnotationGraph;
+use graphannis_core::annostorage::ValueSearch;
+use graphannis_core::graph::{ANNIS_NS, NODE_NAME_KEY, NODE_TYPE_KEY};
+use graphannis_core::types::{AnnoKey, Edge, NodeID};
+use itertools::Itertools;
 use tempfile::TempDir;
 
 pub(crate) struct TempStorage {
@@ -26,3 +37,386 @@ impl Deref for TempStorage {
         &self.storage
     }
 }
+
+/// Shared handle to a [`TempStorage`], from which inbound corpora obtain a read-only
+/// [`StorageView`] and outbound corpora a [`StorageSession`] modifying the storage
+///
+/// The handle can be sent to other threads, since the underlying storage synchronizes access
+/// internally.
+#[derive(Clone)]
+pub(crate) struct StorageHandle {
+    storage: Arc<TempStorage>,
+}
+
+impl StorageHandle {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            storage: Arc::new(TempStorage::new()?),
+        })
+    }
+
+    /// Read-only view of the storage
+    pub(crate) fn view(&self) -> StorageView {
+        StorageView {
+            storage: Arc::clone(&self.storage),
+        }
+    }
+
+    /// Session with full access to the storage, e.g. for importing or updating corpora
+    pub(crate) fn session(&self) -> StorageSession {
+        StorageSession {
+            storage: Arc::clone(&self.storage),
+        }
+    }
+}
+
+/// Read-only view of a storage, see [`StorageHandle::view`]
+#[derive(Clone)]
+pub(crate) struct StorageView {
+    storage: Arc<TempStorage>,
+}
+
+impl StorageView {
+    pub(crate) fn info(&self, corpus_name: &str) -> anyhow::Result<CorpusInfo> {
+        Ok(self.storage.info(corpus_name)?)
+    }
+
+    pub(crate) fn find(
+        &self,
+        query: SearchQuery<'_, &str>,
+        offset: usize,
+        limit: Option<usize>,
+        order: ResultOrder,
+    ) -> anyhow::Result<Vec<String>> {
+        Ok(self.storage.find(query, offset, limit, order)?)
+    }
+
+    pub(crate) fn subcorpus_graph(
+        &self,
+        corpus_name: &str,
+        node_names: Vec<String>,
+    ) -> anyhow::Result<AnnotationGraph> {
+        Ok(self.storage.subcorpus_graph(corpus_name, node_names)?)
+    }
+
+    /// See [`node_anno_keys`]
+    pub(crate) fn node_anno_keys(&self, corpus_name: &str) -> anyhow::Result<BTreeSet<AnnoKey>> {
+        node_anno_keys(&self.storage, corpus_name)
+    }
+
+    /// See [`node_anno_frequencies`]
+    pub(crate) fn node_anno_frequencies(
+        &self,
+        corpus_name: &str,
+    ) -> anyhow::Result<BTreeMap<String, u64>> {
+        node_anno_frequencies(&self.storage, corpus_name)
+    }
+}
+
+/// Session with full access to a storage, see [`StorageHandle::session`]
+pub(crate) struct StorageSession {
+    storage: Arc<TempStorage>,
+}
+
+impl Deref for StorageSession {
+    type Target = graphannis::CorpusStorage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.storage
+    }
+}
+
+/// Node annotation keys of the given corpus, excluding the internal keys of the `annis` namespace
+pub(crate) fn node_anno_keys(
+    storage: &graphannis::CorpusStorage,
+    corpus_name: &str,
+) -> anyhow::Result<BTreeSet<AnnoKey>> {
+    Ok(storage
+        .list_node_annotations(corpus_name, false, false)?
+        .into_iter()
+        .map(|anno| anno.key)
+        .filter(|anno_key| anno_key.ns != ANNIS_NS)
+        .collect())
+}
+
+/// Number of nodes carrying each node annotation key (given as `ns:name`) in the given corpus,
+/// excluding the internal keys of the `annis` namespace
+pub(crate) fn node_anno_frequencies(
+    storage: &graphannis::CorpusStorage,
+    corpus_name: &str,
+) -> anyhow::Result<BTreeMap<String, u64>> {
+    let anno_keys = node_anno_keys(storage, corpus_name)?;
+    let mut counts: BTreeMap<&AnnoKey, u64> = BTreeMap::new();
+
+    CorpusGraph::new(storage, corpus_name)?.for_each_part(|part| {
+        for anno_key in &anno_keys {
+            *counts.entry(anno_key).or_default() +=
+                part.node_names_with_anno(anno_key)?.len() as u64;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(counts
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .map(|(anno_key, count)| (format!("{}:{}", anno_key.ns, anno_key.name), count))
+        .collect())
+}
+
+/// Nodes and edges of a corpus, for looking up annotations by their exact key, which is not
+/// possible in AQL for all keys, e.g. names containing dots
+///
+/// graphannis gives no read access to the annotation storage of a stored corpus, so the lookups
+/// run on copies of its parts (see [`CorpusGraph::for_each_part`]): the corpus structure, which
+/// contains the corpus, subcorpus and document nodes, and the graph of each document. Only one
+/// document graph is held in memory at a time.
+pub(crate) struct CorpusGraph<'a> {
+    storage: &'a graphannis::CorpusStorage,
+    corpus_name: &'a str,
+    structure: AnnotationGraph,
+    doc_node_names: Vec<String>,
+}
+
+impl<'a> CorpusGraph<'a> {
+    pub(crate) fn new(
+        storage: &'a graphannis::CorpusStorage,
+        corpus_name: &'a str,
+    ) -> anyhow::Result<Self> {
+        let structure = storage.corpus_graph(corpus_name)?;
+        let node_annos = structure.get_node_annos();
+
+        let doc_node_names = node_annos
+            .exact_anno_search(Some(ANNIS_NS), "doc", ValueSearch::Any)
+            .map(|m| node_name(&structure, m?.node))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            storage,
+            corpus_name,
+            structure,
+            doc_node_names,
+        })
+    }
+
+    /// Calls `f` with the corpus structure and then with the graph of each document, where each
+    /// node and edge of the corpus below the top-level corpus node is part of exactly one of them
+    /// (nodes outside of documents other than corpus nodes, e.g. linked files, are left out)
+    pub(crate) fn for_each_part(
+        &self,
+        mut f: impl FnMut(&CorpusGraphPart<'_>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        f(&CorpusGraphPart {
+            graph: &self.structure,
+            corpus_nodes: true,
+        })?;
+
+        for doc_node_name in &self.doc_node_names {
+            let graph = self
+                .storage
+                .subcorpus_graph(self.corpus_name, vec![doc_node_name.clone()])?;
+
+            f(&CorpusGraphPart {
+                graph: &graph,
+                corpus_nodes: false,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Part of a [`CorpusGraph`], i.e. either the corpus structure, of which only the corpus nodes are
+/// considered, or the graph of a document, of which only the nodes other than corpus nodes are
+/// considered
+pub(crate) struct CorpusGraphPart<'g> {
+    graph: &'g AnnotationGraph,
+    corpus_nodes: bool,
+}
+
+impl CorpusGraphPart<'_> {
+    /// Names of the nodes carrying the given annotation key
+    pub(crate) fn node_names_with_anno(&self, anno_key: &AnnoKey) -> anyhow::Result<Vec<String>> {
+        let node_annos = self.graph.get_node_annos();
+        let mut node_names = Vec::new();
+
+        for m in node_annos.exact_anno_search(Some(&anno_key.ns), &anno_key.name, ValueSearch::Any)
+        {
+            let node_id = m?.node;
+            let is_corpus_node = node_annos
+                .get_value_for_item(&node_id, &NODE_TYPE_KEY)?
+                .is_some_and(|node_type| node_type == "corpus");
+
+            if is_corpus_node == self.corpus_nodes {
+                node_names.push(node_name(self.graph, node_id)?);
+            }
+        }
+
+        Ok(node_names)
+    }
+
+    /// Source and target node names of the edges of the given component, together with the keys
+    /// of the annotations of each edge
+    pub(crate) fn edges_with_annos(
+        &self,
+        component: &AnnotationComponent,
+    ) -> anyhow::Result<Vec<(String, String, Vec<AnnoKey>)>> {
+        let Some(graph_storage) = self.graph.get_graphstorage_as_ref(component) else {
+            return Ok(Vec::new());
+        };
+
+        let mut edges = Vec::new();
+
+        for source in graph_storage.source_nodes() {
+            let source = source?;
+
+            for target in graph_storage.get_outgoing_edges(source) {
+                let target = target?;
+
+                let anno_keys = graph_storage
+                    .get_anno_storage()
+                    .get_all_keys_for_item(&Edge { source, target }, None, None)?
+                    .iter()
+                    .map(|anno_key| AnnoKey::clone(anno_key))
+                    .collect_vec();
+
+                if !anno_keys.is_empty() {
+                    edges.push((
+                        node_name(self.graph, source)?,
+                        node_name(self.graph, target)?,
+                        anno_keys,
+                    ));
+                }
+            }
+        }
+
+        Ok(edges)
+    }
+}
+
+fn node_name(graph: &AnnotationGraph, node_id: NodeID) -> anyhow::Result<String> {
+    graph
+        .get_node_annos()
+        .get_value_for_item(&node_id, &NODE_NAME_KEY)?
+        .map(Cow::into_owned)
+        .ok_or_else(|| anyhow!("node {node_id} has no annis:node_name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use graphannis::update::{GraphUpdate, UpdateEvent};
+
+    use super::*;
+
+    fn storage_with_corpus() -> anyhow::Result<TempStorage> {
+        let storage = TempStorage::new()?;
+
+        let mut update = GraphUpdate::new();
+
+        for (node_name, node_type) in [
+            ("corpus", "corpus"),
+            ("corpus/doc", "corpus"),
+            ("corpus/doc#n1", "node"),
+            ("corpus/doc#n2", "node"),
+        ] {
+            update.add_event(UpdateEvent::AddNode {
+                node_name: node_name.into(),
+                node_type: node_type.into(),
+            })?;
+        }
+
+        for (source_node, target_node) in [
+            ("corpus/doc", "corpus"),
+            ("corpus/doc#n1", "corpus/doc"),
+            ("corpus/doc#n2", "corpus/doc"),
+        ] {
+            update.add_event(UpdateEvent::AddEdge {
+                source_node: source_node.into(),
+                target_node: target_node.into(),
+                layer: ANNIS_NS.into(),
+                component_type: "PartOf".into(),
+                component_name: String::new(),
+            })?;
+        }
+
+        for (node_name, anno_ns, anno_name) in [
+            ("corpus", "meta", "source"),
+            ("corpus/doc#n1", "default_ns", "infl.case"),
+            ("corpus/doc#n2", "default_ns", "infl.case"),
+            ("corpus/doc#n1", "", "infl.case"),
+            ("corpus/doc#n2", "other", "infl"),
+        ] {
+            update.add_event(UpdateEvent::AddNodeLabel {
+                node_name: node_name.into(),
+                anno_ns: anno_ns.into(),
+                anno_name: anno_name.into(),
+                anno_value: "x".into(),
+            })?;
+        }
+
+        update.add_event(UpdateEvent::AddNodeLabel {
+            node_name: "corpus/doc".into(),
+            anno_ns: ANNIS_NS.into(),
+            anno_name: "doc".into(),
+            anno_value: "doc".into(),
+        })?;
+
+        storage.apply_update("corpus", &mut update)?;
+
+        Ok(storage)
+    }
+
+    #[test]
+    fn node_anno_frequencies_counts_by_exact_key() -> anyhow::Result<()> {
+        let storage = storage_with_corpus()?;
+
+        assert_eq!(
+            node_anno_frequencies(&storage, "corpus")?,
+            BTreeMap::from([
+                (":infl.case".into(), 1),
+                ("default_ns:infl.case".into(), 2),
+                ("meta:source".into(), 1),
+                ("other:infl".into(), 1),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn corpus_graph_finds_nodes_by_exact_key() -> anyhow::Result<()> {
+        let storage = storage_with_corpus()?;
+        let graph = CorpusGraph::new(&storage, "corpus")?;
+
+        let node_names = |ns: &str, name: &str| -> anyhow::Result<BTreeSet<String>> {
+            let anno_key = AnnoKey {
+                ns: ns.into(),
+                name: name.into(),
+            };
+            let mut node_names = BTreeSet::new();
+
+            graph.for_each_part(|part| {
+                node_names.extend(part.node_names_with_anno(&anno_key)?);
+                Ok(())
+            })?;
+
+            Ok(node_names)
+        };
+
+        assert_eq!(
+            node_names("default_ns", "infl.case")?,
+            BTreeSet::from(["corpus/doc#n1".into(), "corpus/doc#n2".into()])
+        );
+        assert_eq!(
+            node_names("", "infl.case")?,
+            BTreeSet::from(["corpus/doc#n1".into()])
+        );
+        assert_eq!(
+            node_names("meta", "source")?,
+            BTreeSet::from(["corpus".into()])
+        );
+        assert!(node_names("default_ns", "infl")?.is_empty());
+
+        Ok(())
+    }
+}
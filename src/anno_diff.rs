@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use itertools::Itertools;
+use serde::Serialize;
+use tracing::info;
+
+/// Differences between the node annotation frequencies (per `ns:name`) of a corpus before and
+/// after the conversion
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct AnnoDiff {
+    /// Annotation keys whose frequency increased, with the frequencies before and after
+    added: BTreeMap<String, (u64, u64)>,
+    /// Annotation keys whose frequency decreased, with the frequencies before and after
+    lost: BTreeMap<String, (u64, u64)>,
+    /// Annotation keys whose frequency decreased because they were deliberately stripped, with the
+    /// frequencies before and after
+    stripped: BTreeMap<String, (u64, u64)>,
+}
+
+impl AnnoDiff {
+    /// Compares the given frequencies, where `is_stripped` tells whether annotations of the given
+    /// key (given as `ns:name`) were deliberately deleted
+    pub(crate) fn new(
+        before: &BTreeMap<String, u64>,
+        after: &BTreeMap<String, u64>,
+        is_stripped: impl Fn(&str) -> bool,
+    ) -> Self {
+        let mut anno_diff = Self::default();
+
+        for anno_key in before.keys().chain(after.keys()).unique() {
+            let before = before.get(anno_key).copied().unwrap_or(0);
+            let after = after.get(anno_key).copied().unwrap_or(0);
+
+            if after > before {
+                anno_diff.added.insert(anno_key.clone(), (before, after));
+            } else if after < before {
+                if is_stripped(anno_key) {
+                    anno_diff.stripped.insert(anno_key.clone(), (before, after));
+                } else {
+                    anno_diff.lost.insert(anno_key.clone(), (before, after));
+                }
+            }
+        }
+
+        anno_diff
+    }
+
+    /// Logs the added and stripped annotations and fails if any existing annotations were lost
+    pub(crate) fn check(&self, corpus_name: &str) -> anyhow::Result<()> {
+        for (anno_key, &(before, after)) in &self.added {
+            info!(
+                corpus_name,
+                anno_key,
+                before,
+                after,
+                added = after - before,
+                "added annotations"
+            );
+        }
+
+        for (anno_key, &(before, after)) in &self.stripped {
+            info!(
+                corpus_name,
+                anno_key,
+                before,
+                after,
+                stripped = before - after,
+                "stripped annotations"
+            );
+        }
+
+        if !self.lost.is_empty() {
+            bail!(
+                "annotations of corpus {corpus_name} were lost: {}",
+                self.lost
+                    .iter()
+                    .map(|(anno_key, (before, after))| format!("{anno_key} ({before} -> {after})"))
+                    .format(", ")
+            );
+        }
+
+        info!(corpus_name, "no annotations lost");
+
+        Ok(())
+    }
+}
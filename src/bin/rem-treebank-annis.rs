@@ -0,0 +1,3 @@
+fn main() {
+    rem_treebank_annis::main();
+}
@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+/// Location within the conversion that is currently being processed, used to prefix errors with
+/// the corpus, document and TTL file they occurred in, e.g.
+/// `corpus REM, document M001-N1, ttl file ttl/M001-N1_1.ttl: ...`
+#[derive(Default)]
+pub(crate) struct Breadcrumbs {
+    corpus: Option<String>,
+    document: Option<String>,
+    ttl_file: Option<PathBuf>,
+}
+
+impl Breadcrumbs {
+    pub(crate) fn enter_corpus(&mut self, corpus_name: &str) {
+        *self = Self {
+            corpus: Some(corpus_name.into()),
+            ..Self::default()
+        };
+    }
+
+    pub(crate) fn enter_document(&mut self, doc_name: &str) {
+        self.document = Some(doc_name.into());
+        self.ttl_file = None;
+    }
+
+    pub(crate) fn enter_ttl_file(&mut self, path: &Path) {
+        self.ttl_file = Some(path.into());
+    }
+
+    /// Leaves the current document, so that later errors are attributed to the corpus only
+    pub(crate) fn leave_document(&mut self) {
+        self.document = None;
+        self.ttl_file = None;
+    }
+
+    /// Leaves the current corpus, so that later errors are not attributed to any location
+    pub(crate) fn leave_corpus(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Prefixes the given error with the current location, if any
+    pub(crate) fn wrap(&self, err: anyhow::Error) -> anyhow::Error {
+        let location = [
+            self.corpus.as_ref().map(|name| format!("corpus {name}")),
+            self.document
+                .as_ref()
+                .map(|name| format!("document {name}")),
+            self.ttl_file
+                .as_ref()
+                .map(|path| format!("ttl file {}", path.display())),
+        ]
+        .into_iter()
+        .flatten()
+        .join(", ");
+
+        if location.is_empty() {
+            err
+        } else {
+            anyhow!("{location}: {err:#}")
+        }
+    }
+}
@@ -0,0 +1,4687 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{mpsc, Once};
+use std::thread;
+
+use anyhow::{anyhow, bail, ensure};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use itertools::Itertools;
+use regex::Regex;
+use serde::Serialize;
+use tempfile::{NamedTempFile, TempDir};
+use time::OffsetDateTime;
+use tracing::{error, field, info, info_span, Span};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::{
+    align, anno_diff, breadcrumbs, coverage, doc_map, features, inbound, list, node_name, outbound,
+    relabel, rem, remote, report, telemetry, tree_builder, tree_stats, warnings, SUMMARY_TARGET,
+};
+
+mod document;
+mod overrides;
+mod tui;
+
+pub use document::convert_document;
+
+/// Converts the Treebank edition of the Referenzkorpus Mittelhochdeutsch (ReM) into the ANNIS
+/// format
+#[derive(Clone, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to input corpora, must be a .zip file containing the ReM in the relANNIS or GraphML
+    /// format, or `-` to read it from stdin, or an `s3://` or `https://` URL (requires the `remote`
+    /// feature)
+    #[arg(
+        value_name = "INPUT ANNIS ZIP",
+        required_unless_present = "version_info"
+    )]
+    input_annis: Option<PathBuf>,
+
+    /// Path to input treebank data, must be a directory containing the treebank data in the Turtle
+    /// (.ttl) format
+    #[arg(
+        value_name = "INPUT TTL DIRECTORY",
+        required_unless_present = "version_info"
+    )]
+    input_ttl: Option<PathBuf>,
+
+    /// Path to output corpus, will be a .zip file containing the merged corpus in the
+    /// GraphML format, or `-` to write it to stdout, or an `s3://` or `https://` URL (requires the
+    /// `remote` feature) [default: like input corpus, but with `.out.zip` extension, or `-` if the
+    /// input corpus is read from stdin]
+    #[arg(long, value_name = "ANNIS ZIP")]
+    output: Option<PathBuf>,
+
+    /// Template for the file name of the output corpus if `--output` is not given, which is placed
+    /// next to the input corpus (or in the current directory if the input corpus is read from stdin
+    /// or remote). May contain the placeholders `{stem}` (file name of the input corpus without
+    /// extension), `{date}` (current date), `{version}` (version of this tool) and `{profile}`
+    /// (name of the profile), e.g. `{stem}_{date}_treebank.zip`
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "output")]
+    output_template: Option<OutputTemplate>,
+
+    /// Whether to wait for another conversion writing the same output to finish rather than
+    /// failing right away
+    #[arg(long, default_value = "false")]
+    wait: bool,
+
+    /// If specified, only process the corpus of this name, leaving out all other corpora from the
+    /// output, can be repeated
+    #[arg(long = "corpus", value_name = "NAME")]
+    corpora: Vec<String>,
+
+    /// If specified, rename corpora using this pattern
+    /// Must contain the placeholder `%c` representing the original corpus name, e.g. `%c_treebank`
+    /// May contain `%d` (current date), `%v` (version of this tool) and `%%` (literal `%`)
+    /// This facilitates importing the original and new corpora into the same ANNIS data directory
+    #[arg(long, value_name = "PATTERN")]
+    rename: Option<RenamePattern>,
+
+    /// Whether to proceed with renaming without asking for confirmation, which is required when
+    /// not running in a terminal
+    #[arg(long, default_value = "false")]
+    yes: bool,
+
+    /// If specified, override the settings `layer`, `tree_anno`, `rename`, `max_edge_depth` and
+    /// `discontinuous` for the corpora matching the sections of this TOML file
+    #[arg(long, value_name = "FILE")]
+    corpus_overrides: Option<PathBuf>,
+
+    /// Template for the node names of treebank nonterminals
+    /// Must start with the placeholder `{doc}` representing the node name of the document and may
+    /// contain the placeholders `{sentence}` (1-based sentence index), `{segment}` (last segment of
+    /// the TTL node IRI, percent-encoded) and `{counter}` (running counter within the document)
+    #[arg(long, default_value = "{doc}#{segment}", value_name = "TEMPLATE")]
+    node_name_template: NodeNameTemplate,
+
+    /// Rule for extracting the `{segment}` placeholder from the TTL node IRI (`fragment`, `path` or
+    /// `query:PARAM`), can be repeated to try the rules in order [default: fragment, path]
+    #[arg(long, value_name = "RULE")]
+    iri_segment: Vec<IriSegmentRule>,
+
+    /// Whether to rename nonterminal nodes whose names collide with existing or other new nodes by
+    /// appending a suffix rather than failing
+    #[arg(long, default_value = "false")]
+    dedupe_node_names: bool,
+
+    /// Layer of the treebank nodes, also used as the namespace of their annotations unless
+    /// `--anno-ns` is specified
+    #[arg(long, default_value = "treebank", value_name = "TREE LAYER")]
+    layer: String,
+
+    /// Namespace of the treebank annotations (the tree annotation and the IRI annotation), defaults
+    /// to the layer of the respective node
+    #[arg(long, value_name = "NS")]
+    anno_ns: Option<String>,
+
+    /// Delete all node and edge annotations whose keys (given as `[NS:]NAME`, where `*` and `?` can
+    /// be used as wildcards) match this pattern from the output, e.g. `annotation:comment`, can be
+    /// repeated
+    #[arg(long, value_name = "PATTERN")]
+    strip_anno: Vec<outbound::annis::AnnoKeyPattern>,
+
+    /// Name of the treebank annotation
+    #[arg(long, default_value = "tree", value_name = "TREE ANNO")]
+    tree_anno: String,
+
+    /// Display name for the ANNIS tree visualizer
+    #[arg(long, default_value = "tree", value_name = "TREE DISPLAY")]
+    tree_display: String,
+
+    /// Whether to skip adding tree visualizers to the corpus config, leaving the exported config
+    /// untouched
+    #[arg(long, default_value = "false")]
+    no_visualizer: bool,
+
+    /// Set the mapping `KEY` to `VALUE` for each tree visualizer added to the corpus config,
+    /// overriding the default mappings, can be repeated
+    #[arg(long, value_name = "KEY=VALUE", conflicts_with = "no_visualizer")]
+    tree_mapping: Vec<VisualizerMapping>,
+
+    /// Set the value at the given dotted key (e.g. `view.page_size=20`) in the corpus config, can
+    /// be repeated. The value is parsed as a TOML value if possible, otherwise it is taken as a
+    /// string.
+    #[arg(long, value_name = "KEY=VALUE")]
+    set_config: Vec<ConfigSetting>,
+
+    /// If specified, route treebank nodes into different layers depending on the value (a literal or
+    /// an IRI) of this predicate (an IRI), see `--route`
+    #[arg(long, value_name = "IRI")]
+    route_predicate: Option<String>,
+
+    /// Route treebank nodes whose route predicate has the value `VALUE` into the layer `LAYER`
+    /// instead of `--layer`, using the annotation name `ANNO` instead of `--tree-anno` if
+    /// specified, can be repeated. Each layer gets its own tree visualizer.
+    #[arg(long, value_name = "VALUE=LAYER[/ANNO]", requires = "route_predicate")]
+    route: Vec<Route>,
+
+    /// If specified, add an annotation of this name to each node containg the IRI of the
+    /// corresponding TTL node where applicable
+    #[arg(long, value_name = "IRI ANNO")]
+    iri_anno: Option<String>,
+
+    /// Kinds of nodes to which the annotation given by `--iri-anno` is added
+    #[arg(long, value_enum, default_value_t = IriTargets::Both, requires = "iri_anno")]
+    iri_anno_on: IriTargets,
+
+    /// Preferred language tag (e.g. `gmh`) of TTL literals, can be repeated in order of
+    /// preference. If specified, literals with other language tags are ignored.
+    #[arg(long, value_name = "TAG")]
+    literal_lang: Vec<String>,
+
+    /// If specified, pipe each TTL file through this shell command (e.g. `sed 's/foo/bar/'`)
+    /// before parsing it, which receives the file on stdin and writes the TTL to be parsed to stdout
+    #[arg(long, value_name = "CMD")]
+    pre_process: Option<String>,
+
+    /// Maximum number of malformed statements to skip in each TTL file in order to parse the rest
+    /// of the file, each reported as a warning with its line and column. If `0`, TTL files with
+    /// syntax errors are not used at all.
+    #[arg(long, default_value = "0", value_name = "N")]
+    skip_malformed_ttl: usize,
+
+    /// If specified, convert at most this number of selected documents per corpus, e.g. to try
+    /// out options on a large corpus. The remaining documents are neither queried nor loaded.
+    #[arg(long, value_name = "N")]
+    limit_docs: Option<usize>,
+
+    /// If specified, use the TTL files of the documents given in this CSV file (e.g. written by
+    /// the `match-docs` subcommand) for the ANNIS documents mapped to them rather than the TTL
+    /// files named after the ANNIS documents themselves
+    #[arg(long, value_name = "CSV")]
+    doc_map: Option<PathBuf>,
+
+    /// If specified, add an annotation of this name containing the language tag of the `WORD`
+    /// literal to the segmentation node of each word and of the `CAT` literal to each nonterminal
+    #[arg(long, value_name = "LANG ANNO")]
+    lang_anno: Option<String>,
+
+    /// If specified, strip this prefix from the IRIs stored in the annotations given by
+    /// `--iri-anno` and store it once in an annotation of the same name on the corpus node instead
+    #[arg(long, value_name = "PREFIX", requires = "iri_anno")]
+    iri_prefix: Option<String>,
+
+    /// Whether to strip the prefix common to the IRIs of each document (e.g. up to the `#`) from
+    /// the IRIs stored in the annotations given by `--iri-anno` and store it once in an annotation
+    /// of the same name on the document node instead
+    #[arg(
+        long,
+        default_value = "false",
+        requires = "iri_anno",
+        conflicts_with = "iri_prefix"
+    )]
+    document_iri_prefix: bool,
+
+    /// If specified, write the mapping from ANNIS node names to the IRIs of the corresponding TTL
+    /// nodes as a TSV file of this name linked to the corpus node
+    #[arg(long, value_name = "FILE NAME")]
+    iri_map_file: Option<String>,
+
+    /// If specified, exclude the trees of sentences whose root category matches this regex (which
+    /// must match the whole category) from being merged
+    #[arg(long, value_name = "REGEX")]
+    filter_cat: Option<CatFilter>,
+
+    /// Exclude the trees of sentences with fewer than this number of words from being merged
+    #[arg(long, default_value = "0", value_name = "N")]
+    min_sentence_length: usize,
+
+    /// If specified, add an annotation of this name to each created tree node containing the 1-based
+    /// index of its sentence within the document
+    #[arg(long, value_name = "NAME")]
+    sentence_number_anno: Option<String>,
+
+    /// If specified, add an annotation of this name to each created tree node containing the IRI of
+    /// its `nif:Sentence` in the TTL file
+    #[arg(long, value_name = "NAME")]
+    sentence_iri_anno: Option<String>,
+
+    /// If specified, add annotations `depth` (length of the longest path down to a terminal) and
+    /// `yield` (number of terminals) in this namespace to each created nonterminal node
+    #[arg(long, value_name = "NS")]
+    tree_stats_ns: Option<String>,
+
+    /// Whether to (re)write the token annotation (see `--tok-anno`) of each segmentation node
+    /// aligned with a TTL word from the `WORD` value of the word, e.g. when it is empty in the input
+    #[arg(long, default_value = "false")]
+    rebuild_tok: bool,
+
+    /// Key of the token annotation written with `--rebuild-tok`, given as `NS:NAME`
+    #[arg(long, default_value = "annis:tok", value_name = "NS:NAME")]
+    tok_anno: QualifiedName,
+
+    /// Overwrite this annotation of each segmentation node aligned with a TTL word (with the key
+    /// given by the profile) with the value of the word, can be repeated
+    #[arg(long, value_enum, value_name = "ANNO")]
+    overwrite_token_anno: Vec<TokenAnno>,
+
+    /// If specified, record the source of each annotation overwritten with `--overwrite-token-anno`
+    /// whose previous value differs, in an annotation named like it with this suffix, e.g. `_src`
+    /// for `annotation:lemma_src=ttl`
+    #[arg(long, value_name = "SUFFIX", requires = "overwrite_token_anno")]
+    source_anno_suffix: Option<String>,
+
+    /// If specified, keep the previous value of each annotation overwritten with
+    /// `--overwrite-token-anno` that differs, in an annotation named like it with this suffix, e.g.
+    /// `_annis` for `annotation:lemma_annis`
+    #[arg(long, value_name = "SUFFIX", requires = "overwrite_token_anno")]
+    other_value_suffix: Option<String>,
+
+    /// Whether to merge the treebank layer into the input corpora or to write it as standalone
+    /// corpora, which contain only the aligned tokens and are linked to the input corpora only by
+    /// the node names of the tokens
+    #[arg(long, value_enum, default_value_t = Mode::Merge)]
+    mode: Mode,
+
+    /// Treebank convention determining how the TTL trees are converted
+    #[arg(long, value_enum, default_value_t = tree_builder::Convention::Constituency)]
+    tree_convention: tree_builder::Convention,
+
+    /// If specified, relabel the categories of the nonterminals with the ordered regex rules from
+    /// this TOML file
+    #[arg(long, value_name = "FILE")]
+    relabel: Option<PathBuf>,
+
+    /// If specified, validate the categories of the nonterminals against the allowed categories
+    /// from this TOML file, reporting violations as warnings
+    #[arg(long, value_name = "FILE")]
+    tagset: Option<PathBuf>,
+
+    /// Whether to fail without finishing the output if any category is not in the tagset (see
+    /// `--tagset`)
+    #[arg(long, default_value = "false", requires = "tagset")]
+    strict: bool,
+
+    /// If specified, split the `INFL` values of TTL nodes into separate annotations for their
+    /// morphological features (e.g. `infl.case`) according to the rule in this TOML file
+    #[arg(long, value_name = "FILE")]
+    infl_features: Option<PathBuf>,
+
+    /// If specified, merge each chain of nonterminals with a single child into one node whose
+    /// category consists of the categories along the chain, separated by `SEPARATOR` (`+` if not
+    /// given)
+    #[arg(
+        long,
+        value_name = "SEPARATOR",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "+"
+    )]
+    collapse_unary: Option<String>,
+
+    /// How to handle TTL nodes with more than one parent
+    #[arg(long, value_enum, default_value_t = inbound::ttl::MultiParentPolicy::First)]
+    multi_parent: inbound::ttl::MultiParentPolicy,
+
+    /// How to handle TTL edges from a child to a parent in a different sentence
+    #[arg(long, value_enum, default_value_t = CrossSentencePolicy::Drop)]
+    cross_sentence: CrossSentencePolicy,
+
+    /// If specified, fail if the path from any treebank node up to its root consists of more than
+    /// this number of edges, e.g. due to a runaway chain of parents in the TTL data
+    #[arg(long, value_name = "N")]
+    max_edge_depth: Option<usize>,
+
+    /// If specified, check whether the yield of each treebank node, i.e. the words it dominates,
+    /// forms a contiguous range of words, which the ANNIS tree visualizer requires
+    #[arg(long, value_enum, value_name = "POLICY")]
+    discontinuous: Option<DiscontinuityPolicy>,
+
+    /// Annotation (given as `[NS:]NAME`) marking treebank nodes with discontinuous yields if
+    /// `--discontinuous=mark` or `--discontinuous=allow` is given
+    #[arg(long, value_name = "NS:NAME", default_value = "discontinuous")]
+    discontinuous_anno: QualifiedName,
+
+    /// If specified, keep empty elements (e.g. traces), i.e. TTL nodes other than words without
+    /// children, as treebank nodes covering no tokens and mark them with this annotation (given as
+    /// `[NS:]NAME`)
+    #[arg(long, value_name = "NS:NAME")]
+    empty_element_anno: Option<QualifiedName>,
+
+    /// Order of the matches of the queries on the output corpora, which determines the order of
+    /// the update events when adding PartOf edges, stripping annotations and renaming
+    #[arg(long, value_enum, default_value_t = outbound::annis::QueryResultOrder::Sorted)]
+    result_order: outbound::annis::QueryResultOrder,
+
+    /// Target of the PartOf edges from the treebank nodes
+    #[arg(long, value_enum, default_value_t = PartOfTarget::Datasource)]
+    partof_target: PartOfTarget,
+
+    /// How to handle corpora in which no document matches a TTL file, which would be written
+    /// unchanged
+    #[arg(long, value_enum, default_value_t = UntouchedPolicy::Warn)]
+    untouched: UntouchedPolicy,
+
+    /// Whether to copy corpora in which no document matches a TTL file from the input ZIP file
+    /// into the output as they are rather than exporting them, which only applies to corpora
+    /// imported from GraphML and not in combination with `--mode=standalone` or `--rename`, and
+    /// which cannot be combined with options modifying all corpora such as `--strip-anno`
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = [
+            "strip_anno",
+            "set_config",
+            "metadata",
+            "iri_prefix",
+            "post_process",
+            "verify_graphml",
+        ]
+    )]
+    copy_unmatched_corpora: bool,
+
+    /// If specified, write a JSON sidecar file for each document into this directory, containing
+    /// the mapping between ANNIS tokens and TTL words, the sentence boundaries and statistics
+    /// about the merged trees
+    #[arg(long, value_name = "PATH")]
+    sidecar_dir: Option<PathBuf>,
+
+    /// If specified, write a CSV table to this file linking the ANNIS nodes aligned with TTL words
+    /// and the created treebank nodes to the corresponding nodes of the output corpora and the IRIs
+    /// of the TTL nodes, e.g. to query standalone corpora jointly with the input corpora
+    #[arg(long, value_name = "PATH")]
+    link_table: Option<PathBuf>,
+
+    /// Whether to store temporary ANNIS corpus graphs in memory rather than on disk.
+    /// Running with this flag is faster, but can fail if there is not enough memory to fit the
+    /// corpus graphs.
+    #[arg(long, default_value = "false")]
+    in_memory: bool,
+
+    /// If specified, store the temporary graph of each ANNIS corpus in memory if its files
+    /// (uncompressed within the input ZIP file) are at most this large, and on disk otherwise
+    #[arg(long, value_name = "MB", conflicts_with = "in_memory")]
+    memory_threshold_mb: Option<u64>,
+
+    /// Maximum number of corpora compressed concurrently into the output ZIP file. With more than
+    /// one thread, each corpus is compressed in the background while the next one is converted and
+    /// appended to the output in order once finished.
+    #[arg(long, default_value = "1", value_name = "N")]
+    zip_threads: usize,
+
+    /// Maximum number of threads used for determining trees and pre-validating documents as well
+    /// as by graphannis for executing queries [default: number of CPUs available to the process,
+    /// which respects cgroup CPU limits]
+    #[arg(long, value_name = "N")]
+    threads: Option<NonZeroUsize>,
+
+    /// Size in MiB from which GraphML files of the exported corpora are streamed from disk while
+    /// writing the output rather than read into memory
+    #[arg(
+        long,
+        default_value_t = outbound::annis::DEFAULT_STREAM_THRESHOLD_MB,
+        value_name = "MB"
+    )]
+    stream_threshold_mb: u64,
+
+    /// If specified, stop after the given phase of the conversion without writing the output, e.g.
+    /// for debugging together with `--artifacts-dir`
+    #[arg(long, value_enum, value_name = "PHASE")]
+    stop_after: Option<Phase>,
+
+    /// If specified, write intermediate artifacts of the conversion phases (the imported documents,
+    /// the alignments and the generated graph updates) to this directory for inspection
+    #[arg(long, value_name = "DIR")]
+    artifacts_dir: Option<PathBuf>,
+
+    /// If specified, write all graph update events generated for the corpora to this file, which
+    /// can be replayed with the `apply-updates` subcommand
+    #[arg(long, value_name = "PATH")]
+    dump_updates: Option<PathBuf>,
+
+    /// Whether to compare the node annotation frequencies of each corpus before and after the
+    /// conversion, failing if any existing annotations were lost and logging the added ones
+    #[arg(long, default_value = "false")]
+    anno_diff: bool,
+
+    /// Whether to check that the GraphML of each corpus is well-formed before writing it into the
+    /// output ZIP file, failing right away otherwise
+    #[arg(long, default_value = "false")]
+    verify_graphml: bool,
+
+    /// If specified, run the pipeline of external commands (e.g. annatto) from this TOML file on
+    /// the GraphML export of each corpus before writing it into the output ZIP file
+    #[arg(long, value_name = "SCRIPT")]
+    post_process: Option<PathBuf>,
+
+    /// How to determine the order of the segmentation nodes
+    #[arg(long, value_enum, default_value_t = inbound::annis::OrderingSource::Tokens)]
+    ordering: inbound::annis::OrderingSource,
+
+    /// If specified, only use these coverage components (given as `LAYER/NAME`) to determine the
+    /// segmentation nodes, can be repeated [default: all non-empty coverage components]
+    #[arg(long, value_name = "LAYER/NAME")]
+    coverage_component: Vec<inbound::annis::ComponentName>,
+
+    /// Never use this coverage component (given as `LAYER/NAME`) to determine the segmentation
+    /// nodes, can be repeated
+    #[arg(long, value_name = "LAYER/NAME")]
+    exclude_coverage_component: Vec<inbound::annis::ComponentName>,
+
+    /// Skip segmentation nodes matching this filter (given as `[NS:]NAME="VALUE"` or
+    /// `[NS:]NAME=/REGEX/`) when they have no counterpart in TTL, e.g. `pos="$_"` for punctuation
+    /// missing from the TTL tokenization, can be repeated
+    #[arg(long, value_name = "FILTER")]
+    skip_token: Vec<inbound::annis::TokenFilter>,
+
+    /// Maximum number of consecutive ANNIS segmentation nodes without counterpart in TTL tolerated
+    /// within a document, where the alignment re-synchronizes on the next node whose lemma and
+    /// normalized form match the current TTL word
+    #[arg(long, default_value = "0", value_name = "N")]
+    max_gap: usize,
+
+    /// Whether to look up, parse and align the TTL files of all selected documents in parallel
+    /// before applying any update, failing with a report of all problems at once
+    #[arg(long, default_value = "false")]
+    prevalidate: bool,
+
+    /// Profile of corpus-specific settings (name of the segmentation, keys of the annotations
+    /// compared during alignment and sanitization rules), given as `rem`, `ref`, `ren` or
+    /// `custom=FILE`
+    #[arg(long, default_value = "rem", value_name = "PROFILE")]
+    profile: rem::ProfileSource,
+
+    /// If specified, read the rules for sanitizing ANNIS annotation values before comparing them to
+    /// TTL values (characters to remove or replace) from this TOML file, overriding those of the
+    /// profile
+    #[arg(long, value_name = "FILE")]
+    sanitization: Option<PathBuf>,
+
+    /// If specified, read explicit alignment anchors between TTL words and ANNIS segmentation nodes
+    /// per document from this TOML file, the alignment between two anchors is interpolated
+    #[arg(long, value_name = "FILE")]
+    anchors: Option<PathBuf>,
+
+    /// If specified, read document-level metadata from this TOML file and add it as annotations to
+    /// the document nodes
+    #[arg(long, value_name = "FILE")]
+    metadata: Option<PathBuf>,
+
+    /// Namespace of the annotations added from `--metadata`, none by default
+    #[arg(long, value_name = "NS")]
+    metadata_ns: Option<String>,
+
+    /// If specified, annotate each merged document node with this annotation (given as
+    /// `[NS:]NAME`) recording the version of this tool, the date and the hash of the TTL file
+    #[arg(long, value_name = "NS:NAME")]
+    changelog_anno: Option<QualifiedName>,
+
+    /// Whether to log for each document which components are considered and which are ignored
+    #[arg(long, default_value = "false")]
+    list_components: bool,
+
+    /// Additionally export the treebank layer of each document in the given format (given as
+    /// `FORMAT=DIR`), can be repeated
+    #[arg(long, value_name = "FORMAT=DIR")]
+    also_export: Vec<AlsoExport>,
+
+    /// If specified, write the full list of warnings to this JSON file, while the log only contains
+    /// the first occurrence and the number of occurrences of each kind of warning
+    #[arg(long, value_name = "PATH")]
+    warnings_report: Option<PathBuf>,
+
+    /// If specified, write a summary of the conversion (coverage, warnings and skipped documents)
+    /// to this HTML file
+    #[arg(long, value_name = "PATH")]
+    html_report: Option<PathBuf>,
+
+    /// Whether to run an interactive terminal UI that lets you select the documents to convert and
+    /// shows the progress and warnings per document (requires the `tui` feature)
+    #[arg(long, default_value = "false")]
+    tui: bool,
+
+    /// Whether to only log warnings and errors as well as a single line per corpus
+    #[arg(long, default_value = "false", conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// If specified, export the spans of the conversion (per corpus, document and applied update)
+    /// to this OTLP/HTTP endpoint, e.g. `http://localhost:4318/v1/traces` (requires the `otlp`
+    /// feature)
+    #[arg(long, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+
+    /// Whether to log debug messages, either of all modules of this tool or only of the given
+    /// module (e.g. `align` or `inbound::ttl`), can be repeated
+    #[arg(
+        long,
+        value_name = "MODULE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    verbose: Vec<String>,
+
+    /// Print the versions of this tool and of the bundled graphannis as well as the supported input
+    /// formats, then exit. If an input ANNIS ZIP is given, also print the formats of the corpora
+    /// contained in it.
+    #[arg(long, default_value = "false")]
+    version_info: bool,
+}
+
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// Print a completion script for the given shell to stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print a man page in the roff format to stdout
+    Manpage,
+
+    /// Apply graph update events written with `--dump-updates` to the input corpora and write the
+    /// resulting corpora, leaving their configs untouched
+    ApplyUpdates {
+        /// Path to input corpora, see the main command
+        #[arg(value_name = "INPUT ANNIS ZIP")]
+        input_annis: PathBuf,
+
+        /// Path to the update events
+        #[arg(value_name = "UPDATES")]
+        updates: PathBuf,
+
+        /// Path to output corpus, will be a .zip file containing the updated corpora in the
+        /// GraphML format
+        #[arg(value_name = "OUTPUT ZIP")]
+        output: PathBuf,
+    },
+
+    /// Print for each corpus how many documents, sentences and tokens would get treebank
+    /// annotations, broken down by subcorpus, without converting anything
+    Coverage {
+        /// Path to input corpora, see the main command
+        #[arg(value_name = "INPUT ANNIS ZIP")]
+        input_annis: PathBuf,
+
+        /// Path to input treebank data, see the main command
+        #[arg(value_name = "INPUT TTL DIRECTORY")]
+        input_ttl: PathBuf,
+    },
+
+    /// Assign to each document of the input corpora the TTL document with the most similar name
+    /// and write the assignment as a CSV file to be reviewed and passed to `--doc-map`
+    MatchDocs {
+        /// Path to input corpora, see the main command
+        #[arg(value_name = "INPUT ANNIS ZIP")]
+        input_annis: PathBuf,
+
+        /// Path to input treebank data, see the main command
+        #[arg(value_name = "INPUT TTL DIRECTORY")]
+        input_ttl: PathBuf,
+
+        /// Path to the CSV file to be written
+        #[arg(value_name = "OUTPUT CSV")]
+        output: PathBuf,
+
+        /// Maximum edit distance between the normalized names of assigned documents
+        #[arg(long, default_value_t = 3)]
+        max_distance: usize,
+    },
+
+    /// Print the names of the corpora and documents of the input corpora or of the documents of the
+    /// treebank data to stdout, e.g. to determine which documents can be merged
+    List {
+        #[command(subcommand)]
+        source: ListSource,
+    },
+}
+
+#[derive(Clone, Subcommand)]
+enum ListSource {
+    /// List the corpora and their documents
+    Annis {
+        /// Path to input corpora, must be a .zip file containing corpora in the relANNIS or
+        /// GraphML format
+        #[arg(value_name = "INPUT ANNIS ZIP")]
+        input_annis: PathBuf,
+
+        /// Print the names as JSON
+        #[arg(long, default_value = "false")]
+        json: bool,
+    },
+
+    /// List the documents of the TTL files, inferred from their file names
+    Ttl {
+        /// Path to input treebank data, see the main command
+        #[arg(value_name = "INPUT TTL DIRECTORY")]
+        input_ttl: PathBuf,
+
+        /// Print the names as JSON
+        #[arg(long, default_value = "false")]
+        json: bool,
+    },
+}
+
+/// Pattern for renaming corpora, parsed into literal parts and the placeholder for the corpus name
+///
+/// The placeholders `%d` (date) and `%v` (version) are resolved while parsing, so they are the
+/// same for all corpora of a run.
+#[derive(Clone)]
+struct RenamePattern(Vec<RenamePart>);
+
+#[derive(Clone)]
+enum RenamePart {
+    Literal(String),
+    CorpusName,
+}
+
+impl FromStr for RenamePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().enumerate();
+
+        while let Some((index, c)) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+
+            match chars.next().map(|(_, c)| c) {
+                Some('c') => {
+                    parts.push(RenamePart::Literal(std::mem::take(&mut literal)));
+                    parts.push(RenamePart::CorpusName);
+                }
+                Some('d') => literal.push_str(&OffsetDateTime::now_utc().date().to_string()),
+                Some('v') => literal.push_str(env!("CARGO_PKG_VERSION")),
+                Some('%') => literal.push('%'),
+                Some(c) => bail!(
+                    "unknown placeholder `%{c}` at position {}, expected one of `%c`, `%d`, `%v` \
+                     or `%%`",
+                    index + 1
+                ),
+                None => bail!(
+                    "incomplete placeholder `%` at position {}, use `%%` for a literal `%`",
+                    index + 1
+                ),
+            }
+        }
+
+        parts.push(RenamePart::Literal(literal));
+
+        ensure!(
+            parts
+                .iter()
+                .any(|part| matches!(part, RenamePart::CorpusName)),
+            "pattern must contain placeholder `%c`"
+        );
+
+        Ok(Self(parts))
+    }
+}
+
+impl RenamePattern {
+    fn apply(&self, name: &str) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                RenamePart::Literal(literal) => literal.as_str(),
+                RenamePart::CorpusName => name,
+            })
+            .collect()
+    }
+}
+
+/// Template for the file name of the output corpus, parsed into literal parts and placeholders
+#[derive(Clone)]
+struct OutputTemplate(Vec<OutputPart>);
+
+#[derive(Clone)]
+enum OutputPart {
+    Literal(String),
+    Stem,
+    Date,
+    Version,
+    Profile,
+}
+
+impl FromStr for OutputTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "unclosed placeholder at position {}",
+                        s.len() - rest.len() + start + 1
+                    )
+                })?;
+
+            parts.push(OutputPart::Literal(rest[..start].into()));
+            parts.push(match &rest[start + 1..end] {
+                "stem" => OutputPart::Stem,
+                "date" => OutputPart::Date,
+                "version" => OutputPart::Version,
+                "profile" => OutputPart::Profile,
+                placeholder => bail!(
+                    "unknown placeholder `{{{placeholder}}}`, expected one of `{{stem}}`, \
+                     `{{date}}`, `{{version}}` or `{{profile}}`"
+                ),
+            });
+
+            rest = &rest[end + 1..];
+        }
+
+        parts.push(OutputPart::Literal(rest.into()));
+
+        ensure!(
+            parts
+                .iter()
+                .any(|part| !matches!(part, OutputPart::Literal(literal) if literal.is_empty())),
+            "template must not be empty"
+        );
+
+        Ok(Self(parts))
+    }
+}
+
+impl OutputTemplate {
+    fn apply(&self, stem: &str, profile: &str) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                OutputPart::Literal(literal) => literal.clone(),
+                OutputPart::Stem => stem.into(),
+                OutputPart::Date => OffsetDateTime::now_utc().date().to_string(),
+                OutputPart::Version => env!("CARGO_PKG_VERSION").into(),
+                OutputPart::Profile => profile.into(),
+            })
+            .collect()
+    }
+}
+
+/// Filter for tree root categories, matching the whole category like a regex in AQL
+#[derive(Clone, Debug)]
+struct CatFilter(Regex);
+
+impl FromStr for CatFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(Regex::new(&format!("^(?:{s})$"))?))
+    }
+}
+
+impl CatFilter {
+    fn matches(&self, cat: &str) -> bool {
+        self.0.is_match(cat)
+    }
+}
+
+/// Template for node names, parsed into literal parts and placeholders, so that values substituted
+/// for placeholders are never substituted again
+#[derive(Clone, Debug)]
+struct NodeNameTemplate(Vec<NodeNamePart>);
+
+#[derive(Clone, Debug)]
+enum NodeNamePart {
+    Literal(String),
+    Doc,
+    Sentence,
+    Segment,
+    Counter,
+}
+
+impl FromStr for NodeNameTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ensure!(
+            s.starts_with("{doc}"),
+            "template must start with placeholder `{{doc}}`"
+        );
+
+        let mut parts = Vec::new();
+        let mut rest = s;
+
+        while let Some(start) = rest.find('{') {
+            let placeholder = rest[start..]
+                .find('}')
+                .map(|end| &rest[start..=start + end])
+                .ok_or_else(|| anyhow!("unterminated placeholder in template"))?;
+
+            let part = match placeholder {
+                "{doc}" => NodeNamePart::Doc,
+                "{sentence}" => NodeNamePart::Sentence,
+                "{segment}" => NodeNamePart::Segment,
+                "{counter}" => NodeNamePart::Counter,
+                _ => bail!(
+                    "unknown placeholder `{placeholder}` in template, expected one of {}",
+                    Self::PLACEHOLDERS
+                        .iter()
+                        .map(|p| format!("`{p}`"))
+                        .join(", ")
+                ),
+            };
+
+            if start > 0 {
+                parts.push(NodeNamePart::Literal(rest[..start].into()));
+            }
+
+            parts.push(part);
+            rest = &rest[start + placeholder.len()..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(NodeNamePart::Literal(rest.into()));
+        }
+
+        Ok(Self(parts))
+    }
+}
+
+impl NodeNameTemplate {
+    const PLACEHOLDERS: [&'static str; 4] = ["{doc}", "{sentence}", "{segment}", "{counter}"];
+
+    fn apply(&self, doc: &str, sentence: Option<usize>, segment: &str, counter: usize) -> String {
+        let mut node_name = String::new();
+
+        for part in &self.0 {
+            match part {
+                NodeNamePart::Literal(literal) => node_name.push_str(literal),
+                NodeNamePart::Doc => node_name.push_str(doc),
+                NodeNamePart::Sentence => {
+                    if let Some(sentence) = sentence {
+                        node_name.push_str(&sentence.to_string());
+                    }
+                }
+                NodeNamePart::Segment => node_name.push_str(segment),
+                NodeNamePart::Counter => node_name.push_str(&counter.to_string()),
+            }
+        }
+
+        node_name
+    }
+}
+
+/// Rule for extracting the segment of a TTL node IRI used for the node name of a nonterminal
+#[derive(Clone)]
+enum IriSegmentRule {
+    /// Fragment (after `#`)
+    Fragment,
+    /// Last non-empty segment of the path (before `?` and `#`)
+    Path,
+    /// Value of the given parameter of the query (after `?`)
+    Query(String),
+}
+
+impl FromStr for IriSegmentRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fragment" => Ok(Self::Fragment),
+            "path" => Ok(Self::Path),
+            _ => match s.strip_prefix("query:") {
+                Some(param) if !param.is_empty() => Ok(Self::Query(param.into())),
+                _ => bail!("rule must be one of `fragment`, `path` or `query:PARAM`"),
+            },
+        }
+    }
+}
+
+impl IriSegmentRule {
+    const DEFAULTS: &'static [Self] = &[Self::Fragment, Self::Path];
+
+    fn extract<'i>(&self, iri: &'i str) -> Option<&'i str> {
+        let (rest, fragment) = match iri.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (iri, None),
+        };
+
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+
+        let segment = match self {
+            Self::Fragment => fragment,
+            Self::Path => path
+                .trim_end_matches('/')
+                .rsplit_once('/')
+                .map(|(_, segment)| segment),
+            Self::Query(param) => query?.split('&').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                (key == param).then_some(value)
+            }),
+        };
+
+        segment.filter(|segment| !segment.is_empty())
+    }
+}
+
+/// Routing rule for treebank nodes
+#[derive(Clone)]
+struct Route {
+    value: String,
+    layer: String,
+    anno: Option<String>,
+}
+
+impl FromStr for Route {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, target) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("route must be given as `VALUE=LAYER` or `VALUE=LAYER/ANNO`"))?;
+
+        let (layer, anno) = match target.split_once('/') {
+            Some((layer, anno)) => (layer, Some(anno.into())),
+            None => (target, None),
+        };
+
+        ensure!(!layer.is_empty(), "route target layer must not be empty");
+
+        Ok(Self {
+            value: value.into(),
+            layer: layer.into(),
+            anno,
+        })
+    }
+}
+
+/// Layer of treebank nodes together with the namespace and name of their tree annotation and the
+/// display name of their visualizer
+#[derive(Clone, Copy)]
+struct TreeLayer<'a> {
+    layer: &'a str,
+    anno_ns: &'a str,
+    anno: &'a str,
+    display: &'a str,
+}
+
+impl Args {
+    /// Checks the constraints between arguments that cannot be expressed with clap attributes
+    fn check(&self) -> Result<(), clap::Error> {
+        // both annotations would be written to the same key
+        if self.source_anno_suffix.is_some() && self.source_anno_suffix == self.other_value_suffix {
+            return Err(Self::command().error(
+                ErrorKind::ArgumentConflict,
+                "--source-anno-suffix and --other-value-suffix must differ",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Determines the layer of the given treebank node according to the routing rules
+    fn tree_layer(&self, ttl_node: inbound::ttl::Node<'_>) -> TreeLayer<'_> {
+        ttl_node
+            .route_value()
+            .and_then(|value| self.route.iter().find(|route| route.value == value))
+            .map_or(
+                TreeLayer {
+                    layer: &self.layer,
+                    anno_ns: self.anno_ns(&self.layer),
+                    anno: &self.tree_anno,
+                    display: &self.tree_display,
+                },
+                |route| TreeLayer {
+                    layer: &route.layer,
+                    anno_ns: self.anno_ns(&route.layer),
+                    anno: route.anno.as_deref().unwrap_or(&self.tree_anno),
+                    display: &route.layer,
+                },
+            )
+    }
+
+    /// How to store the ANNIS corpora: in memory if `--in-memory` is set, on disk otherwise, or
+    /// chosen automatically per corpus by its size if `--memory-threshold-mb` is set
+    fn storage_mode(&self) -> inbound::annis::StorageMode {
+        match self.memory_threshold_mb {
+            Some(memory_threshold_mb) => inbound::annis::StorageMode::Auto {
+                memory_threshold: memory_threshold_mb.saturating_mul(1024 * 1024),
+            },
+            None if self.in_memory => inbound::annis::StorageMode::Memory,
+            None => inbound::annis::StorageMode::Disk,
+        }
+    }
+
+    /// Maximum number of threads according to `--threads`
+    fn threads(&self) -> usize {
+        self.threads.map_or_else(
+            || thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            NonZeroUsize::get,
+        )
+    }
+
+    /// Filter for log messages according to `--quiet` and `--verbose`
+    fn log_filter(&self) -> Targets {
+        let mut targets = Targets::new().with_default(if self.quiet {
+            LevelFilter::WARN
+        } else {
+            LevelFilter::INFO
+        });
+
+        if self.quiet {
+            targets = targets.with_target(SUMMARY_TARGET, LevelFilter::INFO);
+        }
+
+        for module in &self.verbose {
+            let target = if module.is_empty() {
+                env!("CARGO_CRATE_NAME").into()
+            } else {
+                format!("{}::{module}", env!("CARGO_CRATE_NAME"))
+            };
+
+            targets = targets.with_target(target, LevelFilter::DEBUG);
+        }
+
+        targets
+    }
+
+    fn iri_segment_rules(&self) -> &[IriSegmentRule] {
+        if self.iri_segment.is_empty() {
+            IriSegmentRule::DEFAULTS
+        } else {
+            &self.iri_segment
+        }
+    }
+
+    /// Namespace of the annotations of nodes in the given layer
+    fn anno_ns<'a>(&'a self, layer: &'a str) -> &'a str {
+        self.anno_ns.as_deref().unwrap_or(layer)
+    }
+
+    /// All layers treebank nodes can be routed into, starting with the default layer
+    fn tree_layers(&self) -> Vec<TreeLayer<'_>> {
+        let default_layer = TreeLayer {
+            layer: &self.layer,
+            anno_ns: self.anno_ns(&self.layer),
+            anno: &self.tree_anno,
+            display: &self.tree_display,
+        };
+
+        std::iter::once(default_layer)
+            .chain(self.route.iter().map(|route| TreeLayer {
+                layer: &route.layer,
+                anno_ns: self.anno_ns(&route.layer),
+                anno: route.anno.as_deref().unwrap_or(&self.tree_anno),
+                display: &route.layer,
+            }))
+            .unique_by(|tree_layer| tree_layer.layer)
+            .collect()
+    }
+}
+
+/// Additional export of the treebank layer
+#[derive(Clone)]
+#[cfg_attr(not(feature = "export-extras"), allow(dead_code))]
+struct AlsoExport {
+    format: ExportFormat,
+    dir: PathBuf,
+}
+
+impl FromStr for AlsoExport {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, dir) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("export must be given as `FORMAT=DIR`"))?;
+
+        Ok(Self {
+            format: ExportFormat::from_str(format, true).map_err(|err| anyhow!(err))?,
+            dir: dir.into(),
+        })
+    }
+}
+
+#[cfg(feature = "export-extras")]
+impl AlsoExport {
+    fn writer(&self, args: &Args) -> outbound::treebank::TreebankWriter {
+        let dir = self.dir.clone();
+        let layer = args.layer.clone();
+        let anno_ns = args.anno_ns(&args.layer).to_owned();
+        let anno = args.tree_anno.clone();
+
+        match self.format {
+            ExportFormat::Paula => outbound::treebank::TreebankWriter::Paula(
+                outbound::paula::PaulaWriter::new(dir, layer, anno_ns, anno),
+            ),
+            ExportFormat::SaltXml => outbound::treebank::TreebankWriter::SaltXml(
+                outbound::saltxml::SaltXmlWriter::new(dir, layer, anno_ns, anno),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// PAULA XML (ISO 24612)
+    Paula,
+    /// SaltXML as used by Pepper
+    #[value(name = "saltxml")]
+    SaltXml,
+}
+
+/// Qualified annotation name, given as `NS:NAME`, or as `NAME` for the empty namespace
+#[derive(Clone)]
+struct QualifiedName {
+    ns: String,
+    name: String,
+}
+
+impl QualifiedName {
+    fn anno_key(&self) -> inbound::annis::AnnoKey {
+        inbound::annis::AnnoKey {
+            ns: self.ns.as_str().into(),
+            name: self.name.as_str().into(),
+        }
+    }
+}
+
+impl FromStr for QualifiedName {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ns, name) = s.split_once(':').unwrap_or(("", s));
+        ensure!(!name.is_empty(), "annotation name must not be empty");
+
+        Ok(Self {
+            ns: ns.into(),
+            name: name.into(),
+        })
+    }
+}
+
+/// Mapping of a visualizer in the corpus config, whose value is always a string
+#[derive(Clone)]
+struct VisualizerMapping {
+    key: String,
+    value: String,
+}
+
+impl FromStr for VisualizerMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("visualizer mapping must be given as `KEY=VALUE`"))?;
+        ensure!(!key.is_empty(), "visualizer mapping key must not be empty");
+
+        Ok(Self {
+            key: key.into(),
+            value: value.into(),
+        })
+    }
+}
+
+/// Setting of a corpus config value
+#[derive(Clone)]
+struct ConfigSetting {
+    /// Keys of the nested tables leading to the value
+    keys: Vec<String>,
+    value: toml::Value,
+}
+
+impl FromStr for ConfigSetting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("config setting must be given as `KEY=VALUE`"))?;
+
+        let keys: Vec<String> = key.split('.').map(Into::into).collect();
+        ensure!(
+            keys.iter().all(|key| !key.is_empty()),
+            "config key `{key}` must not have empty parts"
+        );
+
+        // the value is parsed as a TOML value if possible (e.g. `20`, `true` or `"a"`), otherwise
+        // it is taken as a string
+        let value = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| value.into());
+
+        Ok(Self { keys, value })
+    }
+}
+
+impl ConfigSetting {
+    /// Sets the value in the given corpus config, creating nested tables as needed
+    fn apply(&self, config: &mut toml::Table) -> anyhow::Result<()> {
+        let (last_key, parent_keys) = self
+            .keys
+            .split_last()
+            .ok_or_else(|| anyhow!("config key must not be empty"))?;
+
+        let mut table = config;
+
+        for key in parent_keys {
+            table = table
+                .entry(key.as_str())
+                .or_insert_with(|| toml::Table::new().into())
+                .as_table_mut()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "invalid config key `{}`: `{key}` is not a table",
+                        self.keys.join(".")
+                    )
+                })?;
+        }
+
+        table.insert(last_key.clone(), self.value.clone());
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum Mode {
+    /// Add the treebank layer to the input corpora
+    Merge,
+    /// Create new corpora containing only the aligned tokens and the treebank layer
+    Standalone,
+}
+
+/// Kinds of nodes to which IRI annotations are added
+#[derive(Clone, Copy, ValueEnum)]
+enum IriTargets {
+    /// Created nonterminal nodes only
+    Nonterminals,
+    /// Segmentation nodes aligned with TTL words only
+    Words,
+    /// Both nonterminal and word nodes
+    Both,
+}
+
+impl IriTargets {
+    fn includes(self, is_word: bool) -> bool {
+        match self {
+            Self::Nonterminals => !is_word,
+            Self::Words => is_word,
+            Self::Both => true,
+        }
+    }
+}
+
+/// Policy for TTL edges from a child to a parent in a different sentence, which are data errors
+#[derive(Clone, Copy, ValueEnum)]
+enum CrossSentencePolicy {
+    /// Fail
+    Error,
+    /// Drop the edge
+    Drop,
+    /// Add the edge as a pointing relation `cross_sentence` rather than a dominance edge
+    Pointing,
+}
+
+/// Policy for treebank nodes whose yields are not contiguous, e.g. due to non-projective or
+/// erroneous structures
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DiscontinuityPolicy {
+    /// Warn about each such node
+    Report,
+    /// Warn about each such node and mark it with the annotation `--discontinuous-anno`
+    Mark,
+    /// Warn about each such node and exclude the trees of the sentences containing such nodes
+    Exclude,
+    /// Keep each such node without a warning and mark it with the annotation
+    /// `--discontinuous-anno`, for data in which discontinuous constituents are legitimate
+    Allow,
+}
+
+/// Annotation of a segmentation node that corresponds to a TTL annotation of the aligned word
+#[derive(Clone, Copy, ValueEnum)]
+enum TokenAnno {
+    /// Inflection, from `INFL`
+    Inflection,
+    /// Lemma, from `LEMMA`
+    Lemma,
+    /// Normalized form, from `WORD`
+    Norm,
+    /// Part of speech, from `POS`
+    Pos,
+}
+
+impl TokenAnno {
+    /// Keys of the TTL annotation and of the corresponding ANNIS annotation according to the
+    /// profile
+    fn anno_keys(
+        self,
+        profile: &rem::Profile,
+    ) -> (inbound::ttl::AnnoKey, &inbound::annis::AnnoKey) {
+        match self {
+            Self::Inflection => (inbound::ttl::AnnoKey::Infl, &profile.anno_key_inflection),
+            Self::Lemma => (inbound::ttl::AnnoKey::Lemma, &profile.anno_key_lemma),
+            Self::Norm => (inbound::ttl::AnnoKey::Word, &profile.anno_key_norm),
+            Self::Pos => (inbound::ttl::AnnoKey::Pos, &profile.anno_key_pos),
+        }
+    }
+}
+
+/// Policy for corpora in which no document matches a TTL file
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum UntouchedPolicy {
+    /// Warn and write the corpus anyway
+    Warn,
+    /// Warn and leave the corpus out of the output
+    Skip,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PartOfTarget {
+    /// The datasource of the segmentation nodes covered by the treebank node
+    Datasource,
+    /// The document containing the treebank node
+    Document,
+}
+
+impl PartOfTarget {
+    fn name(self) -> &'static str {
+        match self {
+            PartOfTarget::Datasource => "datasource",
+            PartOfTarget::Document => "document",
+        }
+    }
+
+    fn query(self) -> &'static str {
+        match self {
+            PartOfTarget::Datasource => "annis:node_type=\"datasource\"",
+            PartOfTarget::Document => "annis:doc",
+        }
+    }
+}
+
+/// Phase of the conversion, in the order in which they are run
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd, ValueEnum)]
+enum Phase {
+    /// Importing the input corpora
+    Import,
+    /// Aligning the TTL words with the ANNIS segmentation nodes
+    Align,
+    /// Adding the treebank nodes and edges to the corpora
+    Merge,
+    /// Adding the PartOf edges from the treebank nodes
+    #[value(name = "partof")]
+    PartOf,
+    /// Deleting the annotations matching `--strip-anno`
+    Strip,
+    /// Renaming the corpora
+    Rename,
+    /// Writing the output
+    Export,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Import => "import",
+            Phase::Align => "align",
+            Phase::Merge => "merge",
+            Phase::PartOf => "partof",
+            Phase::Strip => "strip",
+            Phase::Rename => "rename",
+            Phase::Export => "export",
+        }
+    }
+}
+
+/// Path denoting stdin (for the input ANNIS ZIP) or stdout (for the output)
+const STDIO_PATH: &str = "-";
+
+/// Maximum number of documents loaded (with their TTL files parsed) ahead of the document being
+/// converted, which bounds the memory used by documents waiting to be converted
+const DOCUMENTS_LOADED_AHEAD: usize = 2;
+
+/// Maximum number of converted corpora waiting to be exported, after which the conversion of the
+/// next corpus waits for the export to catch up
+const CORPORA_EXPORTED_BEHIND: usize = 1;
+
+/// Runs the command-line interface of the converter with the arguments of the current process
+pub fn main() {
+    let args = Args::parse();
+
+    if let Err(err) = args.check() {
+        err.exit();
+    }
+
+    let log_layer = if args.tui {
+        // log messages are shown within the TUI while it is active
+        tui::log_layer().boxed()
+    } else {
+        // log to stderr so that the output ZIP can be written to stdout
+        tracing_subscriber::fmt::layer()
+            .with_writer(io::stderr)
+            .boxed()
+    };
+
+    let (telemetry, telemetry_err) = match args
+        .otlp_endpoint
+        .as_deref()
+        .map(telemetry::Telemetry::new)
+        .transpose()
+    {
+        Ok(telemetry) => (telemetry, None),
+        Err(err) => (None, Some(err)),
+    };
+    let (telemetry, otlp_layer) = telemetry.unzip();
+
+    tracing_subscriber::registry()
+        .with(log_layer.with_filter(args.log_filter()))
+        .with(otlp_layer)
+        .init();
+
+    let result = match telemetry_err {
+        Some(err) => Err(err),
+        None => run(&args),
+    };
+
+    if let Err(err) = result {
+        error!("{}", err);
+    }
+
+    if let Some(telemetry) = telemetry {
+        if let Err(err) = telemetry.shutdown() {
+            error!("{}", err);
+        }
+    }
+}
+
+/// Builds the global thread pool of rayon, in which graphannis executes queries, with the given
+/// number of threads
+///
+/// This is only done on the paths running queries and at most once, and if the pool has already
+/// been initialized by using rayon before, that pool is kept.
+fn init_thread_pool(threads: usize) {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            info!(%err, "keeping existing thread pool");
+        }
+    });
+}
+
+fn run(args: &Args) -> anyhow::Result<()> {
+    match &args.command {
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(
+                *shell,
+                &mut Args::command(),
+                env!("CARGO_PKG_NAME"),
+                &mut io::stdout(),
+            );
+            return Ok(());
+        }
+        Some(Command::Manpage) => {
+            clap_mangen::Man::new(Args::command()).render(&mut io::stdout())?;
+            return Ok(());
+        }
+        Some(Command::ApplyUpdates {
+            input_annis,
+            updates,
+            output,
+        }) => {
+            init_thread_pool(args.threads());
+            return apply_updates(input_annis, updates, output);
+        }
+        Some(Command::Coverage {
+            input_annis,
+            input_ttl,
+        }) => {
+            init_thread_pool(args.threads());
+            let input = Input::open(
+                input_annis,
+                input_ttl,
+                None,
+                Vec::new(),
+                None,
+                inbound::annis::StorageMode::Disk,
+                &[],
+            )?;
+            return coverage::report(&input.annis_storage, &input.ttl_storage);
+        }
+        Some(Command::MatchDocs {
+            input_annis,
+            input_ttl,
+            output,
+            max_distance,
+        }) => {
+            init_thread_pool(args.threads());
+            let input = Input::open(
+                input_annis,
+                input_ttl,
+                None,
+                Vec::new(),
+                None,
+                inbound::annis::StorageMode::Disk,
+                &[],
+            )?;
+            return doc_map::match_documents(
+                &input.annis_storage,
+                &input.ttl_storage,
+                output,
+                *max_distance,
+            );
+        }
+        Some(Command::List {
+            source: ListSource::Annis { input_annis, json },
+        }) => {
+            init_thread_pool(args.threads());
+            inbound::annis_zip::check_compatibility(input_annis)?;
+            let annis_storage = inbound::annis::Storage::from_zip(
+                input_annis,
+                inbound::annis::StorageMode::Disk,
+                &[],
+            )?;
+            return list::annis(&annis_storage, *json);
+        }
+        Some(Command::List {
+            source: ListSource::Ttl { input_ttl, json },
+        }) => {
+            let ttl_storage = inbound::ttl::Storage::from_dir(input_ttl, None)?;
+            return list::ttl(&ttl_storage, *json);
+        }
+        None => {}
+    }
+
+    if args.version_info {
+        return print_version_info(args.input_annis.as_deref());
+    }
+
+    let (Some(input_annis), Some(input_ttl)) = (&args.input_annis, &args.input_ttl) else {
+        bail!("missing input paths");
+    };
+
+    let input_is_stdin = input_annis == Path::new(STDIO_PATH);
+    let input_is_remote = remote::is_remote(input_annis);
+
+    let output_path = args.output.clone().unwrap_or_else(|| {
+        if let Some(output_template) = &args.output_template {
+            let stem = if input_is_stdin {
+                "stdin".into()
+            } else {
+                input_annis
+                    .file_stem()
+                    .map_or_else(|| "out".into(), |stem| stem.to_string_lossy().into_owned())
+            };
+
+            let file_name = output_template.apply(&stem, &args.profile.name());
+
+            return if input_is_stdin || input_is_remote {
+                PathBuf::from(file_name)
+            } else {
+                input_annis.with_file_name(file_name)
+            };
+        }
+
+        if input_is_stdin {
+            return PathBuf::from(STDIO_PATH);
+        }
+
+        match input_annis.file_stem() {
+            Some(stem) => {
+                let mut file_name = stem.to_os_string();
+                file_name.push(".out.zip");
+
+                if input_is_remote {
+                    // write output for remote input to the current directory by default
+                    PathBuf::from(file_name)
+                } else {
+                    input_annis.with_file_name(&file_name)
+                }
+            }
+            None => PathBuf::from("out.zip"),
+        }
+    });
+
+    ensure!(
+        !(args.anno_diff && args.mode == Mode::Standalone),
+        "--anno-diff cannot be used with --mode standalone"
+    );
+
+    ensure!(
+        args.also_export.is_empty() || cfg!(feature = "export-extras"),
+        "--also-export requires building with the `export-extras` feature"
+    );
+
+    if args.tui {
+        ensure!(
+            cfg!(feature = "tui"),
+            "the TUI requires building with the `tui` feature"
+        );
+        ensure!(
+            !input_is_stdin && output_path != Path::new(STDIO_PATH),
+            "the TUI cannot be used when reading from stdin or writing to stdout"
+        );
+    }
+
+    // taken before importing the corpora so that concurrent conversions fail (or wait) right away
+    let _output_lock = if output_path == Path::new(STDIO_PATH) || remote::is_remote(&output_path) {
+        None
+    } else {
+        Some(outbound::lock::OutputLock::acquire(
+            &output_path,
+            args.wait,
+        )?)
+    };
+
+    // read before importing the corpora so that errors in them are reported right away
+    let doc_map = args.doc_map.as_deref().map(doc_map::read).transpose()?;
+    let corpus_overrides = args
+        .corpus_overrides
+        .as_deref()
+        .map(overrides::CorpusOverrides::from_file)
+        .transpose()?
+        .unwrap_or_default();
+
+    init_thread_pool(args.threads());
+
+    let mut input = Input::open(
+        input_annis,
+        input_ttl,
+        args.route_predicate.clone(),
+        args.literal_lang.clone(),
+        args.pre_process.clone(),
+        args.storage_mode(),
+        &args.corpora,
+    )?;
+
+    if let Some(doc_map) = doc_map {
+        input.ttl_storage = input.ttl_storage.with_doc_map(doc_map);
+    }
+
+    let mut duplicate_names = Vec::new();
+    for corpus in input.annis_storage.corpora() {
+        for (name, doc_names) in corpus.duplicate_document_names()? {
+            info!(
+                corpus_name = corpus.name(),
+                name,
+                doc_names = doc_names.join(", "),
+                "documents in different subcorpora share their name"
+            );
+            duplicate_names.push(doc_names);
+        }
+    }
+
+    input.ttl_storage = input
+        .ttl_storage
+        .with_max_skipped_statements(args.skip_malformed_ttl)
+        .with_duplicate_names(duplicate_names);
+
+    if args.rename.is_some() || corpus_overrides.renames() {
+        check_renamed_names(args, &corpus_overrides, &input.annis_storage)?;
+        confirm_rename(args, &corpus_overrides, &input.annis_storage)?;
+    }
+
+    if args.tui {
+        tui::run(args, &corpus_overrides, &input, &output_path)
+    } else {
+        convert(
+            args,
+            &corpus_overrides,
+            &input,
+            &output_path,
+            &Selection::All,
+            &mut NoProgress,
+        )
+    }
+}
+
+/// New name of the given corpus according to `--rename` or the corpus overrides, if it is renamed
+fn renamed_corpus_name(
+    args: &Args,
+    corpus_overrides: &overrides::CorpusOverrides,
+    corpus_name: &str,
+) -> Option<String> {
+    corpus_overrides
+        .apply(args, corpus_name)
+        .rename
+        .as_ref()
+        .map(|rename_pattern| rename_pattern.apply(corpus_name))
+}
+
+/// Checks that renaming maps the corpora to distinct names whose entries in the output ZIP file
+/// (`CORPUS.graphml` and the directory `CORPUS`) don't collide, also with corpora that are not
+/// renamed, and that the new names are no paths, which would lead to nested entries
+fn check_renamed_names(
+    args: &Args,
+    corpus_overrides: &overrides::CorpusOverrides,
+    annis_storage: &inbound::annis::Storage,
+) -> anyhow::Result<()> {
+    let mut original_names_by_new_name: BTreeMap<String, String> = BTreeMap::new();
+
+    for corpus in annis_storage.corpora() {
+        let new_name = match renamed_corpus_name(args, corpus_overrides, corpus.name()) {
+            Some(new_name) => {
+                ensure!(
+                    !new_name.contains(['/', '\\']) && new_name != "." && new_name != "..",
+                    "renaming corpus {} to {new_name} leads to a path in the output ZIP file",
+                    corpus.name()
+                );
+
+                new_name
+            }
+            None => corpus.name().into(),
+        };
+
+        if let Some(other_name) =
+            original_names_by_new_name.insert(new_name.clone(), corpus.name().into())
+        {
+            bail!(
+                "renaming maps both corpus {other_name} and corpus {} to {new_name}",
+                corpus.name()
+            );
+        }
+    }
+
+    for (new_name, original_name) in &original_names_by_new_name {
+        if let Some(other_original_name) =
+            original_names_by_new_name.get(&format!("{new_name}.graphml"))
+        {
+            bail!(
+                "renaming corpus {original_name} to {new_name} and corpus {other_original_name} to \
+                 {new_name}.graphml leads to colliding entries in the output ZIP file"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows a preview of the new names of all renamed corpora and their documents on stderr and asks
+/// for confirmation unless `--yes` is given
+fn confirm_rename(
+    args: &Args,
+    corpus_overrides: &overrides::CorpusOverrides,
+    annis_storage: &inbound::annis::Storage,
+) -> anyhow::Result<()> {
+    let mut stderr = io::stderr().lock();
+
+    writeln!(stderr, "renaming:")?;
+
+    for corpus in annis_storage.corpora() {
+        let Some(new_corpus_name) = renamed_corpus_name(args, corpus_overrides, corpus.name())
+        else {
+            continue;
+        };
+
+        writeln!(stderr, "  {} -> {new_corpus_name}", corpus.name())?;
+
+        for (_, doc_node_name) in corpus.document_node_names_by_name()? {
+            writeln!(
+                stderr,
+                "    {doc_node_name} -> {}",
+                node_name::rename_corpus(&doc_node_name, corpus.name(), &new_corpus_name)?
+            )?;
+        }
+    }
+
+    if args.yes {
+        return Ok(());
+    }
+
+    ensure!(
+        io::stdin().is_terminal(),
+        "renaming requires confirmation, use --yes when not running in a terminal"
+    );
+
+    write!(stderr, "proceed? [y/N] ")?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    ensure!(
+        matches!(answer.trim(), "y" | "Y" | "yes"),
+        "renaming aborted"
+    );
+
+    Ok(())
+}
+
+/// Imported input corpora together with the treebank data
+struct Input {
+    annis_storage: inbound::annis::Storage,
+    ttl_storage: inbound::ttl::Storage,
+    /// Path of the input ZIP file, which may be a temporary copy
+    annis_path: PathBuf,
+    /// Temporary copy of the input ZIP file read from stdin or a remote location, which is kept
+    /// until the input is dropped
+    _annis_buffer: Option<NamedTempFile>,
+}
+
+impl Input {
+    fn open(
+        input_annis: &Path,
+        input_ttl: &Path,
+        route_predicate: Option<String>,
+        languages: Vec<String>,
+        pre_process: Option<String>,
+        storage_mode: inbound::annis::StorageMode,
+        corpora: &[String],
+    ) -> anyhow::Result<Self> {
+        // the ZIP file needs to be seekable, so buffer stdin or remote input into a temporary file
+        let input_buffer = if input_annis == Path::new(STDIO_PATH) {
+            info!("buffering input from stdin");
+            let mut input_buffer = NamedTempFile::new()?;
+            io::copy(&mut io::stdin().lock(), &mut input_buffer)?;
+            Some(input_buffer)
+        } else if remote::is_remote(input_annis) {
+            let mut input_buffer = NamedTempFile::new()?;
+            remote::download(input_annis, &mut input_buffer)?;
+            Some(input_buffer)
+        } else {
+            None
+        };
+
+        let input_annis_path = input_buffer
+            .as_ref()
+            .map_or(input_annis, |f| f.path())
+            .to_path_buf();
+
+        inbound::annis_zip::check_compatibility(&input_annis_path)?;
+
+        // the TTL directory is indexed while the corpora are being imported
+        thread::scope(|scope| {
+            let ttl_storage =
+                scope.spawn(|| inbound::ttl::Storage::from_dir(input_ttl, route_predicate));
+
+            let annis_storage =
+                inbound::annis::Storage::from_zip(&input_annis_path, storage_mode, corpora)?;
+
+            let ttl_storage = ttl_storage
+                .join()
+                .map_err(|_| anyhow!("thread indexing the ttl files panicked"))??
+                .with_languages(languages)
+                .with_pre_process(pre_process);
+
+            Ok(Self {
+                annis_storage,
+                ttl_storage,
+                annis_path: input_annis_path,
+                _annis_buffer: input_buffer,
+            })
+        })
+    }
+}
+
+/// Documents to be converted
+enum Selection {
+    All,
+    /// Names of the selected documents by corpus name (only selected in interactive mode)
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    Documents(HashMap<String, HashSet<String>>),
+}
+
+impl Selection {
+    fn contains(&self, corpus_name: &str, doc_name: &str) -> bool {
+        match self {
+            Selection::All => true,
+            Selection::Documents(documents) => documents
+                .get(corpus_name)
+                .is_some_and(|doc_names| doc_names.contains(doc_name)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum DocumentStatus {
+    Converted,
+    /// No treebank data found for the document
+    Skipped,
+}
+
+/// Observer of the progress of a conversion
+trait Progress {
+    fn document_started(&mut self, corpus_name: &str, doc_name: &str) -> anyhow::Result<()>;
+
+    fn document_finished(
+        &mut self,
+        corpus_name: &str,
+        doc_name: &str,
+        status: DocumentStatus,
+    ) -> anyhow::Result<()>;
+}
+
+/// Progress observer that does nothing, progress in batch mode is logged anyway
+struct NoProgress;
+
+impl Progress for NoProgress {
+    fn document_started(&mut self, _corpus_name: &str, _doc_name: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn document_finished(
+        &mut self,
+        _corpus_name: &str,
+        _doc_name: &str,
+        _status: DocumentStatus,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Applies the update events from the given dump to the corpora in the given ZIP file and writes
+/// the updated corpora
+fn apply_updates(input_annis: &Path, updates: &Path, output: &Path) -> anyhow::Result<()> {
+    let _output_lock = outbound::lock::OutputLock::acquire(output, false)?;
+    let mut events_by_corpus = outbound::annis::read_update_dump(updates)?;
+
+    inbound::annis_zip::check_compatibility(input_annis)?;
+    let annis_storage =
+        inbound::annis::Storage::from_zip(input_annis, inbound::annis::StorageMode::Disk, &[])?;
+
+    let mut corpus_writer = outbound::annis::CorpusWriter::new(
+        output,
+        1,
+        false,
+        outbound::annis::DEFAULT_STREAM_THRESHOLD_MB,
+    )?;
+
+    for inbound_corpus in annis_storage.corpora() {
+        let outbound_corpus = outbound::annis::Corpus::from_inbound_corpus(&inbound_corpus);
+
+        if let Some(events) = events_by_corpus.remove(inbound_corpus.name()) {
+            let mut update = outbound_corpus.begin_update();
+
+            for event in events {
+                update.add_event(event)?;
+            }
+
+            update.apply()?;
+        }
+
+        corpus_writer.write_corpus(&outbound_corpus, None)?;
+    }
+
+    ensure!(
+        events_by_corpus.is_empty(),
+        "updates refer to corpora missing from the input: {}",
+        events_by_corpus.keys().format(", ")
+    );
+
+    corpus_writer.finish()
+}
+
+fn convert(
+    args: &Args,
+    corpus_overrides: &overrides::CorpusOverrides,
+    input: &Input,
+    output_path: &Path,
+    selection: &Selection,
+    progress: &mut dyn Progress,
+) -> anyhow::Result<()> {
+    let mut breadcrumbs = breadcrumbs::Breadcrumbs::default();
+
+    convert_tracked(
+        args,
+        corpus_overrides,
+        input,
+        output_path,
+        selection,
+        progress,
+        &mut breadcrumbs,
+    )
+    .map_err(|err| breadcrumbs.wrap(err))
+}
+
+/// Like [`convert`], keeping track of the current location in `breadcrumbs`
+fn convert_tracked(
+    args: &Args,
+    corpus_overrides: &overrides::CorpusOverrides,
+    input: &Input,
+    output_path: &Path,
+    selection: &Selection,
+    progress: &mut dyn Progress,
+    breadcrumbs: &mut breadcrumbs::Breadcrumbs,
+) -> anyhow::Result<()> {
+    // remote output is written to a temporary directory first and uploaded once finished
+    let output_buffer_dir = if remote::is_remote(output_path) {
+        Some(TempDir::new()?)
+    } else {
+        None
+    };
+
+    let local_output_path = output_buffer_dir
+        .as_ref()
+        .map_or(output_path.to_path_buf(), |dir| {
+            dir.path().join("output.zip")
+        });
+
+    let stop_after = args.stop_after.unwrap_or(Phase::Export);
+    let artifact_writer = args
+        .artifacts_dir
+        .as_deref()
+        .map(outbound::artifacts::ArtifactWriter::new)
+        .transpose()?;
+
+    if let Some(artifact_writer) = &artifact_writer {
+        let documents = input
+            .annis_storage
+            .corpora()
+            .map(|corpus| Ok((corpus.name().to_owned(), corpus.document_names()?)))
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+        artifact_writer.write_json(None, "import.json", &documents)?;
+    }
+
+    if stop_after == Phase::Import {
+        info!(phase = stop_after.name(), "stopped without writing output");
+        return Ok(());
+    }
+
+    let mut update_dump = args
+        .dump_updates
+        .as_deref()
+        .map(outbound::annis::UpdateDump::create)
+        .transpose()?;
+
+    let component_selection = inbound::annis::ComponentSelection {
+        ordering: args.ordering,
+        coverage: inbound::annis::CoverageFilter {
+            include: args.coverage_component.clone(),
+            exclude: args.exclude_coverage_component.clone(),
+        },
+    };
+
+    let anchors = match &args.anchors {
+        Some(path) => inbound::anchors::Anchors::from_file(path)?,
+        None => inbound::anchors::Anchors::default(),
+    };
+
+    let metadata = args
+        .metadata
+        .as_deref()
+        .map(inbound::metadata::Metadata::from_file)
+        .transpose()?;
+    let mut known_doc_names = HashSet::new();
+    let tagset = args
+        .tagset
+        .as_deref()
+        .map(inbound::tagset::Tagset::from_file)
+        .transpose()?;
+    let mut tagset_violations = 0;
+    let feature_splitting = args
+        .infl_features
+        .as_deref()
+        .map(features::FeatureSplitting::from_file)
+        .transpose()?;
+
+    let mut profile = args.profile.load()?;
+
+    if let Some(path) = &args.sanitization {
+        profile.sanitization = rem::Sanitization::from_file(path)?;
+    }
+
+    let mut anno_keys = BTreeSet::new();
+    for inbound_corpus in input.annis_storage.corpora() {
+        anno_keys.extend(inbound_corpus.node_anno_keys()?);
+    }
+    profile.detect_anno_keys(&anno_keys)?;
+
+    let alignment = AlignmentOptions {
+        component_selection: &component_selection,
+        skip_filters: &args.skip_token,
+        profile: &profile,
+        anchors: &anchors,
+        max_gap: args.max_gap,
+    };
+
+    if args.prevalidate {
+        prevalidate(args, input, selection, &alignment)?;
+    }
+
+    let corpus_writer = if output_path == Path::new(STDIO_PATH) {
+        outbound::annis::CorpusWriter::to_stdout(
+            args.zip_threads,
+            args.verify_graphml,
+            args.stream_threshold_mb,
+        )?
+    } else {
+        outbound::annis::CorpusWriter::new(
+            &local_output_path,
+            args.zip_threads,
+            args.verify_graphml,
+            args.stream_threshold_mb,
+        )?
+    }
+    .with_post_process(
+        args.post_process
+            .as_deref()
+            .map(outbound::post_process::PostProcess::from_file)
+            .transpose()?,
+    );
+    let tree_builder = args.tree_convention.builder(
+        args.empty_element_anno.is_some(),
+        args.relabel
+            .as_deref()
+            .map(relabel::Relabeling::from_file)
+            .transpose()?,
+        args.collapse_unary.clone(),
+    );
+    let sidecar_writer = args
+        .sidecar_dir
+        .clone()
+        .map(outbound::sidecar::SidecarWriter::new);
+    let mut link_table_writer = args
+        .link_table
+        .as_deref()
+        .map(outbound::link_table::LinkTableWriter::create)
+        .transpose()?;
+    #[cfg(feature = "export-extras")]
+    let mut treebank_writers = args
+        .also_export
+        .iter()
+        .map(|also_export| also_export.writer(args))
+        .collect_vec();
+    let warnings = warnings::Warnings::default();
+    let mut untouched_corpus_names = Vec::new();
+    let mut html_report = report::HtmlReport::default();
+
+    // The conversion is a pipeline of three stages connected by bounded channels, so that they run
+    // concurrently while holding only a bounded number of documents and corpora in between: The
+    // documents are loaded and their TTL files parsed in a separate thread, then aligned and merged
+    // into their corpora in this thread, and the finished corpora are exported in another thread.
+    let corpus_writer = thread::scope(|scope| -> anyhow::Result<_> {
+        // the channels are dropped when returning early, so that the other stages stop as well
+        let (document_sender, document_receiver) = mpsc::sync_channel(DOCUMENTS_LOADED_AHEAD);
+        let (export_sender, export_receiver) = mpsc::sync_channel(CORPORA_EXPORTED_BEHIND);
+
+        let load_stage = scope.spawn({
+            let warnings = &warnings;
+
+            move || {
+                load_documents(
+                    args,
+                    corpus_overrides,
+                    input,
+                    selection,
+                    warnings,
+                    &document_sender,
+                );
+            }
+        });
+
+        let export_stage =
+            scope.spawn(move || export_corpora(corpus_writer, &input.annis_path, &export_receiver));
+
+        for inbound_corpus in input.annis_storage.corpora() {
+            let _corpus_span = info_span!("corpus", corpus_name = inbound_corpus.name()).entered();
+            let corpus_args = corpus_overrides.apply(args, inbound_corpus.name());
+            let args = corpus_args.as_ref();
+            breadcrumbs.enter_corpus(inbound_corpus.name());
+            html_report.begin_corpus(inbound_corpus.name());
+
+            info!(
+                target: SUMMARY_TARGET,
+                corpus_name = inbound_corpus.name(),
+                "processing corpus"
+            );
+
+            let output_corpus_name = match &args.rename {
+                Some(rename_pattern) => rename_pattern.apply(inbound_corpus.name()),
+                None => inbound_corpus.name().into(),
+            };
+
+            let anno_frequencies_before = if args.anno_diff {
+                Some(inbound_corpus.node_anno_frequencies()?)
+            } else {
+                None
+            };
+
+            let mut outbound_corpus = match args.mode {
+                Mode::Merge => outbound::annis::Corpus::from_inbound_corpus(&inbound_corpus),
+                Mode::Standalone => outbound::annis::Corpus::standalone(&inbound_corpus)?,
+            }
+            .with_result_order(args.result_order);
+            let mut update = outbound_corpus.begin_update();
+            let mut alignments = BTreeMap::new();
+            let mut iri_map = Vec::new();
+            let mut empty_node_names = HashSet::new();
+            let mut matched_doc_count = 0;
+
+            let merge_options = MergeOptions {
+                args,
+                profile: &profile,
+                tree_builder: tree_builder.as_ref(),
+                tagset: tagset.as_ref(),
+                feature_splitting: feature_splitting.as_ref(),
+            };
+
+            while let Some(loaded) = match document_receiver.recv() {
+                Ok(loaded) => loaded,
+                // the load stage only stops sending without an error if it panicked
+                Err(_) => {
+                    return Err(match load_stage.join() {
+                        Ok(()) => anyhow!("thread loading documents stopped unexpectedly"),
+                        Err(_) => anyhow!("thread loading documents panicked"),
+                    })
+                }
+            } {
+                let LoadedDocument { annis_doc, ttl_doc } = loaded?;
+                let doc_name = annis_doc.doc_name();
+                let document_span = info_span!(
+                    "document",
+                    doc_name,
+                    edges = field::Empty,
+                    collapsed = field::Empty,
+                    events = field::Empty
+                )
+                .entered();
+
+                breadcrumbs.enter_document(doc_name);
+                progress.document_started(inbound_corpus.name(), doc_name)?;
+
+                let Some(ttl_doc) = ttl_doc? else {
+                    info!(doc_name, "skipping document");
+                    html_report.document_skipped(doc_name);
+                    progress.document_finished(
+                        inbound_corpus.name(),
+                        doc_name,
+                        DocumentStatus::Skipped,
+                    )?;
+                    continue;
+                };
+
+                info!(doc_name, "processing document");
+                breadcrumbs.enter_ttl_file(ttl_doc.path());
+                let event_counts_before = update.event_counts();
+                matched_doc_count += 1;
+                html_report.document_converted(
+                    doc_name,
+                    ttl_doc.sentence_nodes_in_order().count(),
+                    ttl_doc.word_nodes_in_order().count(),
+                );
+
+                ttl_doc.check_order(doc_name, &warnings);
+
+                if args.list_components {
+                    for (component, status) in
+                        annis_doc.components(&profile.segmentation, &component_selection)?
+                    {
+                        info!(doc_name, %component, %status, "component");
+                    }
+                }
+
+                let mut node_name_mapper = NodeNameMapper::new(
+                    &ttl_doc,
+                    &annis_doc,
+                    &alignment,
+                    &args.node_name_template,
+                    args.iri_segment_rules(),
+                    args.dedupe_node_names,
+                    &warnings,
+                )?;
+
+                if artifact_writer.is_some() {
+                    alignments.insert(doc_name.to_owned(), node_name_mapper.alignment());
+                }
+
+                if stop_after == Phase::Align {
+                    progress.document_finished(
+                        inbound_corpus.name(),
+                        doc_name,
+                        DocumentStatus::Converted,
+                    )?;
+                    continue;
+                }
+
+                if args.mode == Mode::Standalone {
+                    add_standalone_document(
+                        &mut update,
+                        inbound_corpus.name(),
+                        &annis_doc,
+                        &ttl_doc,
+                        &node_name_mapper,
+                        &profile.segmentation,
+                    )?;
+                }
+
+                let merged = merge_document(
+                    &mut update,
+                    &annis_doc,
+                    &ttl_doc,
+                    &mut node_name_mapper,
+                    &merge_options,
+                    &warnings,
+                )?;
+                tagset_violations += merged.tagset_violations;
+                iri_map.extend(merged.iri_map);
+                empty_node_names.extend(merged.empty_node_names);
+
+                #[cfg(feature = "export-extras")]
+                if !treebank_writers.is_empty() {
+                    let treebank_doc = treebank_document(
+                        &ttl_doc,
+                        &merged.tree,
+                        &output_corpus_name,
+                        doc_name,
+                        &merged.added_edges,
+                    );
+
+                    for treebank_writer in &mut treebank_writers {
+                        treebank_writer.write(&treebank_doc)?;
+                    }
+                }
+
+                if let Some(link_table_writer) = &mut link_table_writer {
+                    for (iri, annis_node_name, is_word) in node_name_mapper.links() {
+                        link_table_writer.write(&outbound::link_table::Link {
+                            corpus: output_corpus_name.clone(),
+                            original_node_name: is_word.then(|| annis_node_name.into()),
+                            treebank_node_name: node_name::rename_corpus(
+                                annis_node_name,
+                                inbound_corpus.name(),
+                                &output_corpus_name,
+                            )?,
+                            iri: iri.to_string(),
+                        })?;
+                    }
+                }
+
+                if let Some(sidecar_writer) = &sidecar_writer {
+                    sidecar_writer.write(&node_name_mapper.sidecar(
+                        &ttl_doc,
+                        &output_corpus_name,
+                        doc_name,
+                        &merged.added_edges,
+                    )?)?;
+                }
+
+                let event_counts = update.event_counts() - event_counts_before;
+                document_span.record("events", event_counts.total());
+                info!(
+                    doc_name,
+                    nodes = event_counts.nodes,
+                    node_labels = event_counts.node_labels,
+                    edges = event_counts.edges,
+                    other = event_counts.other,
+                    "added updates for document"
+                );
+
+                progress.document_finished(
+                    inbound_corpus.name(),
+                    doc_name,
+                    DocumentStatus::Converted,
+                )?;
+            }
+
+            breadcrumbs.leave_document();
+
+            if matched_doc_count == 0 {
+                warnings.warn(
+                    "corpus without documents matching TTL files",
+                    inbound_corpus.name(),
+                    match args.untouched {
+                        UntouchedPolicy::Warn => "written unchanged",
+                        UntouchedPolicy::Skip => "left out of the output",
+                    },
+                );
+                untouched_corpus_names.push(inbound_corpus.name().to_owned());
+
+                if args.untouched == UntouchedPolicy::Skip {
+                    info!(
+                        target: SUMMARY_TARGET,
+                        corpus_name = inbound_corpus.name(),
+                        "skipping untouched corpus"
+                    );
+                    continue;
+                }
+
+                // the corpus can only be copied as it is if nothing is done to it after merging
+                let can_copy = args.copy_unmatched_corpora
+                    && args.mode == Mode::Merge
+                    && args.rename.is_none()
+                    && stop_after == Phase::Export;
+
+                if let Some(graphml_path) = inbound_corpus.graphml_path().filter(|_| can_copy) {
+                    // the export stage is gone if exporting a corpus failed
+                    if export_sender
+                        .send(Export::Copy(outbound_corpus, graphml_path))
+                        .is_err()
+                    {
+                        break;
+                    }
+
+                    continue;
+                }
+            }
+
+            if let Some(artifact_writer) = &artifact_writer {
+                let corpus_name = Some(inbound_corpus.name());
+                artifact_writer.write_json(corpus_name, "align.json", &alignments)?;
+                html_report.alignment_written(artifact_writer.path(corpus_name, "align.json"));
+            }
+
+            if stop_after == Phase::Align {
+                continue;
+            }
+
+            if let Some(metadata) = &metadata {
+                for (doc_name, doc_node_name) in inbound_corpus.document_node_names_by_name()? {
+                    // standalone corpora contain only the converted documents
+                    let has_doc_node =
+                        args.mode == Mode::Merge || update.is_node_created(&doc_node_name);
+
+                    for (key, value) in metadata.for_document(&doc_name).filter(|_| has_doc_node) {
+                        update.add_node_anno(
+                            doc_node_name.clone(),
+                            args.metadata_ns.clone().unwrap_or_default(),
+                            key.into(),
+                            value,
+                        )?;
+                    }
+
+                    known_doc_names.insert(doc_name);
+                }
+            }
+
+            if let (Some(iri_anno), Some(iri_prefix)) = (&args.iri_anno, &args.iri_prefix) {
+                // <anno_ns>:<iri_anno> = <iri prefix> on the corpus node
+                update.add_node_anno(
+                    inbound_corpus.name().into(),
+                    args.anno_ns(&args.layer).into(),
+                    iri_anno.into(),
+                    iri_prefix.clone(),
+                )?;
+            }
+
+            dump_update(
+                &update,
+                Phase::Merge,
+                artifact_writer.as_ref(),
+                update_dump.as_mut(),
+            )?;
+
+            update.apply()?;
+
+            if stop_after == Phase::Merge {
+                continue;
+            }
+
+            let mut update = outbound_corpus.begin_update();
+
+            // PartOf targets of the segmentation nodes reachable from each layer node
+            let mut targets_by_layer_node: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+            for tree_layer in args.tree_layers() {
+                for m in outbound_corpus.query(&format!(
+                    "annis:layer=\"{}\" >* {}:{} @* {}",
+                    tree_layer.layer,
+                    outbound::annis::DEFAULT_NS,
+                    profile.segmentation,
+                    args.partof_target.query(),
+                ))? {
+                    let [layer_node_name, _, target_node_name] = m
+                        .try_into()
+                        .map_err(|_| anyhow!("unexpected number of nodes in query match"))?;
+
+                    targets_by_layer_node
+                        .entry(layer_node_name)
+                        .or_default()
+                        .insert(target_node_name);
+                }
+            }
+
+            // empty elements cover no segmentation nodes, so they are part of the same target as
+            // their ancestors
+            if !empty_node_names.is_empty() {
+                for tree_layer in args.tree_layers() {
+                    for m in outbound_corpus
+                        .query(&format!("annis:layer=\"{}\" >* node", tree_layer.layer))?
+                    {
+                        let [ancestor_node_name, empty_node_name] = m
+                            .try_into()
+                            .map_err(|_| anyhow!("unexpected number of nodes in query match"))?;
+
+                        if !empty_node_names.contains(&empty_node_name) {
+                            continue;
+                        }
+
+                        let targets = targets_by_layer_node
+                            .get(&ancestor_node_name)
+                            .cloned()
+                            .unwrap_or_default();
+
+                        targets_by_layer_node
+                            .entry(empty_node_name)
+                            .or_default()
+                            .extend(targets);
+                    }
+                }
+            }
+
+            for (layer_node_name, target_node_names) in targets_by_layer_node {
+                let target_node_name =
+                    target_node_names
+                        .into_iter()
+                        .exactly_one()
+                        .map_err(|candidates| {
+                            anyhow!(
+                                "node {layer_node_name} is not part of a unique {}, candidates: {}",
+                                args.partof_target.name(),
+                                candidates.format(", ")
+                            )
+                        })?;
+
+                // PartOf/annis/ from node to datasource or document
+                update.add_edge(
+                    layer_node_name,
+                    target_node_name,
+                    &outbound::annis::AnnotationComponentType::PartOf,
+                    outbound::annis::ANNIS_NS.into(),
+                    "".into(),
+                )?;
+            }
+
+            dump_update(
+                &update,
+                Phase::PartOf,
+                artifact_writer.as_ref(),
+                update_dump.as_mut(),
+            )?;
+
+            update.apply()?;
+
+            if stop_after == Phase::PartOf {
+                continue;
+            }
+
+            if !args.strip_anno.is_empty() {
+                let counts = outbound_corpus.strip_annos(&args.strip_anno, |update| {
+                    dump_update(
+                        update,
+                        Phase::Strip,
+                        artifact_writer.as_ref(),
+                        update_dump.as_mut(),
+                    )
+                })?;
+
+                for (anno_key, count) in counts {
+                    info!(
+                        corpus_name = inbound_corpus.name(),
+                        anno_key, count, "stripped annotations"
+                    );
+                }
+            }
+
+            if stop_after == Phase::Strip {
+                continue;
+            }
+
+            if let Some(rename_pattern) = &args.rename {
+                outbound_corpus.update_name(
+                    |n| rename_pattern.apply(n),
+                    |update| {
+                        dump_update(
+                            update,
+                            Phase::Rename,
+                            artifact_writer.as_ref(),
+                            update_dump.as_mut(),
+                        )
+                    },
+                )?;
+            }
+
+            if stop_after == Phase::Rename {
+                continue;
+            }
+
+            if let Some(anno_frequencies_before) = &anno_frequencies_before {
+                let anno_diff = anno_diff::AnnoDiff::new(
+                    anno_frequencies_before,
+                    &outbound_corpus.node_anno_frequencies()?,
+                    |anno_key| {
+                        anno_key.split_once(':').is_some_and(|(ns, name)| {
+                            args.strip_anno
+                                .iter()
+                                .any(|pattern| pattern.matches(ns, name))
+                        })
+                    },
+                );
+
+                if let Some(artifact_writer) = &artifact_writer {
+                    artifact_writer.write_json(
+                        Some(inbound_corpus.name()),
+                        "anno_diff.json",
+                        &anno_diff,
+                    )?;
+                }
+
+                anno_diff.check(inbound_corpus.name())?;
+            }
+
+            // the exported corpus config is left untouched if there is nothing to change
+            let config = if args.no_visualizer && args.set_config.is_empty() {
+                None
+            } else {
+                let mut config = inbound_corpus.config()?;
+
+                if !args.no_visualizer {
+                    add_visualizers(&mut config, args, &profile.segmentation)?;
+                    hide_iri_annos(&mut config, args)?;
+                }
+
+                for config_setting in &args.set_config {
+                    config_setting.apply(&mut config)?;
+                }
+
+                Some(config)
+            };
+
+            if let Some(iri_map_file) = &args.iri_map_file {
+                let content = iri_map
+                    .iter()
+                    .map(|(annis_node_name, iri)| {
+                        Ok(format!(
+                            "{}\t{iri}\n",
+                            node_name::rename_corpus(
+                                annis_node_name,
+                                inbound_corpus.name(),
+                                &output_corpus_name
+                            )?
+                        ))
+                    })
+                    .collect::<anyhow::Result<String>>()?;
+
+                outbound_corpus.add_linked_file(iri_map_file, content)?;
+            }
+
+            // the export stage is gone if exporting a corpus failed
+            if export_sender
+                .send(Export::Write(outbound_corpus, config))
+                .is_err()
+            {
+                break;
+            }
+        }
+
+        breadcrumbs.leave_corpus();
+        drop(export_sender);
+
+        export_stage
+            .join()
+            .map_err(|_| anyhow!("thread exporting corpora panicked"))?
+    })?;
+
+    if !untouched_corpus_names.is_empty() {
+        info!(
+            target: SUMMARY_TARGET,
+            count = untouched_corpus_names.len(),
+            corpus_names = %untouched_corpus_names.join(", "),
+            "untouched corpora"
+        );
+    }
+
+    if let Some(update_dump) = update_dump {
+        update_dump.finish()?;
+    }
+
+    if let Some(metadata) = metadata.filter(|_| stop_after >= Phase::Merge) {
+        for doc_name in metadata.document_names() {
+            if !known_doc_names.contains(doc_name) {
+                warnings.warn(
+                    "metadata for unknown document",
+                    doc_name,
+                    "document not found in any corpus",
+                );
+            }
+        }
+    }
+
+    if args.strict && tagset_violations > 0 {
+        finish_warnings(args, &html_report, warnings)?;
+        bail!("{tagset_violations} categories not in tagset, see warnings");
+    }
+
+    if stop_after < Phase::Export {
+        info!(phase = stop_after.name(), "stopped without writing output");
+        return finish_warnings(args, &html_report, warnings);
+    }
+
+    corpus_writer.finish()?;
+
+    if let Some(link_table_writer) = link_table_writer {
+        link_table_writer.finish()?;
+    }
+
+    #[cfg(feature = "export-extras")]
+    for treebank_writer in treebank_writers {
+        treebank_writer.finish()?;
+    }
+
+    finish_warnings(args, &html_report, warnings)?;
+
+    if output_buffer_dir.is_some() {
+        remote::upload(&local_output_path, output_path)?;
+    }
+
+    Ok(())
+}
+
+/// Document loaded by the load stage of the conversion pipeline, see [`load_documents`]
+struct LoadedDocument {
+    annis_doc: inbound::annis::Document,
+    /// TTL document used for the ANNIS document, if any, where an error reading it is only reported
+    /// once the conversion reaches the document
+    ttl_doc: anyhow::Result<Option<inbound::ttl::Document>>,
+}
+
+/// Corpus handed over to the export stage of the conversion pipeline, see [`export_corpora`]
+enum Export<'a> {
+    /// Converted corpus to be written with the given config, if any
+    Write(outbound::annis::Corpus<'a>, Option<toml::Table>),
+    /// Untouched corpus to be copied from the GraphML file at the given path within the input
+    Copy(outbound::annis::Corpus<'a>, &'a Path),
+}
+
+/// Load stage of the conversion pipeline: Loads the selected documents of each corpus and reads
+/// the TTL documents used for them, sending them to the conversion followed by `None` after the
+/// documents of each corpus
+///
+/// Stops after the first document that cannot be loaded or if the conversion is gone.
+fn load_documents(
+    args: &Args,
+    corpus_overrides: &overrides::CorpusOverrides,
+    input: &Input,
+    selection: &Selection,
+    warnings: &warnings::Warnings,
+    sender: &mpsc::SyncSender<Option<anyhow::Result<LoadedDocument>>>,
+) {
+    for inbound_corpus in input.annis_storage.corpora() {
+        let corpus_args = corpus_overrides.apply(args, inbound_corpus.name());
+
+        let annis_docs = inbound_corpus
+            .documents(|doc_name| selection.contains(inbound_corpus.name(), doc_name))
+            .take(corpus_args.limit_docs.unwrap_or(usize::MAX));
+
+        for annis_doc in annis_docs {
+            let loaded = annis_doc.map(|annis_doc| {
+                let ttl_doc = input
+                    .ttl_storage
+                    .document_for_name(annis_doc.doc_name(), warnings);
+
+                LoadedDocument { annis_doc, ttl_doc }
+            });
+
+            let failed = loaded.is_err();
+
+            if sender.send(Some(loaded)).is_err() || failed {
+                return;
+            }
+        }
+
+        if sender.send(None).is_err() {
+            return;
+        }
+    }
+}
+
+/// Export stage of the conversion pipeline: Writes the corpora received from the conversion to the
+/// output until the conversion is finished, returning the writer to be finished
+fn export_corpora<'a>(
+    mut corpus_writer: outbound::annis::CorpusWriter<'a>,
+    input_path: &Path,
+    receiver: &mpsc::Receiver<Export<'_>>,
+) -> anyhow::Result<outbound::annis::CorpusWriter<'a>> {
+    for export in receiver {
+        match export {
+            Export::Write(corpus, config) => {
+                corpus_writer.write_corpus(&corpus, config.as_ref())?
+            }
+            Export::Copy(corpus, graphml_path) => {
+                corpus_writer.copy_corpus(&corpus, input_path, graphml_path)?;
+            }
+        }
+    }
+
+    Ok(corpus_writer)
+}
+
+/// Writes the HTML report if requested and finishes the warnings, writing the warnings report if
+/// requested
+fn finish_warnings(
+    args: &Args,
+    html_report: &report::HtmlReport,
+    warnings: warnings::Warnings,
+) -> anyhow::Result<()> {
+    if let Some(html_report_path) = &args.html_report {
+        html_report.write(html_report_path, &warnings)?;
+    }
+
+    warnings.finish(args.warnings_report.as_deref())
+}
+
+/// Looks up, parses and aligns the TTL files of all selected documents without applying any
+/// update, failing with a report of all problems found
+///
+/// The documents are loaded from the ANNIS storage in batches, which are then processed in
+/// parallel. Warnings are not reported here since they are reported during the conversion anyway.
+fn prevalidate(
+    args: &Args,
+    input: &Input,
+    selection: &Selection,
+    alignment: &AlignmentOptions<'_>,
+) -> anyhow::Result<()> {
+    let threads = args.threads();
+    let ttl_storage = &input.ttl_storage;
+    let template = &args.node_name_template;
+    let iri_segment_rules = args.iri_segment_rules();
+    let dedupe = args.dedupe_node_names;
+    let mut problems = Vec::new();
+    let mut count = 0;
+
+    for inbound_corpus in input.annis_storage.corpora() {
+        info!(corpus_name = inbound_corpus.name(), "prevalidating corpus");
+
+        let annis_docs = inbound_corpus
+            .documents(|doc_name| selection.contains(inbound_corpus.name(), doc_name))
+            .take(args.limit_docs.unwrap_or(usize::MAX));
+
+        for batch in &annis_docs.chunks(threads) {
+            let batch = batch.collect::<anyhow::Result<Vec<_>>>()?;
+            count += batch.len();
+
+            let results = thread::scope(|scope| {
+                let handles = batch
+                    .iter()
+                    .map(|annis_doc| {
+                        scope.spawn(move || -> anyhow::Result<()> {
+                            let warnings = warnings::Warnings::default();
+                            let Some(ttl_doc) =
+                                ttl_storage.document_for_name(annis_doc.doc_name(), &warnings)?
+                            else {
+                                return Ok(());
+                            };
+
+                            NodeNameMapper::new(
+                                &ttl_doc,
+                                annis_doc,
+                                alignment,
+                                template,
+                                iri_segment_rules,
+                                dedupe,
+                                &warnings,
+                            )?;
+
+                            Ok(())
+                        })
+                    })
+                    .collect_vec();
+
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .map_err(|_| anyhow!("thread prevalidating document panicked"))
+                    })
+                    .collect_vec()
+            });
+
+            for (annis_doc, result) in batch.iter().zip(results) {
+                if let Err(err) = result.and_then(|result| result) {
+                    let doc_name = annis_doc.doc_name();
+                    error!(corpus_name = inbound_corpus.name(), doc_name, "{err}");
+                    problems.push(doc_name.to_owned());
+                }
+            }
+        }
+    }
+
+    ensure!(
+        problems.is_empty(),
+        "prevalidation failed for {} of {count} documents: {}",
+        problems.len(),
+        problems.join(", ")
+    );
+
+    info!(count, "prevalidated documents");
+
+    Ok(())
+}
+
+/// Options for aligning TTL words with ANNIS segmentation nodes
+struct AlignmentOptions<'a> {
+    component_selection: &'a inbound::annis::ComponentSelection,
+    skip_filters: &'a [inbound::annis::TokenFilter],
+    profile: &'a rem::Profile,
+    anchors: &'a inbound::anchors::Anchors,
+    max_gap: usize,
+}
+
+/// Resolves the given anchors into pairs of positions within `ttl_words` and `annis_nodes`
+fn resolve_anchors(
+    ttl_words: &[inbound::ttl::Node<'_>],
+    annis_nodes: &[inbound::annis::Node<'_>],
+    anchors: &[inbound::anchors::Anchor],
+) -> anyhow::Result<Vec<(usize, usize)>> {
+    if anchors.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ttl_indices: HashMap<&str, usize> = ttl_words
+        .iter()
+        .enumerate()
+        .map(|(i, ttl_word)| (ttl_word.node_name().as_ref(), i))
+        .collect();
+
+    let annis_indices: HashMap<String, usize> = annis_nodes
+        .iter()
+        .enumerate()
+        .map(|(i, annis_node)| Ok((annis_node.name()?.into_owned_name(), i)))
+        .collect::<anyhow::Result<_>>()?;
+
+    anchors
+        .iter()
+        .map(|anchor| {
+            let ttl_index = *ttl_indices
+                .get(anchor.ttl.as_str())
+                .ok_or_else(|| anyhow!("anchor ttl word {} not found", anchor.ttl))?;
+
+            let annis_index = match &anchor.annis {
+                inbound::anchors::AnnisTarget::NodeName { annis } => {
+                    *annis_indices.get(annis).ok_or_else(|| {
+                        anyhow!("anchor ANNIS node {annis} is not a segmentation node")
+                    })?
+                }
+                inbound::anchors::AnnisTarget::Index { annis_index } => {
+                    ensure!(
+                        *annis_index < annis_nodes.len(),
+                        "anchor ANNIS index {annis_index} is out of range, there are only {} \
+                         segmentation nodes",
+                        annis_nodes.len()
+                    );
+                    *annis_index
+                }
+            };
+
+            Ok((ttl_index, annis_index))
+        })
+        .collect()
+}
+
+/// TTL words and ANNIS segmentation nodes of a document to be aligned
+struct DocumentTokens<'a, 'b> {
+    ttl_words: Vec<inbound::ttl::Node<'b>>,
+    annis_nodes: Vec<inbound::annis::Node<'a>>,
+    skip_filters: &'b [inbound::annis::TokenFilter],
+    profile: &'b rem::Profile,
+    /// Key compared with the TTL `WORD`, see [`norm_anno_key`]
+    norm_anno_key: &'b inbound::annis::AnnoKey,
+}
+
+impl align::Tokens for DocumentTokens<'_, '_> {
+    fn ttl_len(&self) -> usize {
+        self.ttl_words.len()
+    }
+
+    fn annis_len(&self) -> usize {
+        self.annis_nodes.len()
+    }
+
+    fn ttl_name(&self, ttl_index: usize) -> String {
+        self.ttl_words[ttl_index].node_name().to_string()
+    }
+
+    fn mismatch(&self, ttl_index: usize, annis_index: usize) -> anyhow::Result<Option<String>> {
+        anno_mismatch(
+            self.ttl_words[ttl_index],
+            &self.annis_nodes[annis_index],
+            self.profile,
+            self.norm_anno_key,
+        )
+    }
+
+    fn resyncs(&self, ttl_index: usize, annis_index: usize) -> anyhow::Result<bool> {
+        Ok(resync_mismatch(
+            self.ttl_words[ttl_index],
+            &self.annis_nodes[annis_index],
+            self.profile,
+            self.norm_anno_key,
+        )?
+        .is_none())
+    }
+
+    fn is_skippable(&self, annis_index: usize) -> anyhow::Result<bool> {
+        // ANNIS nodes matching a skip filter (e.g. punctuation missing in TTL) are skipped rather
+        // than aligned
+        self.skip_filters
+            .iter()
+            .map(|filter| filter.matches(&self.annis_nodes[annis_index]))
+            .fold_ok(false, |acc, matches| acc || matches)
+    }
+}
+
+/// Options for merging the trees of a TTL document into an ANNIS document, see [`merge_document`]
+struct MergeOptions<'a> {
+    args: &'a Args,
+    profile: &'a rem::Profile,
+    tree_builder: &'a dyn tree_builder::TreeBuilder,
+    tagset: Option<&'a inbound::tagset::Tagset>,
+    feature_splitting: Option<&'a features::FeatureSplitting>,
+}
+
+/// Result of [`merge_document`]
+struct MergedDocument<'t> {
+    /// Trees of the document that were merged
+    #[cfg_attr(not(feature = "export-extras"), allow(dead_code))]
+    tree: tree_builder::Tree<'t>,
+    /// Dominance edges added to the update, from child to parent
+    added_edges: Vec<(&'t inbound::ttl::NodeName, &'t inbound::ttl::NodeName)>,
+    /// ANNIS node names and IRIs of the TTL nodes added to the update, if `--iri-map-file` is
+    /// specified
+    iri_map: Vec<(String, String)>,
+    /// Number of parts of categories not contained in the tagset
+    tagset_violations: usize,
+    /// ANNIS node names of the empty elements annotated with `--empty-element-anno`
+    empty_node_names: Vec<String>,
+}
+
+/// Adds the updates merging the TTL document into the ANNIS document aligned by
+/// `node_name_mapper`, i.e. the annotations of the document node and the words, and the trees
+/// with their nonterminals and dominance and pointing edges
+///
+/// This is shared by the conversion of whole corpora and by [`convert_document`].
+fn merge_document<'t>(
+    update: &mut outbound::annis::Update<'_>,
+    annis_doc: &inbound::annis::Document,
+    ttl_doc: &'t inbound::ttl::Document,
+    node_name_mapper: &mut NodeNameMapper<'_>,
+    options: &MergeOptions<'_>,
+    warnings: &warnings::Warnings,
+) -> anyhow::Result<MergedDocument<'t>> {
+    let args = options.args;
+    let doc_name = annis_doc.doc_name();
+    let mut iri_map = Vec::new();
+    let mut tagset_violations = 0;
+    let mut empty_node_names = Vec::new();
+
+    let doc_iri_prefix = ttl_doc.iri_prefix().filter(|_| args.document_iri_prefix);
+
+    if let (Some(iri_anno), Some(doc_iri_prefix)) = (&args.iri_anno, doc_iri_prefix) {
+        // <anno_ns>:<iri_anno> = <IRI prefix of the document> on the document node
+        update.add_node_anno(
+            annis_doc.node_name().into_owned_name(),
+            args.anno_ns(&args.layer).into(),
+            iri_anno.into(),
+            doc_iri_prefix.into(),
+        )?;
+    }
+
+    if let Some(changelog_anno) = &args.changelog_anno {
+        update.add_node_anno(
+            annis_doc.node_name().into_owned_name(),
+            changelog_anno.ns.clone(),
+            changelog_anno.name.clone(),
+            format!(
+                "{} {} ({}, ttl sha256:{})",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                OffsetDateTime::now_utc().date(),
+                ttl_doc.file_hash()?,
+            ),
+        )?;
+    }
+
+    if args.rebuild_tok {
+        for ttl_word in ttl_doc.word_nodes_in_order() {
+            let Some(word) = ttl_word.anno(inbound::ttl::AnnoKey::Word) else {
+                warnings.warn(
+                    "missing WORD value for rebuilding token annotation",
+                    doc_name,
+                    ttl_word.node_name().to_string(),
+                );
+                continue;
+            };
+
+            update.add_node_anno(
+                node_name_mapper.annis_node_name(ttl_word, update)?,
+                args.tok_anno.ns.clone(),
+                args.tok_anno.name.clone(),
+                word.into(),
+            )?;
+        }
+    }
+
+    for &token_anno in &args.overwrite_token_anno {
+        let (ttl_anno_key, annis_anno_key) = token_anno.anno_keys(options.profile);
+
+        for ttl_word in ttl_doc.word_nodes_in_order() {
+            let (Some(ttl_value), Some(annis_node_name)) = (
+                ttl_word.anno(ttl_anno_key),
+                node_name_mapper.aligned_name(ttl_word),
+            ) else {
+                continue;
+            };
+
+            let annis_value = match annis_doc.node(annis_node_name)? {
+                Some(annis_node) => annis_node.anno(annis_anno_key)?.map(Cow::into_owned),
+                None => None,
+            };
+
+            // <profile anno> = <TTL value>
+            update.add_node_anno(
+                annis_node_name.into(),
+                annis_anno_key.ns.to_string(),
+                annis_anno_key.name.to_string(),
+                ttl_value.into(),
+            )?;
+
+            let Some(annis_value) = annis_value.filter(|value| value != ttl_value) else {
+                continue;
+            };
+
+            // <profile anno><source_anno_suffix> = ttl
+            if let Some(suffix) = &args.source_anno_suffix {
+                update.add_node_anno(
+                    annis_node_name.into(),
+                    annis_anno_key.ns.to_string(),
+                    format!("{}{suffix}", annis_anno_key.name),
+                    "ttl".into(),
+                )?;
+            }
+
+            // <profile anno><other_value_suffix> = <previous ANNIS value>
+            if let Some(suffix) = &args.other_value_suffix {
+                update.add_node_anno(
+                    annis_node_name.into(),
+                    annis_anno_key.ns.to_string(),
+                    format!("{}{suffix}", annis_anno_key.name),
+                    annis_value,
+                )?;
+            }
+        }
+    }
+
+    if let Some(feature_splitting) = options
+        .feature_splitting
+        .filter(|f| f.applies_to(features::FeatureTarget::Words))
+    {
+        for ttl_word in ttl_doc.word_nodes_in_order() {
+            let annis_node_name = node_name_mapper.annis_node_name(ttl_word, update)?;
+
+            add_infl_features(
+                update,
+                feature_splitting,
+                ttl_word,
+                &annis_node_name,
+                args.anno_ns(&args.layer),
+                doc_name,
+                warnings,
+            )?;
+        }
+    }
+
+    // <anno_ns>:<lang_anno> = <language tag of WORD>
+    if let Some(lang_anno) = &args.lang_anno {
+        for ttl_word in ttl_doc.word_nodes_in_order() {
+            if let Some(language) = ttl_word.anno_language(inbound::ttl::AnnoKey::Word) {
+                update.add_node_anno(
+                    node_name_mapper.annis_node_name(ttl_word, update)?,
+                    args.anno_ns(&args.layer).into(),
+                    lang_anno.clone(),
+                    language.into(),
+                )?;
+            }
+        }
+    }
+
+    let excluded_sentences = excluded_sentences(ttl_doc, node_name_mapper, args);
+
+    if !excluded_sentences.is_empty() {
+        info!(
+            doc_name,
+            count = excluded_sentences.len(),
+            "excluded sentences from merging"
+        );
+    }
+
+    // Add all edges that are reachable from words, where the edges of different sentences are
+    // partitioned since trees never span sentences
+    let mut edges_by_sentence: BTreeMap<Option<usize>, Vec<_>> = BTreeMap::new();
+
+    let parent_edges = ttl_doc.resolved_parent_edges(args.multi_parent, doc_name, warnings)?;
+
+    let mut cross_sentence_edges = Vec::new();
+
+    for &(child, parent) in &parent_edges.primary {
+        let sentence_index = node_name_mapper.sentence_index(child);
+
+        // skip trees of excluded sentences
+        if sentence_index.is_some_and(|index| excluded_sentences.contains(&index)) {
+            continue;
+        }
+
+        if let (Some(child_index), Some(parent_index)) =
+            (sentence_index, node_name_mapper.sentence_index(parent))
+        {
+            if child_index != parent_index {
+                let details = format!(
+                    "{} (sentence {child_index}) -> {} (sentence {parent_index})",
+                    child.node_name(),
+                    parent.node_name()
+                );
+
+                match args.cross_sentence {
+                    CrossSentencePolicy::Error => {
+                        bail!("hasParent edge across sentences: {details}");
+                    }
+                    CrossSentencePolicy::Drop => {}
+                    CrossSentencePolicy::Pointing => {
+                        cross_sentence_edges.push((child, parent));
+                    }
+                }
+
+                warnings.warn("hasParent edge across sentences", doc_name, details);
+                continue;
+            }
+        }
+
+        edges_by_sentence
+            .entry(sentence_index)
+            .or_default()
+            .push((child, parent));
+    }
+
+    let edges_by_sentence = edges_by_sentence.into_values().collect_vec();
+    let mut ttl_node_names: HashSet<inbound::ttl::NodeName> = HashSet::new();
+    let mut added_edges = Vec::new();
+    let mut tree =
+        tree_builder::document_tree(options.tree_builder, &edges_by_sentence, args.threads())?;
+    Span::current().record("edges", tree.edges.len());
+    Span::current().record("collapsed", tree.collapsed);
+
+    if let Some(max_edge_depth) = args.max_edge_depth {
+        tree.check_depth(max_edge_depth)?;
+    }
+
+    let mut discontinuous_nodes = HashSet::new();
+
+    if let Some(policy) = args.discontinuous {
+        let word_positions: HashMap<_, _> = ttl_doc
+            .word_nodes_in_order()
+            .enumerate()
+            .map(|(position, ttl_word)| (ttl_word.node_name(), position))
+            .collect();
+
+        let mut discontinuous_sentences = HashSet::new();
+
+        for ttl_node in tree.discontinuous_nodes(&word_positions) {
+            if policy != DiscontinuityPolicy::Allow {
+                warnings.warn(
+                    "treebank node with discontinuous yield",
+                    doc_name,
+                    ttl_node.node_name().to_string(),
+                );
+            }
+
+            discontinuous_sentences.insert(node_name_mapper.sentence_index(ttl_node));
+            discontinuous_nodes.insert(ttl_node.node_name());
+        }
+
+        if policy == DiscontinuityPolicy::Exclude && !discontinuous_sentences.is_empty() {
+            tree.edges.retain(|&(child, _)| {
+                !discontinuous_sentences.contains(&node_name_mapper.sentence_index(child))
+            });
+
+            info!(
+                doc_name,
+                count = discontinuous_sentences.len(),
+                "excluded trees with discontinuous yields"
+            );
+        }
+
+        if policy == DiscontinuityPolicy::Allow && !discontinuous_nodes.is_empty() {
+            info!(
+                doc_name,
+                count = discontinuous_nodes.len(),
+                "kept treebank nodes with discontinuous yields"
+            );
+        }
+
+        if !matches!(
+            policy,
+            DiscontinuityPolicy::Mark | DiscontinuityPolicy::Allow
+        ) {
+            discontinuous_nodes.clear();
+        }
+    }
+
+    if tree.collapsed > 0 {
+        info!(
+            doc_name,
+            count = tree.collapsed,
+            "collapsed nonterminals in unary chains"
+        );
+    }
+
+    for &(child, parent) in &tree.edges {
+        for ttl_node in [child, parent] {
+            if ttl_node_names.insert(ttl_node.node_name().clone()) {
+                let annis_node_name = node_name_mapper.annis_node_name(ttl_node, update)?;
+
+                let tree_layer = if ttl_node.is_word() {
+                    None
+                } else {
+                    Some(args.tree_layer(ttl_node))
+                };
+
+                if let Some(tree_layer) = tree_layer {
+                    update.add_node(annis_node_name.clone(), outbound::annis::NODE.into())?;
+
+                    // annis:layer = <layer>
+                    update.add_node_anno(
+                        annis_node_name.clone(),
+                        outbound::annis::ANNIS_NS.into(),
+                        outbound::annis::LAYER.into(),
+                        tree_layer.layer.into(),
+                    )?;
+
+                    // <anno_ns>:<tree_anno> = <cat>
+                    if let Some(cat) = tree.category(ttl_node) {
+                        if let Some(tagset) = options.tagset {
+                            for part in tagset.invalid_parts(cat, args.collapse_unary.as_deref()) {
+                                warnings.warn(
+                                    "category not in tagset",
+                                    part,
+                                    format!("{doc_name}: {}", ttl_node.node_name()),
+                                );
+                                tagset_violations += 1;
+                            }
+                        }
+
+                        update.add_node_anno(
+                            annis_node_name.clone(),
+                            tree_layer.anno_ns.into(),
+                            tree_layer.anno.into(),
+                            cat.into(),
+                        )?;
+                    }
+
+                    // <empty_element_anno> = true
+                    if let Some(empty_element_anno) = &args.empty_element_anno {
+                        if ttl_node.is_empty_element() {
+                            update.add_node_anno(
+                                annis_node_name.clone(),
+                                empty_element_anno.ns.clone(),
+                                empty_element_anno.name.clone(),
+                                "true".into(),
+                            )?;
+                            empty_node_names.push(annis_node_name.clone());
+                        }
+                    }
+
+                    // <anno_ns>:<lang_anno> = <language tag of CAT>
+                    if let (Some(lang_anno), Some(language)) = (
+                        &args.lang_anno,
+                        ttl_node.anno_language(inbound::ttl::AnnoKey::Cat),
+                    ) {
+                        update.add_node_anno(
+                            annis_node_name.clone(),
+                            tree_layer.anno_ns.into(),
+                            lang_anno.clone(),
+                            language.into(),
+                        )?;
+                    }
+
+                    // <anno_ns>:<prefix>.<feature> = <value>
+                    if let Some(feature_splitting) = options
+                        .feature_splitting
+                        .filter(|f| f.applies_to(features::FeatureTarget::Nonterminals))
+                    {
+                        add_infl_features(
+                            update,
+                            feature_splitting,
+                            ttl_node,
+                            &annis_node_name,
+                            tree_layer.anno_ns,
+                            doc_name,
+                            warnings,
+                        )?;
+                    }
+
+                    // <discontinuous_anno> = true
+                    if discontinuous_nodes.contains(ttl_node.node_name()) {
+                        update.add_node_anno(
+                            annis_node_name.clone(),
+                            args.discontinuous_anno.ns.clone(),
+                            args.discontinuous_anno.name.clone(),
+                            "true".into(),
+                        )?;
+                    }
+
+                    // <anno_ns>:<sentence_number_anno> = <sentence index>
+                    if let Some(sentence_number_anno) = &args.sentence_number_anno {
+                        if let Some(sentence_index) = node_name_mapper.sentence_index(ttl_node) {
+                            update.add_node_anno(
+                                annis_node_name.clone(),
+                                tree_layer.anno_ns.into(),
+                                sentence_number_anno.into(),
+                                sentence_index.to_string(),
+                            )?;
+                        }
+                    }
+
+                    // <anno_ns>:<sentence_iri_anno> = <sentence IRI>
+                    if let Some(sentence_iri_anno) = &args.sentence_iri_anno {
+                        if let Some(sentence_iri) = node_name_mapper.sentence_iri(ttl_node) {
+                            update.add_node_anno(
+                                annis_node_name.clone(),
+                                tree_layer.anno_ns.into(),
+                                sentence_iri_anno.into(),
+                                sentence_iri.to_string(),
+                            )?;
+                        }
+                    }
+                }
+
+                if args.iri_map_file.is_some() {
+                    iri_map.push((annis_node_name.clone(), ttl_node.node_name().to_string()));
+                }
+
+                if let Some(iri_anno) = args
+                    .iri_anno
+                    .as_ref()
+                    .filter(|_| args.iri_anno_on.includes(ttl_node.is_word()))
+                {
+                    let iri = ttl_node.node_name().as_ref();
+
+                    // <anno_ns>:<iri_anno> = <iri> (without prefix)
+                    update.add_node_anno(
+                        annis_node_name.clone(),
+                        tree_layer
+                            .map_or(args.anno_ns(&args.layer), |l| l.anno_ns)
+                            .into(),
+                        iri_anno.into(),
+                        match &args.iri_prefix {
+                            Some(prefix) => {
+                                iri.strip_prefix(prefix.as_str()).unwrap_or_else(|| {
+                                    warnings.warn("IRI does not start with prefix", doc_name, iri);
+                                    iri
+                                })
+                            }
+                            None => doc_iri_prefix
+                                .and_then(|prefix| iri.strip_prefix(prefix))
+                                .unwrap_or(iri),
+                        }
+                        .into(),
+                    )?;
+                }
+            }
+        }
+
+        // Dominance/<layer>/ from parent to child, where <layer> is the layer of
+        // the parent
+        let parent_node_name = node_name_mapper.annis_node_name(parent, update)?;
+        let child_node_name = node_name_mapper.annis_node_name(child, update)?;
+
+        update.add_edge(
+            parent_node_name,
+            child_node_name,
+            &outbound::annis::AnnotationComponentType::Dominance,
+            args.tree_layer(parent).layer.into(),
+            "".into(),
+        )?;
+
+        added_edges.push((child.node_name(), parent.node_name()));
+    }
+
+    // Pointing/<layer>/<name> from parent to child for edges to additional parents (name
+    // `secedge`) and across sentences (name `cross_sentence`), where <layer> is the layer of the
+    // parent, if both are part of the trees
+    let pointing_edges = parent_edges
+        .secondary
+        .iter()
+        .map(|&edge| (edge, "secedge"))
+        .chain(
+            cross_sentence_edges
+                .iter()
+                .map(|&edge| (edge, "cross_sentence")),
+        );
+
+    for ((child, parent), component_name) in pointing_edges {
+        if !ttl_node_names.contains(child.node_name())
+            || !ttl_node_names.contains(parent.node_name())
+        {
+            continue;
+        }
+
+        let parent_node_name = node_name_mapper.annis_node_name(parent, update)?;
+        let child_node_name = node_name_mapper.annis_node_name(child, update)?;
+
+        update.add_edge(
+            parent_node_name,
+            child_node_name,
+            &outbound::annis::AnnotationComponentType::Pointing,
+            args.tree_layer(parent).layer.into(),
+            component_name.into(),
+        )?;
+    }
+
+    if let Some(tree_stats_ns) = &args.tree_stats_ns {
+        let subtree_stats = tree_stats::subtree_stats(&added_edges);
+
+        for &(_, parent) in added_edges.iter().unique_by(|(_, parent)| *parent) {
+            let (Some(annis_node_name), Some(stats)) = (
+                node_name_mapper.nonterminal_name(parent),
+                subtree_stats.get(&parent),
+            ) else {
+                continue;
+            };
+
+            // <tree_stats_ns>:depth = <depth>
+            update.add_node_anno(
+                annis_node_name.into(),
+                tree_stats_ns.clone(),
+                "depth".into(),
+                stats.depth.to_string(),
+            )?;
+
+            // <tree_stats_ns>:yield = <yield size>
+            update.add_node_anno(
+                annis_node_name.into(),
+                tree_stats_ns.clone(),
+                "yield".into(),
+                stats.yield_size.to_string(),
+            )?;
+        }
+    }
+
+    Ok(MergedDocument {
+        tree,
+        added_edges,
+        iri_map,
+        tagset_violations,
+        empty_node_names,
+    })
+}
+
+/// 1-based indices of the sentences of the given document whose trees are excluded from being
+/// merged by `--filter-cat` or `--min-sentence-length`
+fn excluded_sentences(
+    ttl_doc: &inbound::ttl::Document,
+    node_name_mapper: &NodeNameMapper<'_>,
+    args: &Args,
+) -> HashSet<usize> {
+    let mut excluded_sentences = HashSet::new();
+
+    if let Some(filter_cat) = &args.filter_cat {
+        for (child, parent) in ttl_doc.parent_edges() {
+            // tree roots are the nonterminal children of sentence roots, which have no `CAT`
+            // annotation
+            if child.is_word() || parent.anno(inbound::ttl::AnnoKey::Cat).is_some() {
+                continue;
+            }
+
+            if let (Some(cat), Some(index)) = (
+                child.anno(inbound::ttl::AnnoKey::Cat),
+                node_name_mapper.sentence_index(child),
+            ) {
+                if filter_cat.matches(cat) {
+                    excluded_sentences.insert(index);
+                }
+            }
+        }
+    }
+
+    if args.min_sentence_length > 0 {
+        let sentence_lengths = ttl_doc
+            .word_nodes_in_order()
+            .filter_map(|ttl_word| node_name_mapper.sentence_index(ttl_word))
+            .counts();
+
+        for index in 1..=ttl_doc.sentence_nodes_in_order().count() {
+            if sentence_lengths.get(&index).copied().unwrap_or(0) < args.min_sentence_length {
+                excluded_sentences.insert(index);
+            }
+        }
+    }
+
+    excluded_sentences
+}
+
+/// Dumps an update generated in the given phase to the artifacts (one file per corpus and phase)
+/// and to the update dump, if any
+fn dump_update(
+    update: &outbound::annis::Update<'_>,
+    phase: Phase,
+    artifact_writer: Option<&outbound::artifacts::ArtifactWriter>,
+    update_dump: Option<&mut outbound::annis::UpdateDump>,
+) -> anyhow::Result<()> {
+    if let Some(artifact_writer) = artifact_writer {
+        update.dump(&artifact_writer.path(
+            Some(update.corpus_name()),
+            &format!("{}.jsonl", phase.name()),
+        ))?;
+    }
+
+    if let Some(update_dump) = update_dump {
+        update_dump.write(update)?;
+    }
+
+    Ok(())
+}
+
+/// Adds the morphological features of the `INFL` value of the given TTL node, if any, as
+/// annotations `<anno_ns>:<prefix>.<feature>` of the given ANNIS node
+fn add_infl_features(
+    update: &mut outbound::annis::Update<'_>,
+    feature_splitting: &features::FeatureSplitting,
+    ttl_node: inbound::ttl::Node<'_>,
+    annis_node_name: &str,
+    anno_ns: &str,
+    doc_name: &str,
+    warnings: &warnings::Warnings,
+) -> anyhow::Result<()> {
+    let Some(infl) = ttl_node.anno(inbound::ttl::AnnoKey::Infl) else {
+        return Ok(());
+    };
+
+    match feature_splitting.split(infl) {
+        Ok(features) => {
+            for (name, value) in features {
+                update.add_node_anno(annis_node_name.into(), anno_ns.into(), name, value.into())?;
+            }
+        }
+        Err(reason) => warnings.warn(
+            "INFL value not matching feature splitting rule",
+            doc_name,
+            format!("{}: {infl} ({reason})", ttl_node.node_name()),
+        ),
+    }
+
+    Ok(())
+}
+
+/// Adds a tree visualizer for each tree layer to the given corpus config
+fn add_visualizers(
+    config: &mut toml::Table,
+    args: &Args,
+    segmentation: &str,
+) -> anyhow::Result<()> {
+    let visualizers = config
+        .entry("visualizers")
+        .or_insert_with(|| toml::value::Array::new().into())
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("invalid corpus config: `visualizers` is not an array"))?;
+
+    for tree_layer in args.tree_layers() {
+        visualizers.push({
+            let entries: [(String, toml::Value); 6] = [
+                ("display_name".into(), tree_layer.display.into()),
+                ("element".into(), "node".into()),
+                ("layer".into(), tree_layer.layer.into()),
+                ("vis_type".into(), "tree".into()),
+                ("visibility".into(), "hidden".into()),
+                ("mappings".into(), {
+                    let entries = [
+                        ("edge_type".into(), "null".into()),
+                        ("node_anno_ns".into(), tree_layer.anno_ns.into()),
+                        ("node_key".into(), tree_layer.anno.into()),
+                        ("terminal_ns".into(), outbound::annis::DEFAULT_NS.into()),
+                        ("terminal_name".into(), segmentation.into()),
+                    ];
+                    let mut mappings = entries.into_iter().collect::<toml::Table>();
+                    for mapping in &args.tree_mapping {
+                        mappings.insert(mapping.key.clone(), mapping.value.clone().into());
+                    }
+                    mappings.into()
+                }),
+            ];
+            entries.into_iter().collect::<toml::Table>().into()
+        });
+    }
+
+    Ok(())
+}
+
+/// Adds the annotations given by `--iri-anno` and `--sentence-iri-anno` (in the namespaces of all
+/// tree layers) to the annotations hidden by default in the search results (`view.hidden_annos`)
+/// of the given corpus config, so that they are only shown when requested
+fn hide_iri_annos(config: &mut toml::Table, args: &Args) -> anyhow::Result<()> {
+    let anno_names = [&args.iri_anno, &args.sentence_iri_anno]
+        .into_iter()
+        .flatten()
+        .flat_map(|name| {
+            args.tree_layers()
+                .into_iter()
+                .map(move |tree_layer| format!("{}::{name}", tree_layer.anno_ns))
+        })
+        .unique()
+        .collect_vec();
+
+    if anno_names.is_empty() {
+        return Ok(());
+    }
+
+    let hidden_annos = config
+        .entry("view")
+        .or_insert_with(|| toml::Table::new().into())
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("invalid corpus config: `view` is not a table"))?
+        .entry("hidden_annos")
+        .or_insert_with(|| toml::value::Array::new().into())
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("invalid corpus config: `view.hidden_annos` is not an array"))?;
+
+    for anno_name in anno_names {
+        if !hidden_annos
+            .iter()
+            .any(|value| value.as_str() == Some(&anno_name))
+        {
+            hidden_annos.push(anno_name.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Compares the annotations common to a TTL word and an ANNIS segmentation node to make sure that
+/// they correspond to each other, returning a description of the first mismatch if any
+///
+/// The TTL `WORD` is compared with `norm_anno_key`, see [`norm_anno_key`].
+fn anno_mismatch(
+    ttl_node: inbound::ttl::Node<'_>,
+    annis_node: &inbound::annis::Node<'_>,
+    profile: &rem::Profile,
+    norm_anno_key: &inbound::annis::AnnoKey,
+) -> anyhow::Result<Option<String>> {
+    anno_mismatch_for(
+        ttl_node,
+        annis_node,
+        &profile.sanitization,
+        &[
+            (inbound::ttl::AnnoKey::Infl, &profile.anno_key_inflection),
+            (inbound::ttl::AnnoKey::Lemma, &profile.anno_key_lemma),
+            (inbound::ttl::AnnoKey::Word, norm_anno_key),
+            (inbound::ttl::AnnoKey::Pos, &profile.anno_key_pos),
+        ],
+    )
+}
+
+/// Compares only the lemma and normalized form of a TTL word and an ANNIS segmentation node, which
+/// is enough to re-synchronize the alignment on them after a gap
+fn resync_mismatch(
+    ttl_node: inbound::ttl::Node<'_>,
+    annis_node: &inbound::annis::Node<'_>,
+    profile: &rem::Profile,
+    norm_anno_key: &inbound::annis::AnnoKey,
+) -> anyhow::Result<Option<String>> {
+    anno_mismatch_for(
+        ttl_node,
+        annis_node,
+        &profile.sanitization,
+        &[
+            (inbound::ttl::AnnoKey::Lemma, &profile.anno_key_lemma),
+            (inbound::ttl::AnnoKey::Word, norm_anno_key),
+        ],
+    )
+}
+
+/// Key of the annotation of the given segmentation nodes of a document that is compared with the
+/// TTL `WORD`, i.e. the first of the normalized form and its fallbacks (see
+/// [`rem::Profile::anno_keys_norm_fallback`]) that any of the nodes has
+///
+/// If none of them is present, the normalized form is used, so that the mismatch is reported.
+fn norm_anno_key<'p>(
+    annis_nodes: &[inbound::annis::Node<'_>],
+    profile: &'p rem::Profile,
+) -> anyhow::Result<&'p inbound::annis::AnnoKey> {
+    for anno_key in std::iter::once(&profile.anno_key_norm).chain(&profile.anno_keys_norm_fallback)
+    {
+        for annis_node in annis_nodes {
+            if annis_node.anno(anno_key)?.is_some() {
+                return Ok(anno_key);
+            }
+        }
+    }
+
+    Ok(&profile.anno_key_norm)
+}
+
+/// Compares the given annotations (pairs of TTL and ANNIS annotation keys) of a TTL word and an
+/// ANNIS segmentation node, returning a description of the first mismatch if any
+fn anno_mismatch_for(
+    ttl_node: inbound::ttl::Node<'_>,
+    annis_node: &inbound::annis::Node<'_>,
+    sanitization: &rem::Sanitization,
+    anno_keys: &[(inbound::ttl::AnnoKey, &inbound::annis::AnnoKey)],
+) -> anyhow::Result<Option<String>> {
+    for &(ttl_anno_key, annis_anno_key) in anno_keys {
+        let ttl_anno = ttl_node
+            .anno(ttl_anno_key)
+            .map(|s| rem::normalize_whitespace(s).replace("&quot;", "\""));
+        let annis_anno = annis_node.anno(annis_anno_key)?;
+        let annis_anno = sanitization.sanitize(annis_anno.as_deref());
+
+        if ttl_anno.as_deref() != annis_anno.as_deref() {
+            return Ok(Some(format!(
+                "{} for {} and {} doesn't match: '{}' != '{}'",
+                annis_anno_key.name,
+                ttl_node.node_name(),
+                annis_node.name()?,
+                ttl_anno.as_deref().unwrap_or(""),
+                annis_anno.as_deref().unwrap_or(""),
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Collects the treebank layer of a document for the additional exports, where the tokens are the
+/// TTL words
+#[cfg(feature = "export-extras")]
+fn treebank_document(
+    ttl_doc: &inbound::ttl::Document,
+    tree: &tree_builder::Tree<'_>,
+    corpus_name: &str,
+    doc_name: &str,
+    added_edges: &[(&inbound::ttl::NodeName, &inbound::ttl::NodeName)],
+) -> outbound::treebank::Document {
+    let words = ttl_doc.word_nodes_in_order().collect_vec();
+
+    let token_indices: HashMap<_, _> = words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| (word.node_name(), i))
+        .collect();
+
+    let mut nonterminal_indices = HashMap::new();
+    let mut nonterminals = Vec::new();
+
+    let mut nonterminal_index = |node_name| {
+        *nonterminal_indices.entry(node_name).or_insert_with(|| {
+            nonterminals.push(
+                tree.category(ttl_doc.node_for_name(node_name))
+                    .map(Into::into),
+            );
+            nonterminals.len() - 1
+        })
+    };
+
+    let edges = added_edges
+        .iter()
+        .map(|&(child, parent)| {
+            let child = match token_indices.get(child) {
+                Some(&i) => outbound::treebank::Child::Token(i),
+                None => outbound::treebank::Child::Nonterminal(nonterminal_index(child)),
+            };
+
+            (nonterminal_index(parent), child)
+        })
+        .collect();
+
+    outbound::treebank::Document {
+        corpus: corpus_name.into(),
+        document: doc_name.into(),
+        tokens: words
+            .iter()
+            .map(|word| {
+                word.anno(inbound::ttl::AnnoKey::Word)
+                    .unwrap_or_default()
+                    .replace("&quot;", "\"")
+            })
+            .collect(),
+        nonterminals,
+        edges,
+    }
+}
+
+fn print_version_info(input_annis: Option<&Path>) -> anyhow::Result<()> {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    println!("graphannis {}", inbound::annis_zip::GRAPHANNIS_VERSION);
+    println!(
+        "supported input formats: {}",
+        inbound::annis_zip::SUPPORTED_FORMATS.iter().join(", ")
+    );
+
+    if let Some(input_annis) = input_annis {
+        println!("input {}:", input_annis.display());
+
+        for entry in inbound::annis_zip::inspect(input_annis)? {
+            match entry.format() {
+                Ok(format) => println!("  {}: {format}", entry.path().display()),
+                Err(err) => println!("  {}: unsupported: {err}", entry.path().display()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the document node to a standalone corpus together with a datasource and the ANNIS
+/// segmentation nodes aligned with TTL words, which become the tokens of the document
+fn add_standalone_document(
+    update: &mut outbound::annis::Update<'_>,
+    corpus_name: &str,
+    annis_doc: &inbound::annis::Document,
+    ttl_doc: &inbound::ttl::Document,
+    node_name_mapper: &NodeNameMapper<'_>,
+    segmentation: &str,
+) -> anyhow::Result<()> {
+    let doc_node_name = annis_doc.node_name().into_owned_name();
+    let doc_name = annis_doc.doc_name();
+    let datasource_node_name = format!("{doc_node_name}#{segmentation}");
+
+    update.add_node(doc_node_name.clone(), outbound::annis::CORPUS.into())?;
+
+    // annis:doc = <doc name without subcorpora>
+    update.add_node_anno(
+        doc_node_name.clone(),
+        outbound::annis::ANNIS_NS.into(),
+        outbound::annis::DOC.into(),
+        doc_name.rsplit('/').next().unwrap_or(doc_name).into(),
+    )?;
+
+    // PartOf/annis/ from document to corpus
+    update.add_edge(
+        doc_node_name.clone(),
+        corpus_name.into(),
+        &outbound::annis::AnnotationComponentType::PartOf,
+        outbound::annis::ANNIS_NS.into(),
+        "".into(),
+    )?;
+
+    update.add_node(
+        datasource_node_name.clone(),
+        outbound::annis::DATASOURCE.into(),
+    )?;
+
+    // PartOf/annis/ from datasource to document
+    update.add_edge(
+        datasource_node_name.clone(),
+        doc_node_name,
+        &outbound::annis::AnnotationComponentType::PartOf,
+        outbound::annis::ANNIS_NS.into(),
+        "".into(),
+    )?;
+
+    let tok_anno_key = inbound::annis::AnnoKey {
+        ns: outbound::annis::DEFAULT_NS.into(),
+        name: segmentation.into(),
+    };
+
+    let mut previous_token_name: Option<&str> = None;
+
+    for ttl_word in ttl_doc.word_nodes_in_order() {
+        let Some(token_name) = node_name_mapper.aligned_name(ttl_word) else {
+            continue;
+        };
+
+        if update.is_node_created(token_name) {
+            continue;
+        }
+
+        let value = annis_doc
+            .node(token_name)?
+            .ok_or_else(|| anyhow!("aligned ANNIS node {token_name} not found"))?
+            .anno(&tok_anno_key)?
+            .unwrap_or_default()
+            .into_owned();
+
+        update.add_node(token_name.into(), outbound::annis::NODE.into())?;
+
+        // annis:tok = <value>
+        update.add_node_anno(
+            token_name.into(),
+            outbound::annis::ANNIS_NS.into(),
+            outbound::annis::TOK.into(),
+            value.clone(),
+        )?;
+
+        // default_ns:<segmentation> = <value>
+        update.add_node_anno(
+            token_name.into(),
+            outbound::annis::DEFAULT_NS.into(),
+            segmentation.into(),
+            value,
+        )?;
+
+        // PartOf/annis/ from token to datasource
+        update.add_edge(
+            token_name.into(),
+            datasource_node_name.clone(),
+            &outbound::annis::AnnotationComponentType::PartOf,
+            outbound::annis::ANNIS_NS.into(),
+            "".into(),
+        )?;
+
+        // Ordering/annis/ between consecutive tokens
+        if let Some(previous_token_name) = previous_token_name {
+            update.add_edge(
+                previous_token_name.into(),
+                token_name.into(),
+                &outbound::annis::AnnotationComponentType::Ordering,
+                outbound::annis::ANNIS_NS.into(),
+                "".into(),
+            )?;
+        }
+
+        previous_token_name = Some(token_name);
+    }
+
+    Ok(())
+}
+
+/// Alignment of a document as written to the artifacts directory
+#[derive(Serialize)]
+struct DocumentAlignment {
+    /// Key (`ns:name`) of the annotation compared with the TTL `WORD`, see [`norm_anno_key`]
+    norm_anno: String,
+    /// Names of the segmentation nodes aligned with the TTL words by the IRIs of the words
+    words: BTreeMap<String, String>,
+}
+
+struct NodeNameMapper<'a> {
+    annis_doc_node_name: String,
+    mapping: HashMap<inbound::ttl::NodeName, inbound::annis::NodeName<'a>>,
+    /// Key (`ns:name`) of the annotation compared with the TTL `WORD`, see [`norm_anno_key`]
+    norm_anno_key: String,
+    template: &'a NodeNameTemplate,
+    iri_segment_rules: &'a [IriSegmentRule],
+    sentence_indices: HashMap<inbound::ttl::NodeName, usize>,
+    /// IRIs of the sentences in order, see [`inbound::ttl::Document::sentence_node_names_in_order`]
+    sentence_iris: Vec<inbound::ttl::NodeName>,
+    nonterminal_names: HashMap<inbound::ttl::NodeName, String>,
+    annis_doc: &'a inbound::annis::Document,
+    dedupe: bool,
+    warnings: &'a warnings::Warnings,
+}
+
+impl<'a> NodeNameMapper<'a> {
+    fn new(
+        ttl_doc: &inbound::ttl::Document,
+        annis_doc: &'a inbound::annis::Document,
+        options: &AlignmentOptions<'_>,
+        template: &'a NodeNameTemplate,
+        iri_segment_rules: &'a [IriSegmentRule],
+        dedupe: bool,
+        warnings: &'a warnings::Warnings,
+    ) -> anyhow::Result<Self> {
+        let doc_name = annis_doc.doc_name();
+        let annis_nodes: Vec<_> = annis_doc
+            .segmentation_nodes_in_order(
+                &options.profile.segmentation,
+                options.component_selection,
+            )?
+            .collect();
+        let norm_anno_key = norm_anno_key(&annis_nodes, options.profile)?;
+
+        if norm_anno_key != &options.profile.anno_key_norm {
+            warnings.warn(
+                "normalized form missing, compared TTL words with fallback annotation",
+                format!("{}:{}", norm_anno_key.ns, norm_anno_key.name),
+                doc_name,
+            );
+        }
+
+        let tokens = DocumentTokens {
+            ttl_words: ttl_doc.word_nodes_in_order().collect(),
+            annis_nodes,
+            skip_filters: options.skip_filters,
+            profile: options.profile,
+            norm_anno_key,
+        };
+
+        let anchors = resolve_anchors(
+            &tokens.ttl_words,
+            &tokens.annis_nodes,
+            options.anchors.for_document(doc_name),
+        )?;
+
+        let alignment = align::align(&tokens, &anchors, options.max_gap)?;
+
+        for &annis_index in &alignment.skipped {
+            info!(annis_node_name = %tokens.annis_nodes[annis_index].name()?, "skipped ANNIS node");
+        }
+
+        if !alignment.skipped.is_empty() {
+            info!(
+                doc_name,
+                skipped = alignment.skipped.len(),
+                "skipped ANNIS nodes during alignment"
+            );
+        }
+
+        for gap in alignment.gaps {
+            warnings.warn(
+                "tolerated gap of ANNIS nodes without counterpart in TTL",
+                doc_name,
+                format!(
+                    "annis: {} .. {} ({} nodes)",
+                    tokens.annis_nodes[gap.start].name()?,
+                    tokens.annis_nodes[gap.end - 1].name()?,
+                    gap.len()
+                ),
+            );
+        }
+
+        for conflict in alignment.conflicts {
+            warnings.warn(
+                "sanity check failed between alignment anchors",
+                doc_name,
+                format!(
+                    "ttl: {} -> annis: {}: {}",
+                    tokens.ttl_words[conflict.ttl_index].node_name(),
+                    tokens.annis_nodes[conflict.annis_index].name()?,
+                    conflict.mismatch
+                ),
+            );
+        }
+
+        let mapping = alignment
+            .pairs
+            .into_iter()
+            .map(|(ttl_index, annis_index)| {
+                Ok((
+                    tokens.ttl_words[ttl_index].node_name().clone(),
+                    tokens.annis_nodes[annis_index].name()?,
+                ))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            annis_doc_node_name: annis_doc.node_name().into_owned_name(),
+            mapping,
+            norm_anno_key: format!("{}:{}", norm_anno_key.ns, norm_anno_key.name),
+            template,
+            iri_segment_rules,
+            sentence_indices: ttl_doc.sentence_indices(),
+            sentence_iris: ttl_doc.sentence_node_names_in_order().cloned().collect(),
+            nonterminal_names: HashMap::new(),
+            annis_doc,
+            dedupe,
+            warnings,
+        })
+    }
+
+    /// Name of the ANNIS node created for the given TTL nonterminal node, if any
+    fn nonterminal_name(&self, ttl_node_name: &inbound::ttl::NodeName) -> Option<&str> {
+        self.nonterminal_names
+            .get(ttl_node_name)
+            .map(String::as_str)
+    }
+
+    /// 1-based index of the sentence containing the given TTL node within the document, if any
+    fn sentence_index(&self, ttl_node: inbound::ttl::Node<'_>) -> Option<usize> {
+        self.sentence_indices.get(ttl_node.node_name()).copied()
+    }
+
+    /// IRI of the sentence containing the given TTL node, if any
+    fn sentence_iri(&self, ttl_node: inbound::ttl::Node<'_>) -> Option<&inbound::ttl::NodeName> {
+        self.sentence_iris
+            .get(self.sentence_index(ttl_node)?.checked_sub(1)?)
+    }
+
+    /// Name of the ANNIS node aligned with the given TTL word, if any
+    fn aligned_name(&self, ttl_word: inbound::ttl::Node<'_>) -> Option<&str> {
+        self.mapping.get(ttl_word.node_name()).map(AsRef::as_ref)
+    }
+
+    /// IRIs of the TTL words and nonterminals together with the names of the ANNIS nodes aligned
+    /// with or created for them and whether they are words, sorted by IRI
+    fn links(&self) -> Vec<(&inbound::ttl::NodeName, &str, bool)> {
+        let words = self.mapping.iter().map(|(ttl_node_name, annis_node_name)| {
+            (ttl_node_name, annis_node_name.as_ref(), true)
+        });
+
+        let nonterminals = self
+            .nonterminal_names
+            .iter()
+            .map(|(ttl_node_name, annis_node_name)| {
+                (ttl_node_name, annis_node_name.as_str(), false)
+            });
+
+        words
+            .chain(nonterminals)
+            .sorted_by_key(|&(ttl_node_name, ..)| ttl_node_name.as_ref())
+            .collect()
+    }
+
+    /// Names of the ANNIS nodes aligned with the TTL words, by TTL word
+    fn alignment(&self) -> DocumentAlignment {
+        DocumentAlignment {
+            norm_anno: self.norm_anno_key.clone(),
+            words: self
+                .mapping
+                .iter()
+                .map(|(ttl_node_name, annis_node_name)| {
+                    (ttl_node_name.to_string(), annis_node_name.to_string())
+                })
+                .collect(),
+        }
+    }
+
+    fn sidecar(
+        &self,
+        ttl_doc: &inbound::ttl::Document,
+        corpus_name: &str,
+        doc_name: &str,
+        added_edges: &[(&inbound::ttl::NodeName, &inbound::ttl::NodeName)],
+    ) -> anyhow::Result<outbound::sidecar::Sidecar> {
+        let tokens = ttl_doc
+            .word_nodes_in_order()
+            .map(|ttl_node| {
+                let ttl_node_name = ttl_node.node_name();
+
+                Ok(outbound::sidecar::Token {
+                    annis_node_name: self
+                        .mapping
+                        .get(ttl_node_name)
+                        .ok_or_else(|| {
+                            anyhow!("missing mapping for ttl node name {ttl_node_name}")
+                        })?
+                        .as_ref()
+                        .into(),
+                    iri: ttl_node_name.clone().into(),
+                    sentence: self.sentence_indices.get(ttl_node_name).copied(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let sentences = ttl_doc
+            .sentence_nodes_in_order()
+            .enumerate()
+            .map(|(i, ttl_node)| {
+                let index = i + 1;
+                let token_positions = tokens
+                    .iter()
+                    .positions(|token| token.sentence == Some(index))
+                    .minmax()
+                    .into_option();
+
+                outbound::sidecar::Sentence {
+                    index,
+                    iri: ttl_node.node_name().clone().into(),
+                    first_token: token_positions.map(|(first, _)| first),
+                    last_token: token_positions.map(|(_, last)| last),
+                }
+            })
+            .collect();
+
+        Ok(outbound::sidecar::Sidecar {
+            corpus: corpus_name.into(),
+            document: doc_name.into(),
+            tokens,
+            sentences,
+            tree: outbound::sidecar::TreeStatistics {
+                nonterminals: self.nonterminal_names.len(),
+                dominance_edges: added_edges.len(),
+                max_depth: outbound::sidecar::max_depth(added_edges),
+            },
+        })
+    }
+
+    /// Determines the ANNIS node name for the given TTL node, where the name of a nonterminal node
+    /// must neither collide with an existing ANNIS node nor with a node already created in `update`
+    fn annis_node_name(
+        &mut self,
+        ttl_node: inbound::ttl::Node<'_>,
+        update: &outbound::annis::Update<'_>,
+    ) -> anyhow::Result<String> {
+        let ttl_node_name = ttl_node.node_name();
+
+        if ttl_node.is_word() {
+            return Ok(self
+                .mapping
+                .get(ttl_node_name)
+                .ok_or_else(|| anyhow!("missing mapping for ttl node name {ttl_node_name}"))?
+                .as_ref()
+                .into());
+        }
+
+        if let Some(annis_node_name) = self.nonterminal_names.get(ttl_node_name) {
+            return Ok(annis_node_name.clone());
+        }
+
+        let iri_segment = self
+            .iri_segment_rules
+            .iter()
+            .find_map(|rule| rule.extract(ttl_node_name.as_ref()))
+            .ok_or_else(|| {
+                anyhow!("could not extract segment for node name from ttl node IRI {ttl_node_name}")
+            })?;
+
+        // the segment may already be percent-encoded within the IRI, so normalize it
+        let segment = node_name::encode(&node_name::decode(iri_segment)?).into_owned();
+
+        let candidate = self.template.apply(
+            &self.annis_doc_node_name,
+            self.sentence_indices.get(ttl_node_name).copied(),
+            &segment,
+            self.nonterminal_names.len() + 1,
+        );
+
+        let is_taken = |node_name: &str| -> anyhow::Result<bool> {
+            Ok(update.is_node_created(node_name) || self.annis_doc.has_node(node_name)?)
+        };
+
+        let mut annis_node_name = candidate.clone();
+        let mut suffix = 1;
+
+        while is_taken(&annis_node_name)? {
+            ensure!(
+                self.dedupe,
+                "node name {annis_node_name} for ttl node {ttl_node_name} collides with another \
+                 node, use --dedupe-node-names to rename colliding nodes automatically",
+            );
+
+            suffix += 1;
+            annis_node_name = format!("{candidate}_{suffix}");
+        }
+
+        if suffix > 1 {
+            self.warnings.warn(
+                "node name collision, de-duplicated node name",
+                self.annis_doc_node_name.clone(),
+                format!("{ttl_node_name}: {candidate} -> {annis_node_name}"),
+            );
+        }
+
+        self.nonterminal_names
+            .insert(ttl_node_name.clone(), annis_node_name.clone());
+
+        Ok(annis_node_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args_reject_equal_suffixes() -> anyhow::Result<()> {
+        let args = |suffixes: [&str; 2]| {
+            Args::try_parse_from([
+                env!("CARGO_PKG_NAME"),
+                "-",
+                "-",
+                "--overwrite-token-anno",
+                "lemma",
+                "--source-anno-suffix",
+                suffixes[0],
+                "--other-value-suffix",
+                suffixes[1],
+            ])
+        };
+
+        assert_eq!(
+            args(["_x", "_x"])?.check().unwrap_err().kind(),
+            ErrorKind::ArgumentConflict
+        );
+        args(["_src", "_annis"])?.check()?;
+
+        Ok(())
+    }
+
+    /// Error message for parsing the given invalid value
+    fn parse_err<T: FromStr<Err = anyhow::Error>>(s: &str) -> String {
+        match s.parse::<T>() {
+            Ok(_) => panic!("`{s}` should be rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn node_name_template_applies_all_placeholders() -> anyhow::Result<()> {
+        let template: NodeNameTemplate = "{doc}#s{sentence}_{segment}_{counter}".parse()?;
+
+        assert_eq!(
+            template.apply("corpus/doc", Some(3), "n12", 7),
+            "corpus/doc#s3_n12_7"
+        );
+        assert_eq!(
+            template.apply("corpus/doc", None, "n12", 7),
+            "corpus/doc#s_n12_7"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn node_name_template_doesnt_substitute_placeholders_in_values() -> anyhow::Result<()> {
+        let template: NodeNameTemplate = "{doc}#{segment}-{counter}".parse()?;
+
+        assert_eq!(
+            template.apply("corpus/{segment}", None, "{counter}", 1),
+            "corpus/{segment}#{counter}-1"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn node_name_template_rejects_invalid_templates() {
+        for (template, message) in [
+            ("#{segment}", "template must start with placeholder `{doc}`"),
+            ("{doc}#{segment", "unterminated placeholder in template"),
+            (
+                "{doc}#{node}",
+                "unknown placeholder `{node}` in template, expected one of `{doc}`, \
+                 `{sentence}`, `{segment}`, `{counter}`",
+            ),
+        ] {
+            assert_eq!(parse_err::<NodeNameTemplate>(template), message);
+        }
+    }
+
+    #[test]
+    fn rename_pattern_applies_corpus_name() -> anyhow::Result<()> {
+        let pattern: RenamePattern = "%c_treebank".parse()?;
+        assert_eq!(pattern.apply("REM"), "REM_treebank");
+
+        let pattern: RenamePattern = "%c-%c 100%%".parse()?;
+        assert_eq!(pattern.apply("REM"), "REM-REM 100%");
+
+        let pattern: RenamePattern = "%c_%v".parse()?;
+        assert_eq!(
+            pattern.apply("REM"),
+            format!("REM_{}", env!("CARGO_PKG_VERSION"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rename_pattern_rejects_invalid_patterns() {
+        for (pattern, message) in [
+            ("treebank", "pattern must contain placeholder `%c`"),
+            ("%d", "pattern must contain placeholder `%c`"),
+            (
+                "%c_%x",
+                "unknown placeholder `%x` at position 4, expected one of `%c`, `%d`, `%v` or `%%`",
+            ),
+            (
+                "%c%",
+                "incomplete placeholder `%` at position 3, use `%%` for a literal `%`",
+            ),
+        ] {
+            assert_eq!(parse_err::<RenamePattern>(pattern), message);
+        }
+    }
+
+    #[test]
+    fn output_template_applies_placeholders() -> anyhow::Result<()> {
+        let template: OutputTemplate = "{stem}_{profile}-v{version}.zip".parse()?;
+        assert_eq!(
+            template.apply("REM", "rem"),
+            format!("REM_rem-v{}.zip", env!("CARGO_PKG_VERSION"))
+        );
+
+        let template: OutputTemplate = "{stem}_{date}".parse()?;
+        assert_eq!(
+            template.apply("REM", "rem"),
+            format!("REM_{}", OffsetDateTime::now_utc().date())
+        );
+
+        let template: OutputTemplate = "out.zip".parse()?;
+        assert_eq!(template.apply("REM", "rem"), "out.zip");
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_template_rejects_invalid_templates() {
+        for (template, message) in [
+            ("", "template must not be empty"),
+            ("{stem", "unclosed placeholder at position 1"),
+            ("a_{stem}_{date", "unclosed placeholder at position 10"),
+            (
+                "{name}.zip",
+                "unknown placeholder `{name}`, expected one of `{stem}`, `{date}`, `{version}` or \
+                 `{profile}`",
+            ),
+        ] {
+            assert_eq!(parse_err::<OutputTemplate>(template), message);
+        }
+    }
+
+    #[test]
+    fn iri_segment_rule_parses_rules() -> anyhow::Result<()> {
+        assert!(matches!("fragment".parse()?, IriSegmentRule::Fragment));
+        assert!(matches!("path".parse()?, IriSegmentRule::Path));
+        assert!(matches!("query:id".parse()?, IriSegmentRule::Query(param) if param == "id"));
+
+        for rule in ["", "query:", "query", "host"] {
+            assert_eq!(
+                parse_err::<IriSegmentRule>(rule),
+                "rule must be one of `fragment`, `path` or `query:PARAM`"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn iri_segment_rule_extracts_segments() {
+        let iri = "https://example.org/rem/M001/s1/?id=n5&lang=gmh#t3";
+
+        assert_eq!(IriSegmentRule::Fragment.extract(iri), Some("t3"));
+        assert_eq!(IriSegmentRule::Path.extract(iri), Some("s1"));
+        assert_eq!(IriSegmentRule::Query("id".into()).extract(iri), Some("n5"));
+        assert_eq!(
+            IriSegmentRule::Query("lang".into()).extract(iri),
+            Some("gmh")
+        );
+        assert_eq!(IriSegmentRule::Query("other".into()).extract(iri), None);
+    }
+
+    #[test]
+    fn iri_segment_rule_ignores_missing_and_empty_segments() {
+        let iri = "https://example.org/rem/M001/n5";
+
+        assert_eq!(IriSegmentRule::Fragment.extract(iri), None);
+        assert_eq!(IriSegmentRule::Path.extract(iri), Some("n5"));
+        assert_eq!(IriSegmentRule::Query("id".into()).extract(iri), None);
+
+        assert_eq!(
+            IriSegmentRule::Fragment.extract("https://example.org/a#"),
+            None
+        );
+        assert_eq!(
+            IriSegmentRule::Query("id".into()).extract("https://example.org/?id="),
+            None
+        );
+        assert_eq!(IriSegmentRule::Path.extract("urn:n5"), None);
+    }
+
+    #[test]
+    fn config_setting_parses_toml_values() -> anyhow::Result<()> {
+        for (setting, keys, value) in [
+            (
+                "context.default=20",
+                vec!["context", "default"],
+                toml::Value::Integer(20),
+            ),
+            (
+                "view.page_size=true",
+                vec!["view", "page_size"],
+                true.into(),
+            ),
+            ("a.b.c=\"x y\"", vec!["a", "b", "c"], "x y".into()),
+            ("a=[1, 2]", vec!["a"], vec![1, 2].into()),
+            ("a=x y", vec!["a"], "x y".into()),
+            ("a=", vec!["a"], "".into()),
+        ] {
+            let setting: ConfigSetting = setting.parse()?;
+            assert_eq!(setting.keys, keys);
+            assert_eq!(setting.value, value);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_setting_rejects_invalid_settings() {
+        for (setting, message) in [
+            (
+                "context.default",
+                "config setting must be given as `KEY=VALUE`",
+            ),
+            ("=20", "config key `` must not have empty parts"),
+            (
+                "context..default=20",
+                "config key `context..default` must not have empty parts",
+            ),
+            (
+                "context.=20",
+                "config key `context.` must not have empty parts",
+            ),
+        ] {
+            assert_eq!(parse_err::<ConfigSetting>(setting), message);
+        }
+    }
+
+    #[test]
+    fn config_setting_applies_to_nested_tables() -> anyhow::Result<()> {
+        let mut config: toml::Table = toml::from_str("[context]\nmax = 50\n")?;
+
+        "context.default=20"
+            .parse::<ConfigSetting>()?
+            .apply(&mut config)?;
+        "view.base_text_segmentation=\"norm\""
+            .parse::<ConfigSetting>()?
+            .apply(&mut config)?;
+
+        assert_eq!(
+            config,
+            toml::from_str(
+                "[context]\nmax = 50\ndefault = 20\n[view]\nbase_text_segmentation = \"norm\"\n"
+            )?
+        );
+
+        let err = "context.max.value=1"
+            .parse::<ConfigSetting>()?
+            .apply(&mut config)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config key `context.max.value`: `max` is not a table"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, ensure};
+use clap::Parser;
+use graphannis::AnnotationGraph;
+use graphannis_core::graph::update::GraphUpdate;
+use tracing::info;
+
+use super::{merge_document, AlignmentOptions, Args, MergeOptions, Mode, NodeNameMapper};
+use crate::{features, inbound, node_name, outbound, relabel, rem, warnings};
+
+/// Merges the treebank of a single TTL document into a single ANNIS document without any corpus
+/// storage, so that other tools can embed the tree merging
+///
+/// `graph` is the graph of the ANNIS document whose document node is named `doc_node_name` (e.g.
+/// `corpus/doc`) and `ttl_path` is the path of its TTL file. `options` are command-line options
+/// without the input paths, e.g. `["--layer", "syntax"]`. Options concerning the documents are
+/// applied as in the conversion of whole corpora, while options concerning whole corpora or
+/// writing additional files are rejected.
+///
+/// Returns the update adding the annotations and the trees of the document, which can be applied
+/// to any graph containing the document. Unlike the conversion of whole corpora, this does not
+/// add `PartOf` edges, which are only determined by querying the merged corpus.
+pub fn convert_document<I, T>(
+    graph: AnnotationGraph,
+    doc_node_name: &str,
+    ttl_path: &Path,
+    options: I,
+) -> anyhow::Result<GraphUpdate>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    // the input paths are required on the command line, but not used here
+    let args = Args::try_parse_from(
+        [env!("CARGO_PKG_NAME"), "-", "-"]
+            .into_iter()
+            .map(OsString::from)
+            .chain(options.into_iter().map(Into::into)),
+    )?;
+    args.check()?;
+
+    for (option, given) in [
+        ("--mode=standalone", args.mode == Mode::Standalone),
+        ("--rename", args.rename.is_some()),
+        ("--corpus-overrides", args.corpus_overrides.is_some()),
+        ("--strip-anno", !args.strip_anno.is_empty()),
+        ("--metadata", args.metadata.is_some()),
+        ("--iri-map-file", args.iri_map_file.is_some()),
+        ("--sidecar-dir", args.sidecar_dir.is_some()),
+        ("--link-table", args.link_table.is_some()),
+        ("--also-export", !args.also_export.is_empty()),
+        ("--artifacts-dir", args.artifacts_dir.is_some()),
+        ("--dump-updates", args.dump_updates.is_some()),
+        ("--warnings-report", args.warnings_report.is_some()),
+        ("--html-report", args.html_report.is_some()),
+    ] {
+        ensure!(
+            !given,
+            "{option} is not supported when converting a single document"
+        );
+    }
+
+    let warnings = warnings::Warnings::default();
+    let annis_doc = inbound::annis::Document::new(graph, doc_node_name.into())?;
+    let doc_name = annis_doc.doc_name();
+
+    let ttl_doc = inbound::ttl::Document::from_file(
+        ttl_path,
+        args.route_predicate.as_deref(),
+        &args.literal_lang,
+        args.pre_process.as_deref(),
+        args.skip_malformed_ttl,
+        &warnings,
+    )?
+    .ok_or_else(|| anyhow!("ttl file {} could not be parsed", ttl_path.display()))?;
+
+    ttl_doc.check_order(doc_name, &warnings);
+
+    let mut profile = args.profile.load()?;
+
+    if let Some(path) = &args.sanitization {
+        profile.sanitization = rem::Sanitization::from_file(path)?;
+    }
+
+    let anchors = match &args.anchors {
+        Some(path) => inbound::anchors::Anchors::from_file(path)?,
+        None => inbound::anchors::Anchors::default(),
+    };
+
+    let mut node_name_mapper = NodeNameMapper::new(
+        &ttl_doc,
+        &annis_doc,
+        &AlignmentOptions {
+            component_selection: &inbound::annis::ComponentSelection {
+                ordering: args.ordering,
+                coverage: inbound::annis::CoverageFilter {
+                    include: args.coverage_component.clone(),
+                    exclude: args.exclude_coverage_component.clone(),
+                },
+            },
+            skip_filters: &args.skip_token,
+            profile: &profile,
+            anchors: &anchors,
+            max_gap: args.max_gap,
+        },
+        &args.node_name_template,
+        args.iri_segment_rules(),
+        args.dedupe_node_names,
+        &warnings,
+    )?;
+
+    let corpus_name = node_name::decode(
+        doc_node_name
+            .split_once('/')
+            .map_or(doc_node_name, |(corpus_name, _)| corpus_name),
+    )?;
+    let mut update = outbound::annis::Update::detached(&corpus_name);
+
+    let tree_builder = args.tree_convention.builder(
+        args.empty_element_anno.is_some(),
+        args.relabel
+            .as_deref()
+            .map(relabel::Relabeling::from_file)
+            .transpose()?,
+        args.collapse_unary.clone(),
+    );
+    let tagset = args
+        .tagset
+        .as_deref()
+        .map(inbound::tagset::Tagset::from_file)
+        .transpose()?;
+    let feature_splitting = args
+        .infl_features
+        .as_deref()
+        .map(features::FeatureSplitting::from_file)
+        .transpose()?;
+
+    let merged = merge_document(
+        &mut update,
+        &annis_doc,
+        &ttl_doc,
+        &mut node_name_mapper,
+        &MergeOptions {
+            args: &args,
+            profile: &profile,
+            tree_builder: tree_builder.as_ref(),
+            tagset: tagset.as_ref(),
+            feature_splitting: feature_splitting.as_ref(),
+        },
+        &warnings,
+    )?;
+
+    info!(
+        doc_name,
+        edges = merged.added_edges.len(),
+        "converted document"
+    );
+    warnings.finish(None)?;
+
+    if args.strict && merged.tagset_violations > 0 {
+        bail!(
+            "{} categories not in tagset, see warnings",
+            merged.tagset_violations
+        );
+    }
+
+    Ok(update.into_graph_update())
+}
@@ -0,0 +1,147 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::info;
+
+use super::{Args, DiscontinuityPolicy, RenamePattern};
+use crate::outbound::annis::glob_regex;
+
+/// Settings overriding the command-line options for particular corpora, read from a TOML file of
+/// the form
+///
+/// ```toml
+/// [[corpus]]
+/// name = "REM-M*"
+/// layer = "treebank_hist"
+/// tree_anno = "hist_tree"
+/// rename = "%c_hist"
+/// max_edge_depth = 50
+/// discontinuous = "mark"
+/// ```
+///
+/// where `name` is a corpus name in which `*` and `?` can be used as wildcards. All sections
+/// matching a corpus are applied in the order of the file, so later sections take precedence.
+#[derive(Default)]
+pub(crate) struct CorpusOverrides {
+    sections: Vec<Section>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCorpusOverrides {
+    #[serde(default)]
+    corpus: Vec<RawSection>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSection {
+    name: String,
+    layer: Option<String>,
+    tree_anno: Option<String>,
+    rename: Option<String>,
+    max_edge_depth: Option<usize>,
+    discontinuous: Option<String>,
+}
+
+struct Section {
+    name: Regex,
+    layer: Option<String>,
+    tree_anno: Option<String>,
+    rename: Option<RenamePattern>,
+    max_edge_depth: Option<usize>,
+    discontinuous: Option<DiscontinuityPolicy>,
+}
+
+impl CorpusOverrides {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let raw: RawCorpusOverrides = toml::from_str(&fs::read_to_string(path)?)?;
+
+        let sections = raw
+            .corpus
+            .into_iter()
+            .map(|section| {
+                let invalid = |err| {
+                    anyhow!(
+                        "invalid section for corpus {} in {}: {err}",
+                        section.name,
+                        path.display()
+                    )
+                };
+
+                Ok(Section {
+                    name: glob_regex(&section.name).map_err(invalid)?,
+                    rename: section
+                        .rename
+                        .as_deref()
+                        .map(RenamePattern::from_str)
+                        .transpose()
+                        .map_err(invalid)?,
+                    discontinuous: section
+                        .discontinuous
+                        .as_deref()
+                        .map(|policy| DiscontinuityPolicy::from_str(policy, false))
+                        .transpose()
+                        .map_err(|err| invalid(anyhow!(err)))?,
+                    layer: section.layer,
+                    tree_anno: section.tree_anno,
+                    max_edge_depth: section.max_edge_depth,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        info!(
+            path = %path.display(),
+            count = sections.len(),
+            "read corpus overrides"
+        );
+
+        Ok(Self { sections })
+    }
+
+    /// Whether any section renames corpora
+    pub(crate) fn renames(&self) -> bool {
+        self.sections.iter().any(|section| section.rename.is_some())
+    }
+
+    /// The given options with the settings of all sections matching the given corpus applied
+    pub(crate) fn apply<'a>(&self, args: &'a Args, corpus_name: &str) -> Cow<'a, Args> {
+        let mut args = Cow::Borrowed(args);
+
+        for section in &self.sections {
+            if !section.name.is_match(corpus_name) {
+                continue;
+            }
+
+            let args = args.to_mut();
+
+            if let Some(layer) = &section.layer {
+                args.layer.clone_from(layer);
+            }
+
+            if let Some(tree_anno) = &section.tree_anno {
+                args.tree_anno.clone_from(tree_anno);
+            }
+
+            if let Some(rename) = &section.rename {
+                args.rename = Some(rename.clone());
+            }
+
+            if let Some(max_edge_depth) = section.max_edge_depth {
+                args.max_edge_depth = Some(max_edge_depth);
+            }
+
+            if let Some(discontinuous) = section.discontinuous {
+                args.discontinuous = Some(discontinuous);
+            }
+        }
+
+        args
+    }
+}
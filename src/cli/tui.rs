@@ -0,0 +1,633 @@
+//! Interactive terminal UI for selecting the documents to convert and monitoring the conversion
+
+#[cfg(feature = "tui")]
+use std::io::{self, Write};
+use std::path::Path;
+#[cfg(feature = "tui")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+#[cfg(not(feature = "tui"))]
+use anyhow::bail;
+#[cfg(feature = "tui")]
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+#[cfg(feature = "tui")]
+use ratatui::layout::{Constraint, Layout};
+#[cfg(feature = "tui")]
+use ratatui::style::{Style, Stylize};
+#[cfg(feature = "tui")]
+use ratatui::text::{Line, Span};
+#[cfg(feature = "tui")]
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+#[cfg(feature = "tui")]
+use ratatui::{DefaultTerminal, Frame};
+use tracing::Subscriber;
+#[cfg(feature = "tui")]
+use tracing::{Level, Metadata};
+#[cfg(feature = "tui")]
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::overrides::CorpusOverrides;
+#[cfg(feature = "tui")]
+use super::{convert, DocumentStatus, Progress, Selection};
+use super::{Args, Input};
+
+/// Log lines captured while the TUI is active, `None` while it is inactive
+#[cfg(feature = "tui")]
+static CAPTURED_LOG: Mutex<Option<Vec<LogLine>>> = Mutex::new(None);
+
+/// Layer for logging such that log messages are captured while the TUI is active and written to
+/// stderr otherwise
+#[cfg(feature = "tui")]
+pub(crate) fn log_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(LogWriter)
+}
+
+/// Runs the TUI, converting the documents selected by the user
+#[cfg(feature = "tui")]
+pub(crate) fn run(
+    args: &Args,
+    corpus_overrides: &CorpusOverrides,
+    input: &Input,
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    let mut app = App::new(input)?;
+
+    let mut terminal = ratatui::init();
+    let result = run_app(
+        &mut terminal,
+        &mut app,
+        args,
+        corpus_overrides,
+        input,
+        output_path,
+    );
+    ratatui::restore();
+
+    let lines = captured_log().take().unwrap_or_default();
+    app.collect_log(lines);
+
+    // keep the log visible after the TUI has been closed
+    let mut stderr = io::stderr().lock();
+    for line in &app.log {
+        writeln!(stderr, "{line}")?;
+    }
+
+    result
+}
+
+#[cfg(not(feature = "tui"))]
+pub(crate) fn log_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer().with_writer(std::io::stderr)
+}
+
+#[cfg(not(feature = "tui"))]
+pub(crate) fn run(
+    _args: &Args,
+    _corpus_overrides: &CorpusOverrides,
+    _input: &Input,
+    _output_path: &Path,
+) -> anyhow::Result<()> {
+    bail!("the TUI requires building with the `tui` feature");
+}
+
+#[cfg(feature = "tui")]
+fn run_app(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    args: &Args,
+    corpus_overrides: &CorpusOverrides,
+    input: &Input,
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    if !app.select(terminal)? {
+        return Ok(());
+    }
+
+    *captured_log() = Some(Vec::new());
+    app.phase = Phase::Converting;
+
+    let selection = app.selection();
+    let result = convert(
+        args,
+        corpus_overrides,
+        input,
+        output_path,
+        &selection,
+        &mut TuiProgress { terminal, app },
+    );
+
+    let lines = captured_log().replace(Vec::new()).unwrap_or_default();
+    app.collect_log(lines);
+    app.phase = Phase::Finished(match &result {
+        Ok(()) => format!("output written to {}", output_path.display()),
+        Err(err) => format!("conversion failed: {err}"),
+    });
+    app.wait(terminal)?;
+
+    result
+}
+
+#[cfg(feature = "tui")]
+fn captured_log() -> MutexGuard<'static, Option<Vec<LogLine>>> {
+    CAPTURED_LOG.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "tui")]
+struct LogLine {
+    level: Level,
+    message: String,
+}
+
+/// Writer for log messages that captures them while the TUI is active
+#[cfg(feature = "tui")]
+struct LogWriter;
+
+#[cfg(feature = "tui")]
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogLineWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogLineWriter {
+            level: Level::INFO,
+            buf: Vec::new(),
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        LogLineWriter {
+            level: *meta.level(),
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// Writer for a single log message, which is captured or written to stderr when dropped
+#[cfg(feature = "tui")]
+struct LogLineWriter {
+    level: Level,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "tui")]
+impl Write for LogLineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tui")]
+impl Drop for LogLineWriter {
+    fn drop(&mut self) {
+        match captured_log().as_mut() {
+            Some(lines) => lines.push(LogLine {
+                level: self.level,
+                message: String::from_utf8_lossy(&self.buf).trim_end().into(),
+            }),
+            None => {
+                let _ = io::stderr().write_all(&self.buf);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+struct App {
+    corpora: Vec<CorpusEntry>,
+    rows: Vec<Row>,
+    list_state: ListState,
+    phase: Phase,
+    /// Corpus and document index of the document currently being converted
+    current: Option<(usize, usize)>,
+    /// All captured log lines
+    log: Vec<String>,
+    /// Warnings that occurred outside of a document
+    warnings: Vec<String>,
+}
+
+#[cfg(feature = "tui")]
+struct CorpusEntry {
+    name: String,
+    documents: Vec<DocumentEntry>,
+}
+
+#[cfg(feature = "tui")]
+struct DocumentEntry {
+    name: String,
+    selected: bool,
+    state: DocumentState,
+    warnings: Vec<String>,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Clone, Copy)]
+enum DocumentState {
+    Pending,
+    Converting,
+    Finished(DocumentStatus),
+}
+
+#[cfg(feature = "tui")]
+#[derive(Clone, Copy)]
+enum Row {
+    Corpus(usize),
+    Document(usize, usize),
+}
+
+#[cfg(feature = "tui")]
+enum Phase {
+    Selecting,
+    Converting,
+    /// Conversion finished with the given message
+    Finished(String),
+}
+
+#[cfg(feature = "tui")]
+impl App {
+    fn new(input: &Input) -> anyhow::Result<Self> {
+        let corpora = input
+            .annis_storage
+            .corpora()
+            .map(|corpus| {
+                Ok(CorpusEntry {
+                    name: corpus.name().into(),
+                    documents: corpus
+                        .document_names()?
+                        .into_iter()
+                        .map(|name| DocumentEntry {
+                            name,
+                            selected: true,
+                            state: DocumentState::Pending,
+                            warnings: Vec::new(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let rows = corpora
+            .iter()
+            .enumerate()
+            .flat_map(|(c, corpus)| {
+                std::iter::once(Row::Corpus(c))
+                    .chain((0..corpus.documents.len()).map(move |d| Row::Document(c, d)))
+            })
+            .collect();
+
+        Ok(Self {
+            corpora,
+            rows,
+            list_state: ListState::default().with_selected(Some(0)),
+            phase: Phase::Selecting,
+            current: None,
+            log: Vec::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Lets the user select the documents, returns whether the conversion should be started
+    fn select(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<bool> {
+        loop {
+            terminal.draw(|frame| self.render(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(false)
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.move_cursor(false),
+                KeyCode::Down | KeyCode::Char('j') => self.move_cursor(true),
+                KeyCode::Char(' ') => self.toggle_row(),
+                KeyCode::Char('a') => {
+                    let selected = !self.all_selected(0..self.corpora.len());
+                    for corpus in &mut self.corpora {
+                        corpus.set_selected(selected);
+                    }
+                }
+                KeyCode::Enter if self.selected_count() > 0 => return Ok(true),
+                _ => {}
+            }
+        }
+    }
+
+    /// Lets the user inspect the results until they close the TUI
+    fn wait(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        loop {
+            terminal.draw(|frame| self.render(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => self.move_cursor(false),
+                KeyCode::Down | KeyCode::Char('j') => self.move_cursor(true),
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn move_cursor(&mut self, down: bool) {
+        let cursor = self.list_state.selected().unwrap_or(0);
+
+        let cursor = if down {
+            (cursor + 1).min(self.rows.len().saturating_sub(1))
+        } else {
+            cursor.saturating_sub(1)
+        };
+
+        self.list_state.select(Some(cursor));
+    }
+
+    fn toggle_row(&mut self) {
+        let Some(&row) = self
+            .list_state
+            .selected()
+            .and_then(|cursor| self.rows.get(cursor))
+        else {
+            return;
+        };
+
+        match row {
+            Row::Corpus(c) => {
+                let selected = !self.all_selected(c..c + 1);
+                self.corpora[c].set_selected(selected);
+            }
+            Row::Document(c, d) => {
+                let document = &mut self.corpora[c].documents[d];
+                document.selected = !document.selected;
+            }
+        }
+    }
+
+    fn all_selected(&self, corpus_indices: std::ops::Range<usize>) -> bool {
+        self.corpora[corpus_indices]
+            .iter()
+            .flat_map(|corpus| &corpus.documents)
+            .all(|document| document.selected)
+    }
+
+    fn selected_count(&self) -> usize {
+        self.corpora
+            .iter()
+            .flat_map(|corpus| &corpus.documents)
+            .filter(|document| document.selected)
+            .count()
+    }
+
+    fn finished_count(&self) -> usize {
+        self.corpora
+            .iter()
+            .flat_map(|corpus| &corpus.documents)
+            .filter(|document| matches!(document.state, DocumentState::Finished(_)))
+            .count()
+    }
+
+    fn selection(&self) -> Selection {
+        Selection::Documents(
+            self.corpora
+                .iter()
+                .map(|corpus| {
+                    (
+                        corpus.name.clone(),
+                        corpus
+                            .documents
+                            .iter()
+                            .filter(|document| document.selected)
+                            .map(|document| document.name.clone())
+                            .collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn set_state(&mut self, corpus_name: &str, doc_name: &str, state: DocumentState) {
+        let Some((c, d)) = self.corpora.iter().enumerate().find_map(|(c, corpus)| {
+            (corpus.name == corpus_name)
+                .then(|| corpus.documents.iter().position(|d| d.name == doc_name))
+                .flatten()
+                .map(|d| (c, d))
+        }) else {
+            return;
+        };
+
+        self.corpora[c].documents[d].state = state;
+
+        if let DocumentState::Converting = state {
+            self.current = Some((c, d));
+
+            // follow the conversion with the cursor so that its warnings are shown
+            if let Some(cursor) = self
+                .rows
+                .iter()
+                .position(|row| matches!(*row, Row::Document(rc, rd) if (rc, rd) == (c, d)))
+            {
+                self.list_state.select(Some(cursor));
+            }
+        } else {
+            self.current = None;
+        }
+    }
+
+    /// Adds the given log lines, attributing warnings to the document currently being converted
+    fn collect_log(&mut self, lines: Vec<LogLine>) {
+        for line in lines {
+            if line.level <= Level::WARN {
+                let warnings = match self.current {
+                    Some((c, d)) => &mut self.corpora[c].documents[d].warnings,
+                    None => &mut self.warnings,
+                };
+
+                warnings.push(line.message.clone());
+            }
+
+            self.log.push(line.message);
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame<'_>) {
+        let [list_area, warnings_area, status_area] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(10),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let items: Vec<_> = self.rows.iter().map(|&row| self.row_item(row)).collect();
+
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(" Corpora and documents "))
+                .highlight_style(Style::new().reversed()),
+            list_area,
+            &mut self.list_state,
+        );
+
+        let (title, warnings) = match self
+            .list_state
+            .selected()
+            .and_then(|cursor| self.rows.get(cursor))
+        {
+            Some(&Row::Document(c, d)) => {
+                let document = &self.corpora[c].documents[d];
+                (format!(" Warnings: {} ", document.name), &document.warnings)
+            }
+            _ => (" Warnings ".into(), &self.warnings),
+        };
+
+        // show the most recent warnings that fit into the area
+        let visible = usize::from(warnings_area.height.saturating_sub(2));
+        let lines: Vec<_> = warnings[warnings.len().saturating_sub(visible)..]
+            .iter()
+            .map(|warning| Line::from(warning.as_str()))
+            .collect();
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            warnings_area,
+        );
+
+        let status = match &self.phase {
+            Phase::Selecting => format!(
+                "{} documents selected | up/down: move, space: toggle, a: toggle all, enter: convert, q: quit",
+                self.selected_count()
+            ),
+            Phase::Converting => format!(
+                "converting {}/{} documents ...",
+                self.finished_count(),
+                self.selected_count()
+            ),
+            Phase::Finished(message) => {
+                format!("{message} | up/down: move, any other key: exit")
+            }
+        };
+
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    fn row_item(&self, row: Row) -> ListItem<'static> {
+        match row {
+            Row::Corpus(c) => {
+                let corpus = &self.corpora[c];
+                let selected = corpus.documents.iter().filter(|d| d.selected).count();
+
+                let checkbox = if selected == corpus.documents.len() {
+                    "[x]"
+                } else if selected == 0 {
+                    "[ ]"
+                } else {
+                    "[-]"
+                };
+
+                ListItem::new(Line::from(vec![
+                    Span::from(format!("{checkbox} {}", corpus.name)).bold(),
+                    Span::from(format!(" ({selected}/{})", corpus.documents.len())),
+                ]))
+            }
+            Row::Document(c, d) => {
+                let document = &self.corpora[c].documents[d];
+                let checkbox = if document.selected { "[x]" } else { "[ ]" };
+
+                let state = match (&self.phase, document.state) {
+                    (Phase::Selecting, _) => Span::from(""),
+                    _ if !document.selected => Span::from("not selected").dark_gray(),
+                    (_, DocumentState::Pending) => Span::from("pending"),
+                    (_, DocumentState::Converting) => Span::from("converting").yellow(),
+                    (_, DocumentState::Finished(DocumentStatus::Converted)) => {
+                        Span::from("converted").green()
+                    }
+                    (_, DocumentState::Finished(DocumentStatus::Skipped)) => {
+                        Span::from("skipped (no treebank data)").dark_gray()
+                    }
+                };
+
+                let mut spans = vec![
+                    Span::from(format!("    {checkbox} {}  ", document.name)),
+                    state,
+                ];
+
+                if !document.warnings.is_empty() {
+                    spans.push(Span::from(format!("  {} warnings", document.warnings.len())).red());
+                }
+
+                ListItem::new(Line::from(spans))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+impl CorpusEntry {
+    fn set_selected(&mut self, selected: bool) {
+        for document in &mut self.documents {
+            document.selected = selected;
+        }
+    }
+}
+
+/// Progress observer that updates and redraws the TUI
+#[cfg(feature = "tui")]
+struct TuiProgress<'a> {
+    terminal: &'a mut DefaultTerminal,
+    app: &'a mut App,
+}
+
+#[cfg(feature = "tui")]
+impl TuiProgress<'_> {
+    fn update(
+        &mut self,
+        corpus_name: &str,
+        doc_name: &str,
+        state: DocumentState,
+    ) -> anyhow::Result<()> {
+        let lines = captured_log().replace(Vec::new()).unwrap_or_default();
+        self.app.collect_log(lines);
+        self.app.set_state(corpus_name, doc_name, state);
+        self.terminal.draw(|frame| self.app.render(frame))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tui")]
+impl Progress for TuiProgress<'_> {
+    fn document_started(&mut self, corpus_name: &str, doc_name: &str) -> anyhow::Result<()> {
+        self.update(corpus_name, doc_name, DocumentState::Converting)
+    }
+
+    fn document_finished(
+        &mut self,
+        corpus_name: &str,
+        doc_name: &str,
+        status: DocumentStatus,
+    ) -> anyhow::Result<()> {
+        self.update(corpus_name, doc_name, DocumentState::Finished(status))
+    }
+}
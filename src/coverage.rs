@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::ops::AddAssign;
+
+use tracing::info;
+
+use crate::inbound;
+use crate::warnings::Warnings;
+
+/// Numbers of documents of a (sub)corpus together with the numbers of documents, sentences and
+/// tokens that would get treebank annotations
+#[derive(Clone, Copy, Default)]
+struct Coverage {
+    documents: usize,
+    tree_documents: usize,
+    tree_sentences: usize,
+    tree_tokens: usize,
+}
+
+impl AddAssign for Coverage {
+    fn add_assign(&mut self, other: Self) {
+        self.documents += other.documents;
+        self.tree_documents += other.tree_documents;
+        self.tree_sentences += other.tree_sentences;
+        self.tree_tokens += other.tree_tokens;
+    }
+}
+
+/// Determines for each corpus how much of it is covered by the treebank data, broken down by
+/// subcorpus, and prints it to stdout without converting anything
+pub(crate) fn report(
+    annis_storage: &inbound::annis::Storage,
+    ttl_storage: &inbound::ttl::Storage,
+) -> anyhow::Result<()> {
+    let warnings = Warnings::default();
+    let mut stdout = io::stdout().lock();
+
+    for corpus in annis_storage.corpora() {
+        info!(corpus_name = corpus.name(), "determining coverage");
+
+        let mut by_subcorpus: BTreeMap<String, Coverage> = BTreeMap::new();
+
+        for doc_name in corpus.document_names()? {
+            // documents in nested subcorpora are named `SUBCORPUS/DOC`
+            let subcorpus = doc_name
+                .rsplit_once('/')
+                .map_or("", |(subcorpus, _)| subcorpus);
+
+            let mut coverage = Coverage {
+                documents: 1,
+                ..Coverage::default()
+            };
+
+            if let Some(ttl_doc) = ttl_storage.try_document_for_name(&doc_name, &warnings)? {
+                coverage.tree_documents = 1;
+                coverage.tree_sentences = ttl_doc.sentence_nodes_in_order().count();
+                coverage.tree_tokens = ttl_doc.word_nodes_in_order().count();
+            }
+
+            *by_subcorpus.entry(subcorpus.into()).or_default() += coverage;
+        }
+
+        let mut total = Coverage::default();
+
+        writeln!(stdout, "{}", corpus.name())?;
+        write_row(
+            &mut stdout,
+            "subcorpus",
+            ["documents", "with tree", "sentences", "tokens"],
+        )?;
+
+        for (subcorpus, coverage) in by_subcorpus {
+            let subcorpus = if subcorpus.is_empty() {
+                "(top level)"
+            } else {
+                &subcorpus
+            };
+
+            write_coverage_row(&mut stdout, subcorpus, coverage)?;
+            total += coverage;
+        }
+
+        write_coverage_row(&mut stdout, "(total)", total)?;
+        writeln!(stdout)?;
+    }
+
+    warnings.finish(None)
+}
+
+fn write_coverage_row(w: &mut impl Write, label: &str, coverage: Coverage) -> io::Result<()> {
+    write_row(
+        w,
+        label,
+        [
+            coverage.documents.to_string(),
+            format!(
+                "{} ({:.1}%)",
+                coverage.tree_documents,
+                percentage(coverage.tree_documents, coverage.documents)
+            ),
+            coverage.tree_sentences.to_string(),
+            coverage.tree_tokens.to_string(),
+        ],
+    )
+}
+
+fn write_row(w: &mut impl Write, label: &str, cells: [impl AsRef<str>; 4]) -> io::Result<()> {
+    write!(w, "  {label:<30}")?;
+
+    for cell in cells {
+        write!(w, "{:>16}", cell.as_ref())?;
+    }
+
+    writeln!(w)
+}
+
+pub(crate) fn percentage(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        100.0 * part as f64 / total as f64
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{anyhow, ensure};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::inbound;
+
+/// Row of a document mapping CSV file, mapping the name of an ANNIS document to the name of the
+/// document of its TTL file, where `distance` is only informational
+#[derive(Deserialize, Serialize)]
+struct Row {
+    document: String,
+    ttl_document: String,
+    #[serde(default)]
+    distance: Option<usize>,
+}
+
+/// Reads a document mapping CSV file (see `match-docs`) into a map from the names of ANNIS
+/// documents to the names of the documents of their TTL files, skipping rows without TTL document
+pub(crate) fn read(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let mut doc_map = HashMap::new();
+
+    for row in csv::Reader::from_path(path)?.into_deserialize() {
+        let row: Row = row.map_err(|err| anyhow!("invalid row in {}: {err}", path.display()))?;
+
+        if row.ttl_document.is_empty() {
+            continue;
+        }
+
+        ensure!(
+            doc_map
+                .insert(row.document.clone(), row.ttl_document)
+                .is_none(),
+            "document {} is mapped more than once in {}",
+            row.document,
+            path.display()
+        );
+    }
+
+    info!(path = %path.display(), count = doc_map.len(), "read document mapping");
+
+    Ok(doc_map)
+}
+
+/// Assigns to each ANNIS document the TTL document with the most similar name and writes the
+/// assignment as a CSV file to be reviewed and passed to `--doc-map`
+///
+/// Names are compared by their edit distance after normalizing them, i.e. dropping the subcorpus
+/// path of ANNIS documents, lowercasing them and removing all characters that are not
+/// alphanumeric. Pairs with a distance above `max_distance` are never assigned, and each TTL
+/// document is assigned to at most one ANNIS document, where closer pairs are assigned first.
+pub(crate) fn match_documents(
+    annis_storage: &inbound::annis::Storage,
+    ttl_storage: &inbound::ttl::Storage,
+    output: &Path,
+    max_distance: usize,
+) -> anyhow::Result<()> {
+    let mut doc_names = Vec::new();
+
+    for corpus in annis_storage.corpora() {
+        doc_names.extend(corpus.document_names()?);
+    }
+
+    let ttl_doc_names: Vec<(&str, String)> = ttl_storage
+        .document_names()
+        .into_iter()
+        .map(|(ttl_doc_name, _)| ttl_doc_name)
+        .unique()
+        .map(|ttl_doc_name| (ttl_doc_name, normalize(ttl_doc_name)))
+        .collect();
+
+    let mut candidates = Vec::new();
+
+    for doc_name in &doc_names {
+        let normalized = normalize(doc_name.rsplit('/').next().unwrap_or(doc_name));
+
+        for (ttl_doc_name, ttl_normalized) in &ttl_doc_names {
+            let distance = strsim::levenshtein(&normalized, ttl_normalized);
+
+            if distance <= max_distance {
+                candidates.push((distance, doc_name.as_str(), *ttl_doc_name));
+            }
+        }
+    }
+
+    // closest pairs first, ties broken by name so the result is deterministic
+    candidates.sort_unstable();
+
+    let mut assignment: HashMap<&str, (&str, usize)> = HashMap::new();
+    let mut assigned_ttl_doc_names = HashSet::new();
+
+    for (distance, doc_name, ttl_doc_name) in candidates {
+        if !assignment.contains_key(doc_name) && assigned_ttl_doc_names.insert(ttl_doc_name) {
+            assignment.insert(doc_name, (ttl_doc_name, distance));
+        }
+    }
+
+    let mut writer = csv::Writer::from_path(output)?;
+
+    for doc_name in &doc_names {
+        let (ttl_document, distance) = match assignment.get(doc_name.as_str()) {
+            Some(&(ttl_doc_name, distance)) => (ttl_doc_name.into(), Some(distance)),
+            None => (String::new(), None),
+        };
+
+        writer.serialize(Row {
+            document: doc_name.clone(),
+            ttl_document,
+            distance,
+        })?;
+    }
+
+    writer.flush()?;
+
+    info!(
+        path = %output.display(),
+        documents = doc_names.len(),
+        exact = assignment.values().filter(|&&(_, distance)| distance == 0).count(),
+        fuzzy = assignment.values().filter(|&&(_, distance)| distance > 0).count(),
+        unmatched = doc_names.len() - assignment.len(),
+        "wrote document mapping"
+    );
+
+    Ok(())
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::ensure;
+use serde::Deserialize;
+use tracing::info;
+
+/// Rule for splitting the `INFL` values of TTL nodes into separate morphological features, read
+/// from a TOML file of the form
+///
+/// ```toml
+/// delimiter = "."
+/// names = ["case", "number", "gender"]
+/// prefix = "infl"
+/// apply_to = ["words", "nonterminals"]
+/// ```
+///
+/// where a value such as `Nom.Sg.Masc` leads to the annotations `infl.case = Nom`,
+/// `infl.number = Sg` and `infl.gender = Masc`. If `names` is omitted, the parts of the value must
+/// be `key=value` pairs, e.g. `case=Nom|number=Sg` with `delimiter = "|"`, leading to one
+/// annotation `<prefix>.<key>` per pair. Empty parts are skipped. `apply_to` defaults to
+/// `["words"]`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FeatureSplitting {
+    delimiter: String,
+    #[serde(default)]
+    names: Option<Vec<String>>,
+    prefix: String,
+    #[serde(default = "default_apply_to")]
+    apply_to: HashSet<FeatureTarget>,
+}
+
+/// Kind of TTL node whose `INFL` value is split
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FeatureTarget {
+    Words,
+    Nonterminals,
+}
+
+fn default_apply_to() -> HashSet<FeatureTarget> {
+    HashSet::from([FeatureTarget::Words])
+}
+
+impl FeatureSplitting {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let splitting: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        ensure!(
+            !splitting.delimiter.is_empty(),
+            "feature delimiter in {} must not be empty",
+            path.display()
+        );
+
+        info!(
+            path = %path.display(),
+            names = splitting.names.as_ref().map_or(0, Vec::len),
+            "read feature splitting rule"
+        );
+
+        Ok(splitting)
+    }
+
+    /// Whether the `INFL` values of the given kind of nodes are split
+    pub(crate) fn applies_to(&self, target: FeatureTarget) -> bool {
+        self.apply_to.contains(&target)
+    }
+
+    /// Splits the given `INFL` value into pairs of annotation names and values, or returns a
+    /// description of why the value doesn't fit the rule
+    pub(crate) fn split<'a>(&self, infl: &'a str) -> Result<Vec<(String, &'a str)>, String> {
+        let parts = infl.split(self.delimiter.as_str());
+
+        match &self.names {
+            Some(names) => {
+                let parts: Vec<_> = parts.collect();
+
+                if parts.len() > names.len() {
+                    return Err(format!(
+                        "{} features, but only {} names",
+                        parts.len(),
+                        names.len()
+                    ));
+                }
+
+                Ok(names
+                    .iter()
+                    .zip(parts)
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(name, value)| (self.anno_name(name), value))
+                    .collect())
+            }
+            None => parts
+                .filter(|part| !part.is_empty())
+                .map(|part| match part.split_once('=') {
+                    Some((key, value)) if !key.is_empty() => Ok((self.anno_name(key), value)),
+                    _ => Err(format!("feature `{part}` is not a `key=value` pair")),
+                })
+                .collect(),
+        }
+    }
+
+    fn anno_name(&self, name: &str) -> String {
+        format!("{}.{name}", self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn split_by_names() -> anyhow::Result<()> {
+        let splitting = toml::from_str::<FeatureSplitting>(
+            r#"
+            delimiter = "."
+            names = ["case", "number", "gender"]
+            prefix = "infl"
+            "#,
+        )?;
+
+        assert_eq!(
+            splitting.split("Nom.Sg.Masc"),
+            Ok(vec![
+                ("infl.case".into(), "Nom"),
+                ("infl.number".into(), "Sg"),
+                ("infl.gender".into(), "Masc"),
+            ])
+        );
+        assert_eq!(
+            splitting.split("Nom..Masc"),
+            Ok(vec![
+                ("infl.case".into(), "Nom"),
+                ("infl.gender".into(), "Masc"),
+            ])
+        );
+        assert_eq!(
+            splitting.split("Nom"),
+            Ok(vec![("infl.case".into(), "Nom")])
+        );
+        assert_eq!(
+            splitting.split("Nom.Sg.Masc.Wk"),
+            Err("4 features, but only 3 names".into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_key_value_pairs() -> anyhow::Result<()> {
+        let splitting = toml::from_str::<FeatureSplitting>(
+            r#"
+            delimiter = "|"
+            prefix = "feat"
+            "#,
+        )?;
+
+        assert_eq!(
+            splitting.split("case=Nom||number=Sg"),
+            Ok(vec![
+                ("feat.case".into(), "Nom"),
+                ("feat.number".into(), "Sg"),
+            ])
+        );
+        assert_eq!(
+            splitting.split("case=Nom|Sg"),
+            Err("feature `Sg` is not a `key=value` pair".into())
+        );
+        assert_eq!(
+            splitting.split("=Nom"),
+            Err("feature `=Nom` is not a `key=value` pair".into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn apply_to_defaults_to_words() -> anyhow::Result<()> {
+        let splitting = toml::from_str::<FeatureSplitting>(
+            r#"
+            delimiter = "."
+            prefix = "infl"
+            "#,
+        )?;
+        assert!(splitting.applies_to(FeatureTarget::Words));
+        assert!(!splitting.applies_to(FeatureTarget::Nonterminals));
+
+        let splitting = toml::from_str::<FeatureSplitting>(
+            r#"
+            delimiter = "."
+            prefix = "infl"
+            apply_to = ["nonterminals"]
+            "#,
+        )?;
+        assert!(!splitting.applies_to(FeatureTarget::Words));
+        assert!(splitting.applies_to(FeatureTarget::Nonterminals));
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_file_rejects_empty_delimiter() -> anyhow::Result<()> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        file.write_all(b"delimiter = \"\"\nprefix = \"infl\"\n")?;
+
+        let err = FeatureSplitting::from_file(file.path()).unwrap_err();
+        assert!(err.to_string().starts_with("feature delimiter in "));
+        assert!(err.to_string().ends_with(" must not be empty"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_targets() {
+        assert!(toml::from_str::<FeatureSplitting>(
+            r#"
+            delimiter = "."
+            prefix = "infl"
+            separator = "."
+            "#
+        )
+        .is_err());
+
+        assert!(toml::from_str::<FeatureSplitting>(
+            r#"
+            delimiter = "."
+            prefix = "infl"
+            apply_to = ["sentences"]
+            "#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing::info;
+
+/// Explicit alignment anchors per document, read from a TOML file of the form
+///
+/// ```toml
+/// [documents.DOC]
+/// anchors = [
+///   { ttl = "TTL WORD IRI", annis = "ANNIS NODE NAME" },
+///   { ttl = "TTL WORD IRI", annis_index = 42 },
+/// ]
+/// ```
+///
+/// where `annis_index` is the 0-based position of the ANNIS segmentation node within the document.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Anchors {
+    #[serde(default)]
+    documents: HashMap<String, DocumentAnchors>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DocumentAnchors {
+    anchors: Vec<Anchor>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Anchor {
+    pub(crate) ttl: String,
+    #[serde(flatten)]
+    pub(crate) annis: AnnisTarget,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum AnnisTarget {
+    NodeName { annis: String },
+    Index { annis_index: usize },
+}
+
+impl Anchors {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let anchors: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        info!(
+            path = %path.display(),
+            documents = anchors.documents.len(),
+            "read alignment anchors"
+        );
+
+        Ok(anchors)
+    }
+
+    pub(crate) fn for_document(&self, doc_name: &str) -> &[Anchor] {
+        self.documents
+            .get(doc_name)
+            .map_or(&[], |document| &document.anchors)
+    }
+}
@@ -1,13 +1,15 @@
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::path::Path;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::LazyLock;
-use std::{fmt, vec};
+use std::{fmt, iter, vec};
 
-use anyhow::anyhow;
-use graphannis::corpusstorage::{QueryLanguage, ResultOrder, SearchQuery};
+use anyhow::{anyhow, bail, ensure};
+use clap::ValueEnum;
+use graphannis::corpusstorage::{ImportFormat, QueryLanguage, ResultOrder, SearchQuery};
 use graphannis::graph::{Component, NodeID};
 use graphannis::model::{AnnotationComponent, AnnotationComponentType};
 use graphannis::util::node_names_from_match;
@@ -15,10 +17,16 @@ use graphannis::AnnotationGraph;
 use graphannis_core::graph::{ANNIS_NS, DEFAULT_NS, NODE_NAME_KEY};
 pub(crate) use graphannis_core::types::AnnoKey;
 use itertools::Itertools;
+use regex::Regex;
 use tracing::info;
 
-use crate::annis_util;
+use crate::inbound::annis_zip;
+use crate::{annis_util, node_name};
 
+/// Number of document nodes queried at once by [`Corpus::documents`]
+const DOCUMENT_PAGE_SIZE: usize = 100;
+
+/// Ordering component of the tokens (`annis::`), which exists in every corpus with tokens
 static DEFAULT_ORDERING_COMPONENT: LazyLock<AnnotationComponent> = LazyLock::new(|| {
     Component::new(
         AnnotationComponentType::Ordering,
@@ -27,61 +35,258 @@ static DEFAULT_ORDERING_COMPONENT: LazyLock<AnnotationComponent> = LazyLock::new
     )
 });
 
+/// Where the imported corpus graphs are stored
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum StorageMode {
+    Disk,
+    Memory,
+    /// In memory for corpora whose files (uncompressed within the ZIP file) are at most
+    /// `memory_threshold` bytes large, on disk otherwise
+    Auto {
+        memory_threshold: u64,
+    },
+}
+
 pub(crate) struct Storage {
-    storage: Rc<annis_util::TempStorage>,
+    storage: annis_util::StorageHandle,
     corpus_names: Vec<String>,
+    /// Paths of the GraphML files within the ZIP file by the names of the corpora imported from
+    /// them, which graphannis derives from the file names
+    graphml_paths: HashMap<String, PathBuf>,
 }
 
 impl Storage {
-    pub(crate) fn from_zip(path: &Path, in_memory: bool) -> anyhow::Result<Self> {
-        info!(path = %path.display(), in_memory, "importing corpora");
+    /// Imports the corpora from the ZIP file at the given path, only those named in
+    /// `selected_names` if not empty
+    ///
+    /// If the ZIP file only contains GraphML corpora, whose names are given by their file names,
+    /// the other corpora are not even imported.
+    pub(crate) fn from_zip(
+        path: &Path,
+        mode: StorageMode,
+        selected_names: &[String],
+    ) -> anyhow::Result<Self> {
+        info!(path = %path.display(), "importing corpora");
+
+        let storage = annis_util::StorageHandle::new()?;
+        let session = storage.session();
+        let entries = annis_zip::inspect(path)?;
+
+        let graphml_paths = entries
+            .iter()
+            .filter_map(|entry| Some((entry.corpus_name()?, entry.path().to_path_buf())))
+            .collect();
+
+        let all_graphml = entries.iter().all(|entry| {
+            entry
+                .format()
+                .is_ok_and(|format| format == annis_zip::Format::GraphMl)
+        });
+
+        let in_memory = |entry: &annis_zip::Entry| match mode {
+            StorageMode::Disk => false,
+            StorageMode::Memory => true,
+            StorageMode::Auto { memory_threshold } => entry.size() <= memory_threshold,
+        };
+
+        let mut corpus_names = if !selected_names.is_empty() && all_graphml {
+            let selected_entries: Vec<_> = entries
+                .into_iter()
+                .filter(|entry| {
+                    entry
+                        .corpus_name()
+                        .is_some_and(|name| selected_names.contains(&name))
+                })
+                .collect();
+
+            info!(
+                count = selected_entries.len(),
+                "importing only selected GraphML corpora"
+            );
+
+            import_entries(&session, path, &selected_entries, in_memory)
+        } else {
+            match mode {
+                StorageMode::Auto { .. } => import_entries(&session, path, &entries, in_memory),
+                _ => session
+                    .import_all_from_zip(
+                        File::open(path)?,
+                        mode == StorageMode::Disk,
+                        false, /* overwrite_existing */
+                        |msg| info!("{msg}"),
+                    )
+                    .map_err(Into::into),
+            }
+        }
+        .map_err(|err| {
+            // the errors of graphannis don't say which corpus or file is affected
+            anyhow!(
+                "importing corpora from {} failed: {err}; run with --version-info to list the \
+                     corpora found in it",
+                path.display()
+            )
+        })?;
 
-        let storage = Rc::new(annis_util::TempStorage::new()?);
+        if !selected_names.is_empty() {
+            for selected_name in selected_names {
+                ensure!(
+                    corpus_names.contains(selected_name),
+                    "corpus {selected_name} not found in {}",
+                    path.display()
+                );
+            }
 
-        let corpus_names = storage.import_all_from_zip(
-            File::open(path)?,
-            !in_memory,
-            false, /* overwrite_existing */
-            |msg| info!("{msg}"),
-        )?;
+            corpus_names.retain(|name| selected_names.contains(name));
+        }
 
         info!(count = corpus_names.len(), "imported corpora");
 
         Ok(Self {
             storage,
             corpus_names,
+            graphml_paths,
         })
     }
 
     pub(crate) fn corpora(&self) -> impl Iterator<Item = Corpus<'_>> {
         self.corpus_names.iter().map(|name| Corpus {
-            storage: Rc::clone(&self.storage),
+            handle: &self.storage,
+            storage: self.storage.view(),
+            graphml_path: self.graphml_paths.get(name).map(PathBuf::as_path),
             name,
         })
     }
 }
 
 pub(crate) struct Corpus<'a> {
-    storage: Rc<annis_util::TempStorage>,
+    /// Handle to the storage containing the corpus, from which outbound corpora obtain sessions
+    handle: &'a annis_util::StorageHandle,
+    storage: annis_util::StorageView,
+    graphml_path: Option<&'a Path>,
     name: &'a str,
 }
 
 impl<'a> Corpus<'a> {
-    pub(crate) fn storage(&self) -> &Rc<annis_util::TempStorage> {
-        &self.storage
+    pub(crate) fn storage_handle(&self) -> &'a annis_util::StorageHandle {
+        self.handle
     }
 
-    pub(crate) fn name(&self) -> &str {
+    pub(crate) fn name(&self) -> &'a str {
         self.name
     }
 
+    /// Path of the GraphML file within the input ZIP file from which the corpus was imported, if
+    /// it was imported from GraphML
+    pub(crate) fn graphml_path(&self) -> Option<&'a Path> {
+        self.graphml_path
+    }
+
     pub(crate) fn config(&self) -> anyhow::Result<toml::Table> {
         Ok(toml::Table::try_from(self.storage.info(self.name)?.config)?)
     }
 
-    pub(crate) fn documents(
+    /// Node annotation keys of this corpus, see [`annis_util::node_anno_keys`]
+    pub(crate) fn node_anno_keys(&self) -> anyhow::Result<BTreeSet<AnnoKey>> {
+        self.storage.node_anno_keys(self.name)
+    }
+
+    /// Number of nodes carrying each node annotation key, see
+    /// [`annis_util::node_anno_frequencies`]
+    pub(crate) fn node_anno_frequencies(&self) -> anyhow::Result<BTreeMap<String, u64>> {
+        self.storage.node_anno_frequencies(self.name)
+    }
+
+    /// Names of the documents of this corpus, obtained without loading the document graphs
+    pub(crate) fn document_names(&self) -> anyhow::Result<Vec<String>> {
+        self.document_node_names(0, None)?
+            .iter()
+            .map(|node_name| node_name::doc_name(node_name))
+            .collect()
+    }
+
+    /// Names of documents without their subcorpora (i.e. their `annis:doc` values) that are shared
+    /// by several documents of this corpus in different subcorpora, together with the full names of
+    /// these documents
+    pub(crate) fn duplicate_document_names(&self) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+        let mut doc_names_by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for doc_name in self.document_names()? {
+            let name = doc_name.rsplit('/').next().unwrap_or(&doc_name).to_string();
+            doc_names_by_name.entry(name).or_default().push(doc_name);
+        }
+
+        doc_names_by_name.retain(|_, doc_names| doc_names.len() > 1);
+
+        Ok(doc_names_by_name)
+    }
+
+    /// Names of the documents of this corpus together with the names of their document nodes
+    pub(crate) fn document_node_names_by_name(&self) -> anyhow::Result<Vec<(String, String)>> {
+        self.document_node_names(0, None)?
+            .into_iter()
+            .map(|node_name| Ok((node_name::doc_name(&node_name)?, node_name)))
+            .collect()
+    }
+
+    /// Documents of this corpus whose names satisfy `filter`
+    ///
+    /// The names of the document nodes are queried in pages of [`DOCUMENT_PAGE_SIZE`] and the
+    /// document graphs are loaded one by one while iterating, so that stopping early (e.g. with
+    /// `--limit-docs`) neither queries nor loads the remaining documents.
+    pub(crate) fn documents<'s>(
+        &'s self,
+        mut filter: impl FnMut(&str) -> bool + 's,
+    ) -> impl Iterator<Item = anyhow::Result<Document>> + 's {
+        let mut page = Vec::new().into_iter();
+        let mut offset = 0;
+        let mut exhausted = false;
+
+        iter::from_fn(move || loop {
+            let node_name = match page.next() {
+                Some(node_name) => node_name,
+                None if exhausted => return None,
+                None => match self.document_node_names(offset, Some(DOCUMENT_PAGE_SIZE)) {
+                    Ok(node_names) => {
+                        offset += node_names.len();
+                        exhausted = node_names.len() < DOCUMENT_PAGE_SIZE;
+                        page = node_names.into_iter();
+                        continue;
+                    }
+                    Err(err) => {
+                        exhausted = true;
+                        return Some(Err(err));
+                    }
+                },
+            };
+
+            let doc_name = match node_name::doc_name(&node_name) {
+                Ok(doc_name) => doc_name,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if !filter(&doc_name) {
+                continue;
+            }
+
+            return Some(
+                self.storage
+                    .subcorpus_graph(self.name, vec![node_name.clone()])
+                    .map(|graph| Document {
+                        graph,
+                        node_name,
+                        doc_name,
+                    }),
+            );
+        })
+    }
+
+    /// Names of the document nodes of this corpus in a stable order, starting at `offset` and
+    /// limited to `limit` names, if specified
+    fn document_node_names(
         &self,
-    ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<Document>> + '_> {
+        offset: usize,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<String>> {
         let matches = self.storage.find(
             SearchQuery {
                 corpus_names: &[self.name],
@@ -89,107 +294,442 @@ impl<'a> Corpus<'a> {
                 query_language: QueryLanguage::AQL,
                 timeout: None,
             },
-            0,
-            None,
+            offset,
+            limit,
             ResultOrder::Normal,
         )?;
 
-        Ok(matches.into_iter().map(|m| {
-            let node_name = node_names_from_match(&m).into_iter().exactly_one()?;
-
-            Ok(Document {
-                graph: self
-                    .storage
-                    .subcorpus_graph(self.name, vec![node_name.clone()])?,
-                node_name,
-            })
-        }))
+        matches
+            .iter()
+            .map(|m| Ok(node_names_from_match(m).into_iter().exactly_one()?))
+            .collect()
     }
 }
 
 pub(crate) struct Document {
     graph: AnnotationGraph,
     node_name: String,
+    /// Decoded document name, see [`node_name::doc_name`]
+    doc_name: String,
 }
 
 impl Document {
+    /// Creates a document from a graph that is not part of any corpus storage, where `node_name`
+    /// is the name of the document node, e.g. `corpus/doc`
+    pub(crate) fn new(graph: AnnotationGraph, node_name: String) -> anyhow::Result<Self> {
+        let doc_name = node_name::doc_name(&node_name)?;
+
+        Ok(Self {
+            graph,
+            node_name,
+            doc_name,
+        })
+    }
+
     pub(crate) fn node_name(&self) -> NodeName<'_> {
         NodeName(Cow::Borrowed(&self.node_name))
     }
 
-    pub(crate) fn doc_name(&self) -> anyhow::Result<&str> {
-        let (_, doc_name) = self.node_name.split_once('/').ok_or_else(|| {
-            anyhow!(
-                "could not get document name from node name {}",
-                self.node_name
-            )
-        })?;
+    pub(crate) fn doc_name(&self) -> &str {
+        &self.doc_name
+    }
 
-        Ok(doc_name)
+    pub(crate) fn has_node(&self, node_name: &str) -> anyhow::Result<bool> {
+        Ok(self.graph.get_node_annos().has_node_name(node_name)?)
     }
 
-    pub(crate) fn segmentation_nodes_in_order(
+    /// Node of the given name, if any
+    pub(crate) fn node(&self, node_name: &str) -> anyhow::Result<Option<Node<'_>>> {
+        Ok(self
+            .graph
+            .get_node_annos()
+            .get_node_id_from_name(node_name)?
+            .map(|id| Node {
+                graph: &self.graph,
+                id,
+            }))
+    }
+
+    /// Lists all components of the document graph together with whether they are considered when
+    /// determining the segmentation nodes
+    pub(crate) fn components(
         &self,
         segmentation: &str,
-    ) -> anyhow::Result<Nodes<'_>> {
-        let ordering_storage = self
-            .graph
-            .get_graphstorage(&DEFAULT_ORDERING_COMPONENT)
-            .ok_or_else(|| anyhow!("default ordering component not found"))?;
+        selection: &ComponentSelection,
+    ) -> anyhow::Result<Vec<(AnnotationComponent, ComponentStatus)>> {
+        let ordering = self.ordering(segmentation, selection.ordering)?;
 
-        let coverage_storages = self
+        Ok(self
             .graph
-            .get_all_components(Some(AnnotationComponentType::Coverage), None)
+            .get_all_components(None, None)
             .into_iter()
-            .filter_map(|c| self.graph.get_graphstorage_as_ref(&c))
-            .filter(|gs| {
-                gs.get_statistics()
-                    .map(|stats| stats.nodes > 0)
-                    .unwrap_or(true)
+            .map(|c| {
+                let status = match (c.get_type(), &ordering) {
+                    (AnnotationComponentType::Ordering, _) if c == *ordering.component() => {
+                        ComponentStatus::Considered
+                    }
+                    (AnnotationComponentType::Coverage, SelectedOrdering::Tokens(_)) => {
+                        let is_empty = self
+                            .graph
+                            .get_graphstorage_as_ref(&c)
+                            .and_then(|gs| gs.get_statistics())
+                            .is_some_and(|stats| stats.nodes == 0);
+
+                        selection.coverage.status(&c, is_empty)
+                    }
+                    _ => ComponentStatus::Unused,
+                };
+
+                (c, status)
             })
-            .collect_vec();
+            .collect())
+    }
 
+    pub(crate) fn segmentation_nodes_in_order(
+        &self,
+        segmentation: &str,
+        selection: &ComponentSelection,
+    ) -> anyhow::Result<Nodes<'_>> {
         let segmentation_anno_key = AnnoKey {
             ns: DEFAULT_NS.into(),
             name: segmentation.into(),
         };
 
+        let has_segmentation_anno = |node_id: &NodeID| -> anyhow::Result<bool> {
+            Ok(self
+                .graph
+                .get_node_annos()
+                .get_value_for_item(node_id, &segmentation_anno_key)?
+                .is_some())
+        };
+
         let mut segmentation_node_ids = Vec::new();
 
-        let mut next_token_id = ordering_storage
+        match self.ordering(segmentation, selection.ordering)? {
+            SelectedOrdering::Tokens(ordering_component) => {
+                let coverage_components = self
+                    .components(segmentation, selection)?
+                    .into_iter()
+                    .filter(|(c, status)| {
+                        c.get_type() == AnnotationComponentType::Coverage
+                            && *status == ComponentStatus::Considered
+                    })
+                    .map(|(c, _)| c)
+                    .collect_vec();
+
+                let coverage_storages = coverage_components
+                    .iter()
+                    .filter_map(|c| self.graph.get_graphstorage_as_ref(c))
+                    .collect_vec();
+
+                self.for_each_in_order(&ordering_component, |token_id| {
+                    for coverage_storage in &coverage_storages {
+                        for covering_node_id in coverage_storage.get_ingoing_edges(token_id) {
+                            let covering_node_id = covering_node_id?;
+
+                            if has_segmentation_anno(&covering_node_id)?
+                                && !segmentation_node_ids.contains(&covering_node_id)
+                            {
+                                segmentation_node_ids.push(covering_node_id);
+                            }
+                        }
+                    }
+
+                    Ok(())
+                })?;
+            }
+            SelectedOrdering::Segmentation(ordering_component) => {
+                self.for_each_in_order(&ordering_component, |node_id| {
+                    if has_segmentation_anno(&node_id)? {
+                        segmentation_node_ids.push(node_id);
+                    }
+
+                    Ok(())
+                })?;
+            }
+        }
+
+        Ok(Nodes {
+            graph: &self.graph,
+            ids_iter: segmentation_node_ids.into_iter(),
+        })
+    }
+
+    fn ordering(
+        &self,
+        segmentation: &str,
+        ordering: OrderingSource,
+    ) -> anyhow::Result<SelectedOrdering> {
+        let segmentation_component = || {
+            self.graph
+                .get_all_components(Some(AnnotationComponentType::Ordering), Some(segmentation))
+                .into_iter()
+                .at_most_one()
+                .map_err(|_| {
+                    anyhow!("multiple ordering components for segmentation {segmentation}")
+                })
+        };
+
+        let default_component = || {
+            self.graph
+                .get_graphstorage_as_ref(&DEFAULT_ORDERING_COMPONENT)
+                .map(|_| DEFAULT_ORDERING_COMPONENT.clone())
+        };
+
+        Ok(match ordering {
+            OrderingSource::Tokens => SelectedOrdering::Tokens(
+                default_component()
+                    .ok_or_else(|| anyhow!("default ordering component not found"))?,
+            ),
+            OrderingSource::Segmentation => {
+                SelectedOrdering::Segmentation(segmentation_component()?.ok_or_else(|| {
+                    anyhow!("ordering component for segmentation {segmentation} not found")
+                })?)
+            }
+            OrderingSource::Auto => match segmentation_component()? {
+                Some(c) => SelectedOrdering::Segmentation(c),
+                None => SelectedOrdering::Tokens(default_component().ok_or_else(|| {
+                    anyhow!(
+                        "neither ordering component for segmentation {segmentation} nor default \
+                         ordering component found"
+                    )
+                })?),
+            },
+        })
+    }
+
+    fn for_each_in_order(
+        &self,
+        ordering_component: &AnnotationComponent,
+        mut f: impl FnMut(NodeID) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let ordering_storage = self
+            .graph
+            .get_graphstorage_as_ref(ordering_component)
+            .ok_or_else(|| anyhow!("ordering component {ordering_component} not found"))?;
+
+        let mut next_node_id = ordering_storage
             .root_nodes()
             .at_most_one()
             .map_err(|err| anyhow::Error::msg(err.to_string()))?;
 
-        while let Some(token_id) = next_token_id.take() {
-            let token_id = token_id?;
-
-            for coverage_storage in &coverage_storages {
-                for covering_node_id in coverage_storage.get_ingoing_edges(token_id) {
-                    let covering_node_id = covering_node_id?;
-
-                    if self
-                        .graph
-                        .get_node_annos()
-                        .get_value_for_item(&covering_node_id, &segmentation_anno_key)?
-                        .is_some()
-                        && !segmentation_node_ids.contains(&covering_node_id)
-                    {
-                        segmentation_node_ids.push(covering_node_id);
-                    }
-                }
-            }
+        while let Some(node_id) = next_node_id.take() {
+            let node_id = node_id?;
 
-            next_token_id = ordering_storage
-                .get_outgoing_edges(token_id)
+            f(node_id)?;
+
+            next_node_id = ordering_storage
+                .get_outgoing_edges(node_id)
                 .at_most_one()
                 .map_err(|err| anyhow::Error::msg(err.to_string()))?;
         }
 
-        Ok(Nodes {
-            graph: &self.graph,
-            ids_iter: segmentation_node_ids.into_iter(),
+        Ok(())
+    }
+}
+
+/// Imports the given corpora from the ZIP file at the given path one by one, each in memory or on
+/// disk as determined by `in_memory`, extracting only their files
+fn import_entries(
+    storage: &annis_util::StorageSession,
+    path: &Path,
+    entries: &[annis_zip::Entry],
+    in_memory: impl Fn(&annis_zip::Entry) -> bool,
+) -> anyhow::Result<Vec<String>> {
+    let extract_dir = tempfile::tempdir()?;
+    annis_zip::extract(path, extract_dir.path(), |file_path| {
+        entries.iter().any(|entry| entry.contains(file_path))
+    })?;
+
+    entries
+        .iter()
+        .map(|entry| {
+            let import_format = match entry.format()? {
+                annis_zip::Format::RelAnnis32 | annis_zip::Format::RelAnnis33 => {
+                    ImportFormat::RelANNIS
+                }
+                annis_zip::Format::GraphMl => ImportFormat::GraphML,
+            };
+
+            let in_memory = in_memory(entry);
+
+            info!(
+                path = %entry.path().display(),
+                size = entry.size(),
+                in_memory,
+                "importing corpus"
+            );
+
+            storage
+                .import_from_fs(
+                    &extract_dir.path().join(entry.path()),
+                    import_format,
+                    None,
+                    !in_memory,
+                    false, /* overwrite_existing */
+                    |msg| info!("{msg}"),
+                )
+                .map_err(|err| anyhow!("importing corpus {}: {err}", entry.path().display()))
         })
+        .collect()
+}
+
+/// Ordering component used to determine the order of the segmentation nodes
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum OrderingSource {
+    /// Use the default ordering of the tokens and find the segmentation nodes covering them
+    Tokens,
+    /// Use the segmentation's own ordering component
+    Segmentation,
+    /// Use the segmentation's own ordering component if present, otherwise fall back to `tokens`
+    Auto,
+}
+
+enum SelectedOrdering {
+    Tokens(AnnotationComponent),
+    Segmentation(AnnotationComponent),
+}
+
+impl SelectedOrdering {
+    fn component(&self) -> &AnnotationComponent {
+        match self {
+            SelectedOrdering::Tokens(c) | SelectedOrdering::Segmentation(c) => c,
+        }
+    }
+}
+
+/// Selection of the components used to determine the segmentation nodes
+#[derive(Debug)]
+pub(crate) struct ComponentSelection {
+    pub(crate) ordering: OrderingSource,
+    pub(crate) coverage: CoverageFilter,
+}
+
+/// Selection of the coverage components used to determine the segmentation nodes when ordering by
+/// tokens
+#[derive(Debug, Default)]
+pub(crate) struct CoverageFilter {
+    /// If non-empty, only these components are considered
+    pub(crate) include: Vec<ComponentName>,
+    /// These components are never considered
+    pub(crate) exclude: Vec<ComponentName>,
+}
+
+impl CoverageFilter {
+    fn status(&self, component: &AnnotationComponent, is_empty: bool) -> ComponentStatus {
+        let matches = |name: &ComponentName| {
+            component.layer.as_str() == name.layer && component.name.as_str() == name.name
+        };
+
+        if self.exclude.iter().any(matches) {
+            ComponentStatus::Excluded
+        } else if !self.include.is_empty() && !self.include.iter().any(matches) {
+            ComponentStatus::NotIncluded
+        } else if is_empty {
+            ComponentStatus::Empty
+        } else {
+            ComponentStatus::Considered
+        }
+    }
+}
+
+/// Layer and name of a component, given as `LAYER/NAME`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ComponentName {
+    layer: String,
+    name: String,
+}
+
+impl FromStr for ComponentName {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (layer, name) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("component must be given as `LAYER/NAME`"))?;
+
+        Ok(Self {
+            layer: layer.into(),
+            name: name.into(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ComponentStatus {
+    Considered,
+    Empty,
+    Excluded,
+    NotIncluded,
+    Unused,
+}
+
+impl Display for ComponentStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ComponentStatus::Considered => write!(f, "considered"),
+            ComponentStatus::Empty => write!(f, "ignored (empty)"),
+            ComponentStatus::Excluded => write!(f, "ignored (excluded)"),
+            ComponentStatus::NotIncluded => write!(f, "ignored (not included)"),
+            ComponentStatus::Unused => write!(f, "ignored (not used)"),
+        }
+    }
+}
+
+/// Filter for segmentation nodes, given as `[NS:]NAME="VALUE"` or `[NS:]NAME=/REGEX/` like in AQL
+#[derive(Clone, Debug)]
+pub(crate) struct TokenFilter {
+    ns: Option<String>,
+    name: String,
+    value: ValueMatcher,
+}
+
+#[derive(Clone, Debug)]
+enum ValueMatcher {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl FromStr for TokenFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s.split_once('=').ok_or_else(|| {
+            anyhow!("filter must be given as `[NS:]NAME=\"VALUE\"` or `[NS:]NAME=/REGEX/`")
+        })?;
+
+        let (ns, name) = match key.split_once(':') {
+            Some((ns, name)) => (Some(ns.into()), name.into()),
+            None => (None, key.into()),
+        };
+
+        let value = if let Some(regex) = value.strip_prefix('/').and_then(|v| v.strip_suffix('/')) {
+            // like in AQL, the regex must match the whole value
+            ValueMatcher::Regex(Regex::new(&format!("^(?:{regex})$"))?)
+        } else if let Some(value) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            ValueMatcher::Exact(value.into())
+        } else {
+            bail!("filter value must be given as `\"VALUE\"` or `/REGEX/`");
+        };
+
+        Ok(Self { ns, name, value })
+    }
+}
+
+impl TokenFilter {
+    pub(crate) fn matches(&self, node: &Node<'_>) -> anyhow::Result<bool> {
+        for value in node.annos_named(self.ns.as_deref(), &self.name)? {
+            let matches = match &self.value {
+                ValueMatcher::Exact(expected) => value == *expected,
+                ValueMatcher::Regex(regex) => regex.is_match(&value),
+            };
+
+            if matches {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
     }
 }
 
@@ -222,6 +762,20 @@ impl<'a> Node<'a> {
             .get_value_for_item(&self.id, anno_key)?)
     }
 
+    /// Values of the annotations with the given name, in the given namespace or any namespace
+    fn annos_named(&self, ns: Option<&str>, name: &str) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .graph
+            .get_node_annos()
+            .get_annotations_for_item(&self.id)?
+            .into_iter()
+            .filter(|anno| {
+                anno.key.name.as_str() == name && ns.map_or(true, |ns| anno.key.ns.as_str() == ns)
+            })
+            .map(|anno| anno.val.into())
+            .collect())
+    }
+
     pub(crate) fn name(&self) -> anyhow::Result<NodeName<'a>> {
         Ok(NodeName(self.anno(&NODE_NAME_KEY)?.ok_or_else(|| {
             anyhow!("node {} has no annis:node_name", self.id)
@@ -249,3 +803,68 @@ impl Display for NodeName<'_> {
         write!(f, "{}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use graphannis::update::{GraphUpdate, UpdateEvent};
+
+    use super::*;
+
+    #[test]
+    fn documents_are_queried_in_pages() -> anyhow::Result<()> {
+        let handle = annis_util::StorageHandle::new()?;
+        let doc_count = 2 * DOCUMENT_PAGE_SIZE + 1;
+
+        let mut update = GraphUpdate::new();
+        update.add_event(UpdateEvent::AddNode {
+            node_name: "corpus".into(),
+            node_type: "corpus".into(),
+        })?;
+
+        for i in 0..doc_count {
+            let node_name = format!("corpus/doc{i}");
+
+            update.add_event(UpdateEvent::AddNode {
+                node_name: node_name.clone(),
+                node_type: "corpus".into(),
+            })?;
+            update.add_event(UpdateEvent::AddNodeLabel {
+                node_name: node_name.clone(),
+                anno_ns: ANNIS_NS.into(),
+                anno_name: "doc".into(),
+                anno_value: format!("doc{i}"),
+            })?;
+            update.add_event(UpdateEvent::AddEdge {
+                source_node: node_name,
+                target_node: "corpus".into(),
+                layer: ANNIS_NS.into(),
+                component_type: "PartOf".into(),
+                component_name: String::new(),
+            })?;
+        }
+
+        handle.session().apply_update("corpus", &mut update)?;
+
+        let corpus = Corpus {
+            handle: &handle,
+            storage: handle.view(),
+            graphml_path: None,
+            name: "corpus",
+        };
+
+        let doc_names = corpus
+            .documents(|_| true)
+            .map_ok(|doc| doc.doc_name)
+            .collect::<anyhow::Result<BTreeSet<_>>>()?;
+        assert_eq!(doc_names.len(), doc_count);
+
+        let doc_names = corpus
+            .documents(|doc_name| doc_name.ends_with('0'))
+            .map_ok(|doc| doc.doc_name)
+            .take(3)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(doc_names.len(), 3);
+
+        Ok(())
+    }
+}
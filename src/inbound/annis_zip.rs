@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure};
+use tracing::info;
+use zip::ZipArchive;
+
+/// Version of graphannis this tool is built against, checked against `Cargo.toml` by a test
+pub(crate) const GRAPHANNIS_VERSION: &str = "3.4";
+
+/// Input formats supported by [`GRAPHANNIS_VERSION`]
+pub(crate) const SUPPORTED_FORMATS: [Format; 3] =
+    [Format::RelAnnis32, Format::RelAnnis33, Format::GraphMl];
+
+const ANNIS_VERSION_FILE_NAME: &str = "annis.version";
+const CORPUS_ANNIS_FILE_NAME: &str = "corpus.annis";
+const CORPUS_TAB_FILE_NAME: &str = "corpus.tab";
+const GRAPHML_EXTENSION: &str = "graphml";
+
+/// Tables every relANNIS corpus must contain, as `TABLE.annis` (relANNIS 3.3) or `TABLE.tab`
+/// (relANNIS 3.2)
+const RELANNIS_TABLES: [&str; 6] = [
+    "component",
+    "edge_annotation",
+    "node",
+    "node_annotation",
+    "rank",
+    "text",
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Format {
+    RelAnnis32,
+    RelAnnis33,
+    GraphMl,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::RelAnnis32 => write!(f, "relANNIS 3.2"),
+            Format::RelAnnis33 => write!(f, "relANNIS 3.3"),
+            Format::GraphMl => write!(f, "GraphML"),
+        }
+    }
+}
+
+/// A corpus found in the input ZIP file, identified by the path of its relANNIS directory or
+/// GraphML file within the ZIP file
+#[derive(Debug)]
+pub(crate) struct Entry {
+    path: PathBuf,
+    kind: EntryKind,
+    /// Total uncompressed size of the files of the corpus in bytes
+    size: u64,
+}
+
+#[derive(Debug)]
+enum EntryKind {
+    RelAnnis(RelAnnisFiles),
+    GraphMl,
+}
+
+#[derive(Debug, Default)]
+struct RelAnnisFiles {
+    version: Option<String>,
+    has_corpus_annis: bool,
+    has_corpus_tab: bool,
+    /// Names of all files in the relANNIS directory
+    file_names: BTreeSet<String>,
+}
+
+impl Entry {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Name of the corpus if it is a GraphML corpus, which graphannis derives from the file name
+    pub(crate) fn corpus_name(&self) -> Option<String> {
+        match self.kind {
+            EntryKind::GraphMl => Some(self.path.file_stem()?.to_string_lossy().into_owned()),
+            EntryKind::RelAnnis(_) => None,
+        }
+    }
+
+    /// Whether the file at the given path within the ZIP file belongs to the corpus, i.e. is its
+    /// GraphML file or lies within its relANNIS directory or the directory of its linked files
+    pub(crate) fn contains(&self, file_path: &Path) -> bool {
+        file_path.starts_with(&self.path) || file_path.starts_with(self.path.with_extension(""))
+    }
+
+    /// Determines the format of the corpus, failing with an actionable message if the format is not
+    /// supported by [`GRAPHANNIS_VERSION`]
+    pub(crate) fn format(&self) -> anyhow::Result<Format> {
+        let path = self.path.display();
+
+        match &self.kind {
+            EntryKind::GraphMl => Ok(Format::GraphMl),
+            EntryKind::RelAnnis(RelAnnisFiles {
+                version,
+                has_corpus_annis,
+                has_corpus_tab,
+                file_names,
+            }) => match (version.as_deref(), *has_corpus_annis, *has_corpus_tab) {
+                (Some("3.3"), true, _) => check_tables(&path, file_names, Format::RelAnnis33),
+                (None, _, true) => check_tables(&path, file_names, Format::RelAnnis32),
+                (Some("3.3"), false, _) => bail!(
+                    "relANNIS corpus {path} declares version 3.3, but contains no {CORPUS_ANNIS_FILE_NAME}; \
+                     the corpus seems to be incomplete, re-export it"
+                ),
+                (Some(version), _, _) => bail!(
+                    "relANNIS corpus {path} was exported with relANNIS {version}, but graphannis \
+                     {GRAPHANNIS_VERSION} only supports relANNIS 3.2 and 3.3; re-export the corpus as \
+                     relANNIS 3.3 or GraphML"
+                ),
+                (None, _, false) => bail!(
+                    "relANNIS corpus {path} contains no {ANNIS_VERSION_FILE_NAME}, but relANNIS 3.2 \
+                     requires {CORPUS_TAB_FILE_NAME}; re-export the corpus as relANNIS 3.3 or GraphML"
+                ),
+            },
+        }
+    }
+}
+
+/// Checks that a relANNIS corpus in the given format contains all of [`RELANNIS_TABLES`]
+fn check_tables(
+    path: &impl Display,
+    file_names: &BTreeSet<String>,
+    format: Format,
+) -> anyhow::Result<Format> {
+    let extension = match format {
+        Format::RelAnnis33 => "annis",
+        _ => "tab",
+    };
+
+    let missing_files: Vec<_> = RELANNIS_TABLES
+        .iter()
+        .map(|table| format!("{table}.{extension}"))
+        .filter(|file_name| !file_names.contains(file_name))
+        .collect();
+
+    ensure!(
+        missing_files.is_empty(),
+        "{format} corpus {path} is missing {}; the corpus seems to be incomplete, re-export it",
+        missing_files.join(", ")
+    );
+
+    Ok(format)
+}
+
+/// Lists the corpora contained in the ZIP file at the given path without importing them
+pub(crate) fn inspect(path: &Path) -> anyhow::Result<Vec<Entry>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut relannis_dirs: BTreeMap<PathBuf, RelAnnisFiles> = BTreeMap::new();
+    let mut graphml_files = Vec::new();
+    let mut dir_file_names: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+    let mut file_sizes = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+
+        let Some(file_path) = file.enclosed_name() else {
+            continue;
+        };
+
+        let (Some(file_name), Some(dir)) = (file_path.file_name(), file_path.parent()) else {
+            continue;
+        };
+
+        dir_file_names
+            .entry(dir.to_path_buf())
+            .or_default()
+            .insert(file_name.to_string_lossy().into_owned());
+
+        file_sizes.push((file_path.clone(), file.size()));
+
+        if file_name == ANNIS_VERSION_FILE_NAME
+            || file_name == CORPUS_ANNIS_FILE_NAME
+            || file_name == CORPUS_TAB_FILE_NAME
+        {
+            let files = relannis_dirs.entry(dir.to_path_buf()).or_default();
+
+            if file_name == ANNIS_VERSION_FILE_NAME {
+                let mut version_str = String::new();
+                file.read_to_string(&mut version_str)?;
+                files.version = Some(version_str.trim().into());
+            } else if file_name == CORPUS_ANNIS_FILE_NAME {
+                files.has_corpus_annis = true;
+            } else {
+                files.has_corpus_tab = true;
+            }
+        } else if file_path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(GRAPHML_EXTENSION))
+        {
+            graphml_files.push(file_path);
+        }
+    }
+
+    // a directory that only contains `annis.version` is not a corpus
+    relannis_dirs.retain(|_, files| files.has_corpus_annis || files.has_corpus_tab);
+
+    for (dir, files) in &mut relannis_dirs {
+        files.file_names = dir_file_names.remove(dir).unwrap_or_default();
+    }
+
+    // a relANNIS corpus consists of all files within its directory, including subdirectories for
+    // linked files
+    let size_within = |dir: &Path| {
+        file_sizes
+            .iter()
+            .filter(|(file_path, _)| file_path.starts_with(dir))
+            .map(|(_, size)| size)
+            .sum::<u64>()
+    };
+
+    let size_of = |path: &Path| {
+        file_sizes
+            .iter()
+            .find(|(file_path, _)| file_path == path)
+            .map_or(0, |(_, size)| *size)
+    };
+
+    Ok(relannis_dirs
+        .into_iter()
+        .map(|(path, files)| Entry {
+            size: size_within(&path),
+            path,
+            kind: EntryKind::RelAnnis(files),
+        })
+        .chain(graphml_files.into_iter().map(|path| Entry {
+            size: size_of(&path),
+            path,
+            kind: EntryKind::GraphMl,
+        }))
+        .collect())
+}
+
+/// Extracts the files of the ZIP file at the given path for whose paths `filter` returns `true`
+/// into `dir`
+pub(crate) fn extract(
+    path: &Path,
+    dir: &Path,
+    filter: impl Fn(&Path) -> bool,
+) -> anyhow::Result<()> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+
+        let Some(file_path) = file.enclosed_name() else {
+            continue;
+        };
+
+        if !filter(&file_path) {
+            continue;
+        }
+
+        let output_path = dir.join(file_path);
+
+        if file.is_dir() {
+            fs::create_dir_all(output_path)?;
+        } else {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            io::copy(&mut file, &mut File::create(output_path)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the ZIP file at the given path contains at least one corpus and that all corpora are
+/// in a format supported by [`GRAPHANNIS_VERSION`]
+pub(crate) fn check_compatibility(path: &Path) -> anyhow::Result<()> {
+    let entries = inspect(path)?;
+
+    if entries.is_empty() {
+        bail!(
+            "{} contains neither relANNIS corpora ({CORPUS_ANNIS_FILE_NAME} or {CORPUS_TAB_FILE_NAME}) \
+             nor GraphML files",
+            path.display()
+        );
+    }
+
+    for entry in &entries {
+        let format = entry.format()?;
+        info!(path = %entry.path().display(), %format, "found corpus");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graphannis_version_matches_manifest() {
+        let manifest: toml::Table =
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+                .parse()
+                .unwrap();
+
+        for dependency in ["graphannis", "graphannis-core"] {
+            let version = manifest["dependencies"][dependency]["version"]
+                .as_str()
+                .unwrap();
+
+            assert!(
+                version.starts_with(&format!("{GRAPHANNIS_VERSION}.")),
+                "{dependency} {version} in Cargo.toml doesn't match {GRAPHANNIS_VERSION}"
+            );
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing::info;
+
+/// Document-level metadata, read from a TOML file of the form
+///
+/// ```toml
+/// [documents.DOC]
+/// date = "1250-1300"
+/// region = "bair."
+/// ```
+///
+/// where non-string values are written in their TOML representation.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Metadata {
+    #[serde(default)]
+    documents: HashMap<String, BTreeMap<String, toml::Value>>,
+}
+
+impl Metadata {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let metadata: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        info!(
+            path = %path.display(),
+            documents = metadata.documents.len(),
+            "read document metadata"
+        );
+
+        Ok(metadata)
+    }
+
+    /// Metadata entries (key and value) for the given document
+    pub(crate) fn for_document<'a>(
+        &'a self,
+        doc_name: &str,
+    ) -> impl Iterator<Item = (&'a str, String)> + 'a {
+        self.documents
+            .get(doc_name)
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| {
+                let value = match value {
+                    toml::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+
+                (key.as_str(), value)
+            })
+    }
+
+    /// Names of the documents with metadata
+    pub(crate) fn document_names(&self) -> impl Iterator<Item = &str> {
+        self.documents.keys().map(String::as_str)
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing::info;
+
+/// Allowed categories of the nonterminals, read from a TOML file of the form
+///
+/// ```toml
+/// cat = ["NP", "PP", "VP"]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Tagset {
+    cat: HashSet<String>,
+}
+
+impl Tagset {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let tagset: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        info!(path = %path.display(), cat = tagset.cat.len(), "read tagset");
+
+        Ok(tagset)
+    }
+
+    /// Parts of the given category not contained in the tagset, where a category of a collapsed
+    /// unary chain is split at `separator` into the categories along the chain
+    pub(crate) fn invalid_parts<'a>(
+        &'a self,
+        category: &'a str,
+        separator: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        let parts: Vec<&str> = match separator {
+            Some(separator) => category.split(separator).collect(),
+            None => vec![category],
+        };
+
+        parts.into_iter().filter(|part| !self.cat.contains(*part))
+    }
+}
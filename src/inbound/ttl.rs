@@ -1,19 +1,34 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+#[cfg(not(target_arch = "wasm32"))]
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::BufReader;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::{self, File};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{self, BufReader};
+use std::io::{BufRead, Cursor};
 use std::iter::successors;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::path::{Path, PathBuf};
-use std::{fmt, fs, vec};
+#[cfg(not(target_arch = "wasm32"))]
+use std::process::{Command, Stdio};
+use std::{fmt, vec};
 
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::ensure;
 use anyhow::{anyhow, bail};
+use clap::ValueEnum;
 use itertools::Itertools;
 use rio_api::model::{Literal, NamedNode, Subject, Term};
-use rio_api::parser::TriplesParser;
+use rio_api::parser::{ParseError as _, TriplesParser};
 use rio_turtle::{TurtleError, TurtleParser};
-use tracing::{info, warn};
+#[cfg(not(target_arch = "wasm32"))]
+use sha2::{Digest, Sha256};
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::info;
+
+use crate::warnings::Warnings;
 
 macro_rules! define_named_nodes {
     (
@@ -47,6 +62,8 @@ define_named_nodes! {
         WORD = "WORD",
     },
     nif = "http://persistence.uni-leipzig.org/nlp2rdf/ontologies/nif-core#" {
+        BEGIN_INDEX = "beginIndex",
+        END_INDEX = "endIndex",
         NEXT_SENTENCE = "nextSentence",
         NEXT_WORD = "nextWord",
         SENTENCE = "Sentence",
@@ -58,29 +75,231 @@ define_named_nodes! {
     rdf = "http://www.w3.org/1999/02/22-rdf-syntax-ns#" {
         TYPE = "type",
     },
+    xsd = "http://www.w3.org/2001/XMLSchema#" {
+        BOOLEAN = "boolean",
+        INT = "int",
+        INTEGER = "integer",
+        LONG = "long",
+        NON_NEGATIVE_INTEGER = "nonNegativeInteger",
+    },
 }
 
+/// TTL files of a directory, parsed on demand
+///
+/// Like all file system and process access of this module, this is only available natively, while
+/// [`Document::from_reader`] also works on `wasm32`, e.g. to validate TTL data in the browser.
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug)]
 pub(crate) struct Storage {
-    dir: PathBuf,
+    route_predicate: Option<String>,
+    /// Preferred language tags of literals, see [`Self::with_languages`]
+    languages: Vec<String>,
+    /// Shell command through which each TTL file is piped before parsing, see
+    /// [`Self::with_pre_process`]
+    pre_process: Option<String>,
+    /// Maximum number of malformed statements skipped per TTL file, see
+    /// [`Self::with_max_skipped_statements`]
+    max_skipped_statements: usize,
+    /// Names of the TTL documents by the names of the ANNIS documents they are used for, see
+    /// [`Self::with_doc_map`]
+    doc_map: HashMap<String, String>,
+    /// Full names of the ANNIS documents sharing their name without subcorpora with other
+    /// documents, by the full names of the documents, see [`Self::with_duplicate_names`]
+    duplicate_names: HashMap<String, Vec<String>>,
+    /// Paths of the TTL files in the directory, sorted by path
+    file_paths: Vec<PathBuf>,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Storage {
-    pub(crate) fn from_dir(dir: PathBuf) -> Self {
-        Self { dir }
+    /// Creates a storage for the TTL files in `dir`, where the values of `route_predicate` (an
+    /// IRI) are recorded for each node if specified
+    ///
+    /// The directory is only listed once here, the TTL files are parsed on demand.
+    pub(crate) fn from_dir(dir: &Path, route_predicate: Option<String>) -> anyhow::Result<Self> {
+        let mut file_paths = Vec::new();
+
+        for entry in fs::read_dir(dir)? {
+            let file_path = entry?.path();
+
+            if file_path.extension() == Some(OsStr::new("ttl")) {
+                file_paths.push(file_path);
+            }
+        }
+
+        file_paths.sort();
+
+        info!(dir = %dir.display(), count = file_paths.len(), "indexed ttl files");
+
+        Ok(Self {
+            route_predicate,
+            languages: Vec::new(),
+            pre_process: None,
+            max_skipped_statements: 0,
+            doc_map: HashMap::new(),
+            duplicate_names: HashMap::new(),
+            file_paths,
+        })
+    }
+
+    /// Sets the preferred language tags (e.g. `gmh`) of literals in order of preference
+    ///
+    /// If a node has several literals for the same annotation, the one whose language comes first
+    /// is used, where literals without language tag come after all given languages and literals
+    /// with other language tags are ignored. If no languages are given, literals with any language
+    /// tag are accepted.
+    pub(crate) fn with_languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = languages;
+        self
     }
 
-    pub(crate) fn document_for_name(&self, doc_name: &str) -> anyhow::Result<Option<Document>> {
-        let mut doc_path: Option<PathBuf> = None;
+    /// Sets a shell command (e.g. `sed 's/foo/bar/'`) that receives each TTL file on stdin and
+    /// whose stdout is parsed instead of the file, e.g. to fix known data errors without modifying
+    /// the files themselves
+    pub(crate) fn with_pre_process(mut self, pre_process: Option<String>) -> Self {
+        self.pre_process = pre_process;
+        self
+    }
 
-        for entry in fs::read_dir(&self.dir)? {
-            let file_path = entry?.path();
+    /// Sets the maximum number of malformed statements that are skipped in each TTL file in order
+    /// to parse the rest of the file, where `0` means that a TTL file with any syntax error is not
+    /// used at all
+    ///
+    /// Each skipped statement is reported as a warning with its line and column.
+    pub(crate) fn with_max_skipped_statements(mut self, max_skipped_statements: usize) -> Self {
+        self.max_skipped_statements = max_skipped_statements;
+        self
+    }
 
-            if file_path.extension() == Some(OsStr::new("ttl"))
-                && file_path
-                    .file_stem()
-                    .and_then(|stem| stem.to_str())
-                    .is_some_and(|stem| stem.starts_with(&format!("{doc_name}_")))
+    /// Sets a mapping from the names of ANNIS documents to the names of TTL documents (inferred
+    /// from the file names, see [`Self::document_names`]), which is used to find the TTL files of
+    /// the mapped documents instead of their own names
+    pub(crate) fn with_doc_map(mut self, doc_map: HashMap<String, String>) -> Self {
+        self.doc_map = doc_map;
+        self
+    }
+
+    /// Sets the names of ANNIS documents without subcorpora that are shared by several documents
+    /// of a corpus, together with the full names of these documents (see
+    /// [`crate::inbound::annis::Corpus::duplicate_document_names`]), for which TTL files named
+    /// after the shared name are ambiguous
+    pub(crate) fn with_duplicate_names(
+        mut self,
+        duplicate_names: impl IntoIterator<Item = Vec<String>>,
+    ) -> Self {
+        for doc_names in duplicate_names {
+            for doc_name in &doc_names {
+                self.duplicate_names
+                    .entry(doc_name.clone())
+                    .or_default()
+                    .extend(doc_names.iter().cloned());
+            }
+        }
+
+        self
+    }
+
+    pub(crate) fn document_for_name(
+        &self,
+        doc_name: &str,
+        warnings: &Warnings,
+    ) -> anyhow::Result<Option<Document>> {
+        Document::from_file(
+            &self
+                .document_path(doc_name)?
+                .ok_or_else(|| anyhow!("ttl file for document {doc_name} not found"))?,
+            self.route_predicate.as_deref(),
+            &self.languages,
+            self.pre_process.as_deref(),
+            self.max_skipped_statements,
+            warnings,
+        )
+    }
+
+    /// Like [`Self::document_for_name`], but returns `None` rather than failing if there is no TTL
+    /// file for the document
+    pub(crate) fn try_document_for_name(
+        &self,
+        doc_name: &str,
+        warnings: &Warnings,
+    ) -> anyhow::Result<Option<Document>> {
+        match self.document_path(doc_name)? {
+            Some(doc_path) => Document::from_file(
+                &doc_path,
+                self.route_predicate.as_deref(),
+                &self.languages,
+                self.pre_process.as_deref(),
+                self.max_skipped_statements,
+                warnings,
+            ),
+            None => Ok(None),
+        }
+    }
+
+    /// Names of the documents of the TTL files inferred from their file names, i.e. the part of
+    /// the file stem before the first `_`, together with the paths of the files, sorted by path
+    ///
+    /// Files whose stems contain no `_` are left out since they are never found for any document.
+    pub(crate) fn document_names(&self) -> Vec<(&str, &Path)> {
+        self.file_paths
+            .iter()
+            .filter_map(|file_path| {
+                let stem = file_path.file_stem()?.to_str()?;
+                let (doc_name, _) = stem.split_once('_')?;
+                Some((doc_name, file_path.as_path()))
+            })
+            .collect()
+    }
+
+    /// Path of the TTL file of the document with the given (full) name
+    ///
+    /// The file is found by the name the document is mapped to (see [`Self::with_doc_map`]) or
+    /// else by its full name, where the `/` separating subcorpora is written as `%2F` (e.g.
+    /// `sub%2FM001-N1_1.ttl` for `sub/M001-N1`). For documents in subcorpora, the file is also
+    /// found by the name of the document without subcorpora, unless this name is shared by several
+    /// documents (see [`Self::with_duplicate_names`]), which is an error.
+    fn document_path(&self, doc_name: &str) -> anyhow::Result<Option<PathBuf>> {
+        if let Some(ttl_doc_name) = self.doc_map.get(doc_name) {
+            return self.unique_path(doc_name, ttl_doc_name);
+        }
+
+        let full_path = self.unique_path(doc_name, &doc_name.replace('/', "%2F"))?;
+
+        let Some((_, name)) = doc_name.rsplit_once('/') else {
+            return Ok(full_path);
+        };
+
+        if full_path.is_some() {
+            return Ok(full_path);
+        }
+
+        let Some(path) = self.unique_path(doc_name, name)? else {
+            return Ok(None);
+        };
+
+        if let Some(duplicates) = self.duplicate_names.get(doc_name) {
+            bail!(
+                "ttl file {} for document {doc_name} is ambiguous since the documents {} share the \
+                 name {name}: name the file after the full name of the document (e.g. {}_1.ttl) \
+                 or map it with --doc-map",
+                path.display(),
+                duplicates.iter().unique().join(", "),
+                doc_name.replace('/', "%2F")
+            );
+        }
+
+        Ok(Some(path))
+    }
+
+    /// Path of the only TTL file of the TTL document `ttl_doc_name`, if any
+    fn unique_path(&self, doc_name: &str, ttl_doc_name: &str) -> anyhow::Result<Option<PathBuf>> {
+        let mut doc_path: Option<&PathBuf> = None;
+
+        for file_path in &self.file_paths {
+            if file_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem.starts_with(&format!("{ttl_doc_name}_")))
             {
                 info!(doc_name, path = %file_path.display(), "found document");
 
@@ -99,37 +318,235 @@ impl Storage {
             }
         }
 
-        Document::from_file(
-            &doc_path.ok_or_else(|| anyhow!("ttl file for document {doc_name} not found"))?,
-        )
+        Ok(doc_path.cloned())
+    }
+}
+
+/// Output of the shell command `command` run with the file at `path` as stdin, see
+/// [`Storage::with_pre_process`]
+#[cfg(not(target_arch = "wasm32"))]
+fn pre_processed(path: &Path, command: &str) -> anyhow::Result<Vec<u8>> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+
+    let output = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .stdin(File::open(path)?)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| anyhow!("running pre-processing command `{command}` failed: {err}"))?;
+
+    ensure!(
+        output.status.success(),
+        "pre-processing command `{command}` failed for {} with {}",
+        path.display(),
+        output.status
+    );
+
+    info!(path = %path.display(), "pre-processed ttl file");
+
+    Ok(output.stdout)
+}
+
+/// Blanks out up to `max_skipped_statements` malformed statements in the TTL `data`, reporting each
+/// as a warning, so that the rest of the data can be parsed
+///
+/// A statement is taken to span the lines from the end of the previous statement (a line ending
+/// with `.`) to the next line ending with `.`, which may also cover the previous statement if its
+/// `.` is missing. Statements are only skipped if this is safe, i.e. if they contain no prefix
+/// declarations (which would break later statements) and the error has a known position. Line
+/// breaks are kept, so that the positions of later errors still refer to the original data.
+fn skip_malformed_statements(
+    data: &mut [u8],
+    max_skipped_statements: usize,
+    path: &Path,
+    warnings: &Warnings,
+) {
+    for _ in 0..max_skipped_statements {
+        let mut parser = TurtleParser::new(Cursor::new(&*data), None);
+        let Err(err) = parser.parse_all::<TurtleError>(&mut |_| Ok(())) else {
+            return;
+        };
+
+        let Some(position) = err.textual_position() else {
+            return;
+        };
+
+        let Some(range) = (position.line_number() as usize)
+            .checked_sub(1)
+            .and_then(|line_index| statement_range(data, line_index))
+        else {
+            return;
+        };
+
+        warnings.warn(
+            "malformed ttl statement skipped",
+            path.display().to_string(),
+            turtle_error_description(&err),
+        );
+
+        for byte in &mut data[range] {
+            if *byte != b'\n' && *byte != b'\r' {
+                *byte = b' ';
+            }
+        }
+    }
+}
+
+/// Byte range of the statement containing the 0-based line `line_index` in `data`, see
+/// [`skip_malformed_statements`], or `None` if it cannot be skipped safely
+fn statement_range(data: &[u8], line_index: usize) -> Option<Range<usize>> {
+    let mut line_ranges = Vec::new();
+    let mut start = 0;
+    for (index, &byte) in data.iter().enumerate() {
+        if byte == b'\n' {
+            line_ranges.push(start..index + 1);
+            start = index + 1;
+        }
+    }
+    line_ranges.push(start..data.len());
+
+    let line = |index: usize| data[line_ranges[index].clone()].trim_ascii();
+    let ends_statement = |index: usize| line(index).ends_with(b".");
+
+    if line_index >= line_ranges.len() {
+        return None;
+    }
+
+    let first = (0..line_index)
+        .rev()
+        .find(|&index| ends_statement(index))
+        .map_or(0, |index| index + 1);
+    let last = (line_index..line_ranges.len())
+        .find(|&index| ends_statement(index))
+        .unwrap_or(line_ranges.len() - 1);
+
+    let is_safe = (first..=last).all(|index| {
+        let line = line(index);
+        !line.starts_with(b"@") && !line.to_ascii_uppercase().starts_with(b"PREFIX")
+    }) && (first..=last).any(|index| !line(index).is_empty());
+
+    is_safe.then(|| line_ranges[first].start..line_ranges[last].end)
+}
+
+/// Description of a Turtle syntax error with its 1-based line and column
+fn turtle_error_description(err: &TurtleError) -> String {
+    let message = err.to_string();
+
+    match err.textual_position() {
+        // the parser reports the same positions at the end of its message
+        Some(position) => format!(
+            "line {}, column {}: {}",
+            position.line_number(),
+            position.byte_number(),
+            message
+                .rsplit_once(" on line ")
+                .map_or(message.as_str(), |(message, _)| message)
+        ),
+        None => message,
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct Document {
+    path: PathBuf,
     node_types: HashMap<NodeName, NodeType>,
     node_annos: HashMap<NodeName, HashMap<AnnoKey, String>>,
+    /// Language tags of the literals of the annotations in `node_annos`, if any
+    node_anno_languages: HashMap<NodeName, HashMap<AnnoKey, String>>,
+    node_routes: HashMap<NodeName, String>,
+    /// Character offsets (`nif:beginIndex` and `nif:endIndex`) of the nodes that have them
+    begin_indices: HashMap<NodeName, usize>,
+    end_indices: HashMap<NodeName, usize>,
 
     next_sentence: HashMap<NodeName, NodeName>,
     next_word: HashMap<NodeName, NodeName>,
     word_to_sentence: HashMap<NodeName, NodeName>,
 
     child_to_parent: Vec<(NodeName, NodeName)>,
+    /// Nodes that are the parent of some other node
+    parent_node_names: HashSet<NodeName>,
 }
 
 impl Document {
-    fn from_file(path: &Path) -> anyhow::Result<Option<Self>> {
-        let file = File::open(path)?;
-        let mut parser = TurtleParser::new(BufReader::new(file), None);
+    /// Reads the document from the TTL file at `path`, piped through `pre_process` if specified
+    /// (see [`Storage::with_pre_process`]), skipping up to `max_skipped_statements` malformed
+    /// statements (see [`Storage::with_max_skipped_statements`])
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_file(
+        path: &Path,
+        route_predicate: Option<&str>,
+        languages: &[String],
+        pre_process: Option<&str>,
+        max_skipped_statements: usize,
+        warnings: &Warnings,
+    ) -> anyhow::Result<Option<Self>> {
+        match (pre_process, max_skipped_statements) {
+            (None, 0) => Self::from_reader(
+                BufReader::new(
+                    File::open(path)
+                        .map_err(|err| anyhow!("cannot open {}: {err}", path.display()))?,
+                ),
+                path,
+                route_predicate,
+                languages,
+                warnings,
+            ),
+            _ => {
+                let mut data = match pre_process {
+                    Some(command) => pre_processed(path, command)?,
+                    None => fs::read(path)
+                        .map_err(|err| anyhow!("cannot read {}: {err}", path.display()))?,
+                };
+
+                skip_malformed_statements(&mut data, max_skipped_statements, path, warnings);
+
+                Self::from_reader(
+                    Cursor::new(data),
+                    path,
+                    route_predicate,
+                    languages,
+                    warnings,
+                )
+            }
+        }
+    }
+
+    /// Parses the document from the given TTL data without accessing the file system, where `path`
+    /// only identifies the data in warnings and for [`Self::file_hash`]
+    ///
+    /// Returns `None` if the data cannot be parsed, which is reported as a warning.
+    pub(crate) fn from_reader(
+        reader: impl BufRead,
+        path: &Path,
+        route_predicate: Option<&str>,
+        languages: &[String],
+        warnings: &Warnings,
+    ) -> anyhow::Result<Option<Self>> {
+        let mut parser = TurtleParser::new(reader, None);
+        // 1-based index of the triple being processed, for locating errors
+        let mut triple_index = 0;
 
         let mut node_types: HashMap<NodeName, NodeType> = HashMap::new();
         let mut node_annos: HashMap<NodeName, HashMap<AnnoKey, String>> = HashMap::new();
+        let mut node_anno_languages: HashMap<NodeName, HashMap<AnnoKey, String>> = HashMap::new();
+        // rank of the language of each annotation value, see `language_rank`
+        let mut node_anno_ranks: HashMap<(NodeName, AnnoKey), usize> = HashMap::new();
+        let mut node_routes: HashMap<NodeName, String> = HashMap::new();
+        let mut begin_indices: HashMap<NodeName, usize> = HashMap::new();
+        let mut end_indices: HashMap<NodeName, usize> = HashMap::new();
         let mut next_sentence: HashMap<NodeName, NodeName> = HashMap::new();
         let mut next_word: HashMap<NodeName, NodeName> = HashMap::new();
         let mut word_to_sentence: HashMap<NodeName, NodeName> = HashMap::new();
         let mut child_to_parent = Vec::new();
 
         let result = parser.parse_all::<ParseError>(&mut |t| {
+            triple_index += 1;
+
             for (object, ty) in [
                 (nif::SENTENCE, NodeType::Sentence),
                 (nif::WORD, NodeType::Word),
@@ -152,6 +569,22 @@ impl Document {
                 }
             }
 
+            for (predicate, map) in [
+                (nif::BEGIN_INDEX, &mut begin_indices),
+                (nif::END_INDEX, &mut end_indices),
+            ] {
+                if t.predicate == predicate {
+                    let (value, _) = t.object.try_as_literal()?;
+
+                    map.insert(
+                        t.subject.try_as_named_node()?.node_name(),
+                        value.parse().map_err(|_| {
+                            anyhow!("{} is not a valid offset: {}", t.predicate, t.object)
+                        })?,
+                    );
+                }
+            }
+
             if t.predicate == powla::HAS_PARENT {
                 child_to_parent.push((
                     t.subject.try_as_named_node()?.node_name(),
@@ -167,47 +600,220 @@ impl Document {
                 (conll::WORD, AnnoKey::Word),
             ] {
                 if t.predicate == predicate {
+                    let node_name = t.subject.try_as_named_node()?.node_name();
+                    let (value, language) = t.object.try_as_literal()?;
+
+                    let Some(rank) = language_rank(language, languages) else {
+                        continue;
+                    };
+
+                    // a later value replaces an earlier one of the same rank
+                    let best_rank = node_anno_ranks
+                        .entry((node_name.clone(), anno_key))
+                        .or_insert(rank);
+
+                    if *best_rank < rank {
+                        continue;
+                    }
+
+                    *best_rank = rank;
+
+                    let anno_languages = node_anno_languages.entry(node_name.clone()).or_default();
+
+                    match language {
+                        Some(language) => {
+                            anno_languages.insert(anno_key, language.into());
+                        }
+                        None => {
+                            anno_languages.remove(&anno_key);
+                        }
+                    }
+
                     node_annos
-                        .entry(t.subject.try_as_named_node()?.node_name())
+                        .entry(node_name)
                         .or_default()
-                        .insert(anno_key, t.object.try_as_simple_literal()?.into());
+                        .insert(anno_key, value.into_owned());
                 }
             }
 
+            if route_predicate.is_some_and(|p| t.predicate.iri == p) {
+                let value = match t.object {
+                    Term::NamedNode(n) => n.iri.into(),
+                    _ => t.object.try_as_literal()?.0,
+                };
+
+                node_routes.insert(t.subject.try_as_named_node()?.node_name(), value.into());
+            }
+
             Ok(())
         });
 
         match result {
             Ok(()) => Ok(Some(Self {
+                path: path.into(),
                 node_types,
                 node_annos,
+                node_anno_languages,
+                node_routes,
+                begin_indices,
+                end_indices,
                 next_sentence,
                 next_word,
                 word_to_sentence,
+                parent_node_names: child_to_parent
+                    .iter()
+                    .map(|(_, parent)| parent.clone())
+                    .collect(),
                 child_to_parent,
             })),
-            Err(ParseError::Anyhow(err)) => Err(err),
+            Err(ParseError::Anyhow(err)) => Err(anyhow!(
+                "{}, triple {triple_index}: {err:#}",
+                path.display()
+            )),
             Err(ParseError::Turtle(err)) => {
-                warn!(path = %path.display(), %err, "ttl file could not be parsed");
+                warnings.warn(
+                    "ttl file could not be parsed",
+                    path.display().to_string(),
+                    turtle_error_description(&err),
+                );
                 Ok(None)
             }
         }
     }
 
+    /// Path of the TTL file the document was read from
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Longest prefix common to the IRIs of all nodes of the document that ends with `#` or `/`,
+    /// e.g. `http://example.org/ttl/doc.ttl#`, or `None` if there is no such prefix
+    pub(crate) fn iri_prefix(&self) -> Option<&str> {
+        let mut node_names = self.node_types.keys().map(AsRef::as_ref);
+        let mut prefix: &str = node_names.next()?;
+
+        for node_name in node_names {
+            let common_len = prefix
+                .char_indices()
+                .zip(node_name.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(prefix.len().min(node_name.len()), |((i, _), _)| i);
+
+            prefix = &prefix[..common_len];
+        }
+
+        let end = prefix.rfind(['#', '/'])? + 1;
+        Some(&prefix[..end])
+    }
+
+    /// Hex-encoded SHA-256 hash of the TTL file the document was read from
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn file_hash(&self) -> anyhow::Result<String> {
+        let mut hasher = Sha256::new();
+        io::copy(
+            &mut File::open(&self.path)
+                .map_err(|err| anyhow!("cannot open {}: {err}", self.path.display()))?,
+            &mut hasher,
+        )?;
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Reports broken `nextSentence` and `nextWord` chains (multiple starts, nodes not reachable from
+    /// the start) as well as words assigned to no sentence, since such words would be missing from
+    /// [`Self::word_nodes_in_order`]
+    pub(crate) fn check_order(&self, doc_name: &str, warnings: &Warnings) {
+        let sentence_starts = self.sentence_starts().sorted().collect_vec();
+
+        if sentence_starts.len() > 1 {
+            warnings.warn(
+                "multiple starts of nextSentence chain",
+                doc_name,
+                sentence_starts.iter().join(", "),
+            );
+        }
+
+        let reached_sentences: HashSet<&NodeName> = self.sentence_node_names_in_order().collect();
+
+        for sentence in self
+            .node_names_for_type(NodeType::Sentence)
+            .filter(|s| !reached_sentences.contains(s))
+            .sorted()
+        {
+            warnings.warn(
+                "sentence not reachable via nextSentence",
+                doc_name,
+                sentence.to_string(),
+            );
+        }
+
+        for word in self
+            .node_names_for_type(NodeType::Word)
+            .filter(|w| !self.word_to_sentence.contains_key(*w))
+            .sorted()
+        {
+            warnings.warn("word assigned to no sentence", doc_name, word.to_string());
+        }
+
+        for (sentence, mut word_starts) in self.word_starts_by_sentence().into_iter().sorted() {
+            if word_starts.len() > 1 {
+                word_starts.sort();
+                warnings.warn(
+                    "multiple starts of nextWord chain",
+                    doc_name,
+                    format!("{sentence}: {}", word_starts.iter().join(", ")),
+                );
+            }
+        }
+
+        let reached_words: HashSet<&NodeName> = self
+            .word_nodes_in_order()
+            .map(|node| node.node_name())
+            .collect();
+
+        for (word, next_word) in self.word_nodes_in_order().tuple_windows() {
+            if let (Some(offsets), Some(next_offsets)) = (word.offsets(), next_word.offsets()) {
+                if next_offsets.start < offsets.end {
+                    warnings.warn(
+                        "character offsets not increasing along nextWord",
+                        doc_name,
+                        format!(
+                            "{} ({}..{}) -> {} ({}..{})",
+                            word.node_name(),
+                            offsets.start,
+                            offsets.end,
+                            next_word.node_name(),
+                            next_offsets.start,
+                            next_offsets.end
+                        ),
+                    );
+                }
+            }
+        }
+
+        for word in self
+            .node_names_for_type(NodeType::Word)
+            .filter(|w| self.word_to_sentence.contains_key(*w) && !reached_words.contains(w))
+            .sorted()
+        {
+            warnings.warn(
+                "word not reachable via nextWord",
+                doc_name,
+                word.to_string(),
+            );
+        }
+    }
+
     pub(crate) fn word_nodes_in_order(&self) -> Nodes<'_> {
-        let sentence_node_names_in_order = successors(
-            self.node_names_for_type(NodeType::Sentence)
-                .find(|&s| self.next_sentence.values().all(|v| v != s)),
-            |&s| self.next_sentence.get(s),
-        );
+        let word_starts_by_sentence = self.word_starts_by_sentence();
 
-        let word_node_names_in_order = sentence_node_names_in_order
+        let word_node_names_in_order = self
+            .sentence_node_names_in_order()
             .flat_map(|s| {
                 successors(
-                    self.node_names_for_type(NodeType::Word).find(|&w| {
-                        self.word_to_sentence.get(w) == Some(s)
-                            && self.next_word.values().all(|v| v != w)
-                    }),
+                    word_starts_by_sentence
+                        .get(s)
+                        .and_then(|word_starts| word_starts.first().copied()),
                     |&w| self.next_word.get(w),
                 )
             })
@@ -219,12 +825,157 @@ impl Document {
         }
     }
 
+    pub(crate) fn sentence_nodes_in_order(&self) -> Nodes<'_> {
+        Nodes {
+            document: self,
+            names_iter: self
+                .sentence_node_names_in_order()
+                .collect_vec()
+                .into_iter(),
+        }
+    }
+
+    /// Determines the 1-based index of the sentence each node belongs to, where a nonterminal node
+    /// belongs to the sentence of its children and an empty element to the sentence of its parent
+    pub(crate) fn sentence_indices(&self) -> HashMap<NodeName, usize> {
+        let mut indices: HashMap<&NodeName, usize> = self
+            .sentence_node_names_in_order()
+            .enumerate()
+            .map(|(i, s)| (s, i + 1))
+            .collect();
+
+        for (word, sentence) in &self.word_to_sentence {
+            if let Some(&index) = indices.get(sentence) {
+                indices.insert(word, index);
+            }
+        }
+
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (child, parent) in &self.child_to_parent {
+                if let Some(&index) = indices.get(child) {
+                    if !indices.contains_key(parent) {
+                        indices.insert(parent, index);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        // empty elements have no children, so they belong to the sentence of their parent
+        changed = true;
+
+        while changed {
+            changed = false;
+
+            for (child, parent) in &self.child_to_parent {
+                if let Some(&index) = indices.get(parent) {
+                    if !indices.contains_key(child) {
+                        indices.insert(child, index);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        indices
+            .into_iter()
+            .map(|(node_name, index)| (node_name.clone(), index))
+            .collect()
+    }
+
     pub(crate) fn parent_edges(&self) -> impl Iterator<Item = (Node<'_>, Node<'_>)> {
         self.child_to_parent
             .iter()
             .map(|(child, parent)| (self.node_for_name(child), self.node_for_name(parent)))
     }
 
+    /// Edges from children to parents, where duplicate edges are dropped and edges to additional
+    /// parents of the same child are handled according to `policy`, both reported as warnings
+    pub(crate) fn resolved_parent_edges(
+        &self,
+        policy: MultiParentPolicy,
+        doc_name: &str,
+        warnings: &Warnings,
+    ) -> anyhow::Result<ParentEdges<'_>> {
+        let mut first_parents: HashMap<&NodeName, &NodeName> = HashMap::new();
+        let mut seen_edges: HashSet<(&NodeName, &NodeName)> = HashSet::new();
+        let mut parent_edges = ParentEdges::default();
+
+        for (child, parent) in &self.child_to_parent {
+            if !seen_edges.insert((child, parent)) {
+                warnings.warn(
+                    "duplicate hasParent edge",
+                    doc_name,
+                    format!("{child} -> {parent}"),
+                );
+                continue;
+            }
+
+            let edge = (self.node_for_name(child), self.node_for_name(parent));
+
+            let Some(first_parent) = first_parents.get(child) else {
+                first_parents.insert(child, parent);
+                parent_edges.primary.push(edge);
+                continue;
+            };
+
+            match policy {
+                MultiParentPolicy::Error => {
+                    bail!("node {child} has multiple parents: {first_parent}, {parent}");
+                }
+                MultiParentPolicy::First => {}
+                MultiParentPolicy::Secondary => parent_edges.secondary.push(edge),
+            }
+
+            warnings.warn(
+                "node with multiple parents",
+                doc_name,
+                format!("{child} -> {first_parent}, {parent}"),
+            );
+        }
+
+        Ok(parent_edges)
+    }
+
+    /// IRIs of the sentence nodes in the order of the `nextSentence` chain, i.e. the IRI of the
+    /// sentence with the 1-based index `i` (see [`Self::sentence_indices`]) at position `i - 1`
+    pub(crate) fn sentence_node_names_in_order(&self) -> impl Iterator<Item = &NodeName> {
+        successors(self.sentence_starts().next(), |&s| {
+            self.next_sentence.get(s)
+        })
+    }
+
+    /// Sentences without a predecessor in the `nextSentence` chain
+    fn sentence_starts(&self) -> impl Iterator<Item = &NodeName> {
+        let successors: HashSet<&NodeName> = self.next_sentence.values().collect();
+
+        self.node_names_for_type(NodeType::Sentence)
+            .filter(move |s| !successors.contains(s))
+    }
+
+    /// Words without a predecessor in the `nextWord` chain, by sentence
+    fn word_starts_by_sentence(&self) -> HashMap<&NodeName, Vec<&NodeName>> {
+        let successors: HashSet<&NodeName> = self.next_word.values().collect();
+        let mut word_starts_by_sentence: HashMap<&NodeName, Vec<&NodeName>> = HashMap::new();
+
+        for word in self.node_names_for_type(NodeType::Word) {
+            if let Some(sentence) = self.word_to_sentence.get(word) {
+                if !successors.contains(word) {
+                    word_starts_by_sentence
+                        .entry(sentence)
+                        .or_default()
+                        .push(word);
+                }
+            }
+        }
+
+        word_starts_by_sentence
+    }
+
     fn node_names_for_type(&self, node_type: NodeType) -> impl Iterator<Item = &NodeName> {
         self.node_types
             .iter()
@@ -232,7 +983,7 @@ impl Document {
             .map(|(node_name, _)| node_name)
     }
 
-    fn node_for_name<'a>(&'a self, name: &'a NodeName) -> Node<'a> {
+    pub(crate) fn node_for_name<'a>(&'a self, name: &'a NodeName) -> Node<'a> {
         Node {
             document: self,
             name,
@@ -240,6 +991,27 @@ impl Document {
     }
 }
 
+/// Policy for TTL nodes with more than one parent, which would not form a tree
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum MultiParentPolicy {
+    /// Fail
+    Error,
+    /// Keep only the edge to the first parent in the TTL file
+    First,
+    /// Keep the edge to the first parent in the tree and add the edges to the other parents as
+    /// secondary edges
+    Secondary,
+}
+
+/// Edges from children to parents, see [`Document::resolved_parent_edges`]
+#[derive(Default)]
+pub(crate) struct ParentEdges<'a> {
+    /// Edges to the first parent of each child, which form the trees
+    pub(crate) primary: Vec<(Node<'a>, Node<'a>)>,
+    /// Edges to additional parents, see [`MultiParentPolicy::Secondary`]
+    pub(crate) secondary: Vec<(Node<'a>, Node<'a>)>,
+}
+
 #[derive(Debug)]
 pub(crate) struct Nodes<'a> {
     document: &'a Document,
@@ -263,8 +1035,8 @@ pub(crate) struct Node<'a> {
     name: &'a NodeName,
 }
 
-impl Node<'_> {
-    pub(crate) fn node_name(&self) -> &NodeName {
+impl<'a> Node<'a> {
+    pub(crate) fn node_name(&self) -> &'a NodeName {
         self.name
     }
 
@@ -272,19 +1044,49 @@ impl Node<'_> {
         self.node_type() == Some(NodeType::Word)
     }
 
-    pub(crate) fn anno(&self, anno_key: AnnoKey) -> Option<&str> {
+    /// Whether the node is an empty element (e.g. a trace), i.e. a node other than a word that has
+    /// no children
+    pub(crate) fn is_empty_element(&self) -> bool {
+        !self.is_word()
+            && self.node_type() != Some(NodeType::Sentence)
+            && !self.document.parent_node_names.contains(self.name)
+    }
+
+    /// Character offsets of the node given by `nif:beginIndex` and `nif:endIndex`, if both are
+    /// present
+    pub(crate) fn offsets(&self) -> Option<Range<usize>> {
+        Some(
+            *self.document.begin_indices.get(self.name)?
+                ..*self.document.end_indices.get(self.name)?,
+        )
+    }
+
+    /// Language tag of the literal of the given annotation, if any
+    pub(crate) fn anno_language(&self, anno_key: AnnoKey) -> Option<&'a str> {
+        self.document
+            .node_anno_languages
+            .get(self.name)
+            .and_then(|languages| languages.get(&anno_key).map(String::as_str))
+    }
+
+    pub(crate) fn anno(&self, anno_key: AnnoKey) -> Option<&'a str> {
         self.document
             .node_annos
             .get(self.name)
             .and_then(|annos| annos.get(&anno_key).map(|s| s.deref()))
     }
 
+    /// Value of the route predicate for this node, if any
+    pub(crate) fn route_value(&self) -> Option<&'a str> {
+        self.document.node_routes.get(self.name).map(|s| s.as_str())
+    }
+
     fn node_type(&self) -> Option<NodeType> {
         self.document.node_types.get(self.name).copied()
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub(crate) struct NodeName(String);
 
 impl AsRef<str> for NodeName {
@@ -352,19 +1154,63 @@ impl<'a> TryAsNamedNode<'a> for Term<'a> {
     }
 }
 
-trait TryAsSimpleLiteral<'a> {
-    fn try_as_simple_literal(&self) -> anyhow::Result<&'a str>;
+trait TryAsLiteral<'a> {
+    /// Value and language tag (if any) of a literal, where the values of typed literals are
+    /// normalized according to their datatype
+    fn try_as_literal(&self) -> anyhow::Result<(Cow<'a, str>, Option<&'a str>)>;
 }
 
-impl<'a> TryAsSimpleLiteral<'a> for Term<'a> {
-    fn try_as_simple_literal(&self) -> anyhow::Result<&'a str> {
+impl<'a> TryAsLiteral<'a> for Term<'a> {
+    fn try_as_literal(&self) -> anyhow::Result<(Cow<'a, str>, Option<&'a str>)> {
         match self {
-            Term::Literal(Literal::Simple { value }) => Ok(value),
-            _ => Err(anyhow!("term {self} is not a simple literal")),
+            Term::Literal(Literal::Simple { value }) => Ok(((*value).into(), None)),
+            Term::Literal(Literal::LanguageTaggedString { value, language }) => {
+                Ok(((*value).into(), Some(language)))
+            }
+            Term::Literal(Literal::Typed { value, datatype }) => {
+                Ok((typed_literal_value(value, *datatype)?, None))
+            }
+            _ => Err(anyhow!("term {self} is not a literal")),
         }
     }
 }
 
+/// Canonical form of the value of a typed literal, e.g. `5` for `"+05"^^xsd:integer` and `true`
+/// for `"1"^^xsd:boolean`, where values of other datatypes (e.g. `xsd:string`) are kept as they are
+fn typed_literal_value<'a>(
+    value: &'a str,
+    datatype: NamedNode<'_>,
+) -> anyhow::Result<Cow<'a, str>> {
+    if [xsd::INT, xsd::INTEGER, xsd::LONG, xsd::NON_NEGATIVE_INTEGER].contains(&datatype) {
+        let number: i128 = value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("invalid {datatype} literal: {value}"))?;
+
+        Ok(number.to_string().into())
+    } else if datatype == xsd::BOOLEAN {
+        match value.trim() {
+            "true" | "1" => Ok("true".into()),
+            "false" | "0" => Ok("false".into()),
+            _ => bail!("invalid {datatype} literal: {value}"),
+        }
+    } else {
+        Ok(value.into())
+    }
+}
+
+/// Rank of a literal with the given language tag according to the preferred `languages`, where
+/// lower is better, or `None` if the literal is to be ignored
+fn language_rank(language: Option<&str>, languages: &[String]) -> Option<usize> {
+    match language {
+        _ if languages.is_empty() => Some(0),
+        Some(language) => languages
+            .iter()
+            .position(|preferred| preferred.eq_ignore_ascii_case(language)),
+        None => Some(languages.len()),
+    }
+}
+
 enum ParseError {
     Anyhow(anyhow::Error),
     Turtle(TurtleError),
@@ -381,3 +1227,109 @@ impl From<TurtleError> for ParseError {
         ParseError::Turtle(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = "@prefix ex: <http://example.org/> .\n\
+                        ex:a ex:p \"x\" .\n\
+                        ex:b ex:p\n  ex:c .\n\
+                        ex:e ex:p \"y\" .\n";
+
+    fn parses(data: &[u8]) -> bool {
+        TurtleParser::new(Cursor::new(data), None)
+            .parse_all::<TurtleError>(&mut |_| Ok(()))
+            .is_ok()
+    }
+
+    #[test]
+    fn statement_range_covers_lines_of_statement() {
+        let data = DATA.as_bytes();
+        let range = statement_range(data, 3).unwrap();
+
+        assert_eq!(&DATA[range.clone()], "ex:b ex:p\n  ex:c .\n");
+        assert_eq!(statement_range(data, 2), Some(range));
+        assert_eq!(
+            statement_range(data, 4).map(|range| &DATA[range]),
+            Some("ex:e ex:p \"y\" .\n")
+        );
+    }
+
+    #[test]
+    fn statement_range_covers_previous_statement_without_dot() {
+        let data = "@prefix ex: <http://example.org/> .\nex:a ex:p \"x\"\nex:b ex:p \"y\" .\n";
+
+        assert_eq!(
+            statement_range(data.as_bytes(), 2).map(|range| &data[range]),
+            Some("ex:a ex:p \"x\"\nex:b ex:p \"y\" .\n")
+        );
+    }
+
+    #[test]
+    fn statement_range_refuses_prefix_declarations() {
+        let data = "ex:a ex:p \"x\"\n@prefix ex: <http://example.org/> .\nex:b ex:p \"y\" .\n";
+        assert_eq!(statement_range(data.as_bytes(), 0), None);
+        assert_eq!(statement_range(data.as_bytes(), 1), None);
+
+        let data = "ex:a ex:p \"x\"\nPREFIX ex: <http://example.org/>\nex:b ex:p \"y\" .\n";
+        assert_eq!(statement_range(data.as_bytes(), 0), None);
+
+        let data = "ex:a ex:p \"x\"\n  prefix ex: <http://example.org/>\nex:b ex:p \"y\" .\n";
+        assert_eq!(statement_range(data.as_bytes(), 0), None);
+    }
+
+    #[test]
+    fn statement_range_refuses_position_past_end() {
+        let data = DATA.as_bytes();
+        let line_count = DATA.lines().count();
+
+        // the empty line after the final line break
+        assert_eq!(statement_range(data, line_count), None);
+        assert_eq!(statement_range(data, line_count + 1), None);
+        assert_eq!(statement_range(b"", 0), None);
+    }
+
+    #[test]
+    fn skip_malformed_statements_skips_bad_statement() {
+        let mut data = DATA.replace("ex:c .", "ex:c ex:d .").into_bytes();
+        let warnings = Warnings::default();
+        assert!(!parses(&data));
+
+        skip_malformed_statements(&mut data, 1, Path::new("doc.ttl"), &warnings);
+
+        assert!(parses(&data));
+        assert_eq!(
+            String::from_utf8(data).unwrap().lines().collect_vec(),
+            [
+                "@prefix ex: <http://example.org/> .",
+                "ex:a ex:p \"x\" .",
+                " ".repeat(9).as_str(),
+                " ".repeat(13).as_str(),
+                "ex:e ex:p \"y\" .",
+            ]
+        );
+
+        let entries = warnings.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "malformed ttl statement skipped");
+        assert_eq!(entries[0].1, "doc.ttl");
+        assert_eq!(entries[0].2.len(), 1);
+        assert!(entries[0].2[0].starts_with("line 4, column "));
+    }
+
+    #[test]
+    fn skip_malformed_statements_stops_at_limit() {
+        let mut data = DATA
+            .replace("\"x\"", "\"x\" ex:q")
+            .replace("ex:c .", "ex:c ex:d .")
+            .into_bytes();
+        let warnings = Warnings::default();
+
+        skip_malformed_statements(&mut data, 1, Path::new("doc.ttl"), &warnings);
+        assert!(!parses(&data));
+
+        skip_malformed_statements(&mut data, 1, Path::new("doc.ttl"), &warnings);
+        assert!(parses(&data));
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(feature = "annis")]
+mod align;
+#[cfg(feature = "annis")]
+mod annis_util;
+#[cfg(feature = "annis")]
+mod anno_diff;
+#[cfg(feature = "annis")]
+mod breadcrumbs;
+#[cfg(feature = "annis")]
+mod cli;
+#[cfg(feature = "annis")]
+mod coverage;
+#[cfg(feature = "annis")]
+mod doc_map;
+#[cfg(feature = "annis")]
+mod features;
+#[cfg(feature = "annis")]
+mod list;
+#[cfg(feature = "annis")]
+mod node_name;
+#[cfg(feature = "annis")]
+mod relabel;
+#[cfg(feature = "annis")]
+mod rem;
+#[cfg(feature = "annis")]
+mod remote;
+#[cfg(feature = "annis")]
+mod report;
+#[cfg(feature = "annis")]
+mod telemetry;
+#[cfg(feature = "annis")]
+mod tree_builder;
+#[cfg(feature = "annis")]
+mod tree_stats;
+mod ttl_cli;
+mod warnings;
+
+mod inbound {
+    #[cfg(feature = "annis")]
+    pub(crate) mod anchors;
+    #[cfg(feature = "annis")]
+    pub(crate) mod annis;
+    #[cfg(feature = "annis")]
+    pub(crate) mod annis_zip;
+    #[cfg(feature = "annis")]
+    pub(crate) mod metadata;
+    #[cfg(feature = "annis")]
+    pub(crate) mod tagset;
+    // most of the TTL document is only used when merging it into ANNIS corpora
+    #[cfg_attr(not(feature = "annis"), allow(dead_code))]
+    pub(crate) mod ttl;
+}
+
+#[cfg(feature = "annis")]
+mod outbound {
+    pub(crate) mod annis;
+    pub(crate) mod artifacts;
+    pub(crate) mod link_table;
+    pub(crate) mod lock;
+    #[cfg(feature = "export-extras")]
+    pub(crate) mod paula;
+    pub(crate) mod post_process;
+    #[cfg(feature = "export-extras")]
+    pub(crate) mod saltxml;
+    pub(crate) mod sidecar;
+    #[cfg(feature = "export-extras")]
+    pub(crate) mod treebank;
+}
+
+/// Log target of the messages still logged with `--quiet`
+#[cfg(feature = "annis")]
+pub(crate) const SUMMARY_TARGET: &str = "summary";
+
+#[cfg(feature = "annis")]
+pub use cli::{convert_document, main};
+pub use ttl_cli::main as ttl_main;
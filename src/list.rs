@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::inbound;
+
+/// Corpus of the input corpora together with the names of its documents
+#[derive(Serialize)]
+struct ListedCorpus {
+    corpus: String,
+    documents: Vec<String>,
+    /// Names of documents without subcorpora shared by several documents, together with the full
+    /// names of these documents
+    duplicates: BTreeMap<String, Vec<String>>,
+}
+
+/// TTL file together with the name of the document inferred from its file name
+#[derive(Serialize)]
+struct ListedTtlFile<'a> {
+    document: &'a str,
+    path: String,
+}
+
+/// Prints the names of all corpora and their documents to stdout, either as plain text with one
+/// document per line below the name of its corpus or as JSON
+pub(crate) fn annis(annis_storage: &inbound::annis::Storage, json: bool) -> anyhow::Result<()> {
+    let corpora = annis_storage
+        .corpora()
+        .map(|corpus| {
+            Ok(ListedCorpus {
+                corpus: corpus.name().into(),
+                documents: corpus.document_names()?,
+                duplicates: corpus.duplicate_document_names()?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut stdout = io::stdout().lock();
+
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &corpora)?;
+        writeln!(stdout)?;
+    } else {
+        for corpus in corpora {
+            writeln!(stdout, "{}", corpus.corpus)?;
+
+            for doc_name in corpus.documents {
+                let name = doc_name.rsplit('/').next().unwrap_or(&doc_name);
+
+                if corpus.duplicates.contains_key(name) {
+                    writeln!(stdout, "  {doc_name}\t(duplicate name {name})")?;
+                } else {
+                    writeln!(stdout, "  {doc_name}")?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the names of the documents inferred from the names of the TTL files to stdout, either as
+/// plain text with the document name and the path separated by a tab or as JSON
+pub(crate) fn ttl(ttl_storage: &inbound::ttl::Storage, json: bool) -> anyhow::Result<()> {
+    let files: Vec<_> = ttl_storage
+        .document_names()
+        .into_iter()
+        .map(|(document, path)| ListedTtlFile {
+            document,
+            path: path.display().to_string(),
+        })
+        .collect();
+
+    let mut stdout = io::stdout().lock();
+
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &files)?;
+        writeln!(stdout)?;
+    } else {
+        for file in files {
+            writeln!(stdout, "{}\t{}", file.document, file.path)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+
+use anyhow::{anyhow, ensure};
+
+/// ASCII characters percent-encoded within the segments of node names in addition to control
+/// characters, like `NODE_NAME_ENCODE_SET` of graphannis (non-ASCII characters are always encoded)
+const ENCODED_CHARS: &[u8] = b":/ %\\<>\"|?*";
+
+/// Encodes a corpus or document name for use as a segment of a node name
+pub(crate) fn encode(segment: &str) -> Cow<'_, str> {
+    let needs_encoding =
+        |b: u8| !b.is_ascii() || b.is_ascii_control() || ENCODED_CHARS.contains(&b);
+
+    if !segment.bytes().any(needs_encoding) {
+        return Cow::Borrowed(segment);
+    }
+
+    let mut encoded = String::with_capacity(segment.len() * 3);
+
+    for b in segment.bytes() {
+        if needs_encoding(b) {
+            encoded.push_str(&format!("%{b:02X}"));
+        } else {
+            encoded.push(b as char);
+        }
+    }
+
+    Cow::Owned(encoded)
+}
+
+/// Decodes a segment of a node name
+pub(crate) fn decode(segment: &str) -> anyhow::Result<Cow<'_, str>> {
+    urlencoding::decode(segment)
+        .map_err(|err| anyhow!("segment `{segment}` of node name is not valid UTF-8: {err}"))
+}
+
+/// Decoded name of the document (including the names of the subcorpora containing it, separated by
+/// `/`) from the name of its document node, which has the form `CORPUS/[SUBCORPUS/...]DOC`
+///
+/// Any `/` and `%` within the names are kept encoded as `%2F` and `%25`, so that the names can be
+/// told apart from the separators.
+pub(crate) fn doc_name(doc_node_name: &str) -> anyhow::Result<String> {
+    let (_, doc_path) = doc_node_name
+        .split_once('/')
+        .ok_or_else(|| anyhow!("could not get document name from node name {doc_node_name}"))?;
+
+    Ok(doc_path
+        .split('/')
+        .map(|segment| Ok(decode(segment)?.replace('%', "%25").replace('/', "%2F")))
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .join("/"))
+}
+
+/// Replaces the corpus name `old_corpus_name` in the given node name by `new_corpus_name`
+pub(crate) fn rename_corpus(
+    node_name: &str,
+    old_corpus_name: &str,
+    new_corpus_name: &str,
+) -> anyhow::Result<String> {
+    if node_name == old_corpus_name {
+        // node name of corpus node is *not* encoded
+        return Ok(new_corpus_name.into());
+    }
+
+    // corpus name within node name of non-corpus node *is* encoded
+    let (corpus_name_encoded, rest) = node_name
+        .split_once('/')
+        .ok_or_else(|| anyhow!("unexpected node name: '{node_name}'"))?;
+
+    let old_corpus_name_encoded = encode(old_corpus_name);
+
+    ensure!(
+        corpus_name_encoded == old_corpus_name_encoded,
+        "unexpected corpus name in node name: '{}' != '{}'",
+        corpus_name_encoded,
+        old_corpus_name_encoded,
+    );
+
+    Ok(format!("{}/{rest}", encode(new_corpus_name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: &[&str] = &[
+        "plain",
+        "with space",
+        "a/b",
+        "50%",
+        "a#b",
+        "c:\\x<y>\"z\"|?*",
+        "Bärmann ſ ꝛ",
+        "tab\there",
+    ];
+
+    #[test]
+    fn encode_borrows_names_without_reserved_chars() {
+        assert!(matches!(
+            encode("REM-M-1_2.3"),
+            Cow::Borrowed("REM-M-1_2.3")
+        ));
+    }
+
+    #[test]
+    fn encode_encodes_reserved_chars() {
+        assert_eq!(encode("a/b"), "a%2Fb");
+        assert_eq!(encode("50%"), "50%25");
+        assert_eq!(encode("with space"), "with%20space");
+        assert_eq!(encode("c:\\x"), "c%3A%5Cx");
+        assert_eq!(encode("tab\there"), "tab%09here");
+    }
+
+    #[test]
+    fn encode_encodes_non_ascii_chars() {
+        assert_eq!(encode("Bärmann"), "B%C3%A4rmann");
+    }
+
+    #[test]
+    fn encode_keeps_hash_like_graphannis() {
+        assert_eq!(encode("a#b"), "a#b");
+    }
+
+    #[test]
+    fn decode_inverts_encode() {
+        for name in NAMES {
+            assert_eq!(decode(&encode(name)).unwrap(), *name);
+        }
+    }
+
+    #[test]
+    fn decode_fails_for_invalid_utf8() {
+        assert!(decode("%FF").is_err());
+    }
+
+    #[test]
+    fn doc_name_decodes_all_segments() {
+        assert_eq!(doc_name("corpus/doc").unwrap(), "doc");
+        assert_eq!(
+            doc_name("corpus/sub%20corpus/B%C3%A4rmann").unwrap(),
+            "sub corpus/Bärmann"
+        );
+    }
+
+    #[test]
+    fn doc_name_keeps_slashes_and_percent_signs_within_segments_encoded() {
+        assert_eq!(doc_name("corpus/sub/a%2Fb").unwrap(), "sub/a%2Fb");
+        assert_eq!(doc_name("corpus/sub%2Fa/b").unwrap(), "sub%2Fa/b");
+        assert_eq!(doc_name("corpus/50%25/a%252Fb").unwrap(), "50%25/a%252Fb");
+        assert_ne!(
+            doc_name("corpus/sub/a%2Fb").unwrap(),
+            doc_name("corpus/sub/a/b").unwrap()
+        );
+    }
+
+    #[test]
+    fn doc_name_fails_for_corpus_node() {
+        assert!(doc_name("corpus").is_err());
+    }
+
+    #[test]
+    fn rename_corpus_renames_corpus_node_without_encoding() {
+        assert_eq!(
+            rename_corpus("old name", "old name", "new/name").unwrap(),
+            "new/name"
+        );
+    }
+
+    #[test]
+    fn rename_corpus_renames_document_node() {
+        assert_eq!(
+            rename_corpus("old%20name/sub/doc", "old name", "new/näme").unwrap(),
+            "new%2Fn%C3%A4me/sub/doc"
+        );
+    }
+
+    #[test]
+    fn rename_corpus_renames_node() {
+        assert_eq!(
+            rename_corpus("old/doc#t1", "old", "new").unwrap(),
+            "new/doc#t1"
+        );
+    }
+
+    #[test]
+    fn rename_corpus_fails_for_other_corpus() {
+        let err = rename_corpus("other/doc#t1", "old", "new").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "unexpected corpus name in node name: 'other' != 'old'"
+        );
+    }
+
+    #[test]
+    fn rename_corpus_fails_for_unexpected_node_name() {
+        assert!(rename_corpus("other", "old", "new").is_err());
+    }
+}
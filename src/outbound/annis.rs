@@ -1,52 +1,123 @@
 use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::fs::{self, File};
-use std::io::{self, Write};
-use std::path::Path;
-use std::rc::Rc;
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, Write};
+use std::ops::Sub;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::LazyLock;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
 use anyhow::{anyhow, bail, ensure};
+use clap::ValueEnum;
 use graphannis::corpusstorage::{ExportFormat, QueryLanguage, ResultOrder, SearchQuery};
+use graphannis::model::AnnotationComponent;
 pub(crate) use graphannis::model::AnnotationComponentType;
 use graphannis::util::node_names_from_match;
 use graphannis_core::graph::update::{GraphUpdate, UpdateEvent};
 use graphannis_core::graph::NODE_NAME;
 pub(crate) use graphannis_core::graph::{ANNIS_NS, DEFAULT_NS};
+use graphannis_core::types::AnnoKey;
 use itertools::Itertools;
+use quick_xml::events::Event;
+use quick_xml::Reader;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use tempfile::NamedTempFile;
-use tracing::info;
+use tracing::{field, info, info_span};
 use zip::write::SimpleFileOptions;
-use zip::ZipWriter;
+use zip::{ZipArchive, ZipWriter};
 
-use crate::{annis_util, inbound};
+use crate::outbound::post_process::PostProcess;
+use crate::{annis_util, inbound, node_name};
 
+/// Default size in MiB from which GraphML files are streamed rather than read into memory
+pub(crate) const DEFAULT_STREAM_THRESHOLD_MB: u64 = 512;
+
+pub(crate) const CORPUS: &str = "corpus";
+pub(crate) const DATASOURCE: &str = "datasource";
+pub(crate) const DOC: &str = "doc";
+pub(crate) const FILE: &str = "file";
 pub(crate) const LAYER: &str = "layer";
 pub(crate) const NODE: &str = "node";
+pub(crate) const TOK: &str = "tok";
 
 pub(crate) struct CorpusWriter<'a> {
     corpus_count: usize,
-    path: &'a Path,
+    /// `None` if writing to stdout
+    path: Option<&'a Path>,
     zip_writer: ZipWriter<NamedTempFile>,
+    /// Maximum number of corpora compressed concurrently, where 1 means compressing them directly
+    /// into the output
+    zip_threads: usize,
+    /// Whether to check that the GraphML of each corpus is well-formed before writing it
+    verify_graphml: bool,
+    /// Size in bytes from which GraphML files are streamed from disk rather than read into memory
+    stream_threshold: u64,
+    /// Corpora being compressed in the background into temporary ZIP files, in the order in which
+    /// they are appended to the output
+    pending: VecDeque<JoinHandle<anyhow::Result<NamedTempFile>>>,
+    /// Names of the entries of the output ZIP file written so far, which must be unique
+    entry_names: HashSet<PathBuf>,
+    /// Pipeline run on the export of each corpus before it is written, if any
+    post_process: Option<PostProcess>,
 }
 
 impl<'a> CorpusWriter<'a> {
-    pub(crate) fn new(path: &'a Path) -> anyhow::Result<Self> {
+    pub(crate) fn new(
+        path: &'a Path,
+        zip_threads: usize,
+        verify_graphml: bool,
+        stream_threshold_mb: u64,
+    ) -> anyhow::Result<Self> {
         Ok(Self {
             corpus_count: 0,
-            path,
+            path: Some(path),
             zip_writer: ZipWriter::new(NamedTempFile::new_in(
                 path.parent()
                     .ok_or_else(|| anyhow!("path {} has no parent", path.display()))?,
             )?),
+            zip_threads,
+            verify_graphml,
+            stream_threshold: stream_threshold_mb.saturating_mul(1024 * 1024),
+            pending: VecDeque::new(),
+            entry_names: HashSet::new(),
+            post_process: None,
         })
     }
 
+    pub(crate) fn to_stdout(
+        zip_threads: usize,
+        verify_graphml: bool,
+        stream_threshold_mb: u64,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            corpus_count: 0,
+            path: None,
+            zip_writer: ZipWriter::new(NamedTempFile::new()?),
+            zip_threads,
+            verify_graphml,
+            stream_threshold: stream_threshold_mb.saturating_mul(1024 * 1024),
+            pending: VecDeque::new(),
+            entry_names: HashSet::new(),
+            post_process: None,
+        })
+    }
+
+    /// Sets the pipeline run on the export of each corpus before it is written
+    pub(crate) fn with_post_process(mut self, post_process: Option<PostProcess>) -> Self {
+        self.post_process = post_process;
+        self
+    }
+
     pub(crate) fn write_corpus(
         &mut self,
         corpus: &Corpus<'_>,
-        config: &toml::Table,
+        config: Option<&toml::Table>,
     ) -> anyhow::Result<()> {
+        let _span = info_span!("write_corpus", corpus_name = &*corpus.name).entered();
+
         info!(corpus_name = &*corpus.name, "writing corpus");
 
         let temp_dir = tempfile::tempdir()?;
@@ -57,55 +128,148 @@ impl<'a> CorpusWriter<'a> {
             ExportFormat::GraphMLDirectory,
         )?;
 
-        let graphml_string = {
-            let mut graphml_string = fs::read_to_string(
-                temp_dir
-                    .path()
-                    .join(format!("{}.graphml", corpus.original_name)),
-            )?;
+        // the export consists of `CORPUS.graphml` and a directory `CORPUS` containing linked files
+        // as well as the GraphML files of nested subcorpora, possibly in further subdirectories
+        let main_graphml_path = temp_dir
+            .path()
+            .join(format!("{}.graphml", corpus.original_name));
 
-            let range = CDATA_REGEX
-                .find_iter(&graphml_string)
-                .exactly_one()
-                .map_err(|err| anyhow::Error::msg(err.to_string()))?
-                .range();
+        if let Some(post_process) = &self.post_process {
+            post_process.run(temp_dir.path(), &main_graphml_path, corpus.original_name)?;
+        }
 
-            graphml_string.replace_range(
-                range,
-                &format!("<![CDATA[{}]]>", toml::to_string_pretty(&config)?),
-            );
+        let mut exported_paths = Vec::new();
+        collect_files(temp_dir.path(), &mut exported_paths)?;
 
-            graphml_string
-        };
+        // the GraphML of the top-level corpus comes first
+        exported_paths.sort_by(|a, b| {
+            (*a != main_graphml_path)
+                .cmp(&(*b != main_graphml_path))
+                .then_with(|| a.cmp(b))
+        });
 
-        self.zip_writer.start_file(
-            format!("{}.graphml", corpus.name),
-            SimpleFileOptions::default(),
-        )?;
+        ensure!(
+            exported_paths.first() == Some(&main_graphml_path),
+            "corpus export does not contain {}",
+            main_graphml_path.display()
+        );
 
-        self.zip_writer.write_all(graphml_string.as_bytes())?;
+        let mut entries = Vec::with_capacity(exported_paths.len());
 
-        let linked_files_dir = temp_dir.path().join(&*corpus.name);
+        for path in exported_paths {
+            let relative_path = path.strip_prefix(temp_dir.path())?.to_path_buf();
+            let name = corpus.entry_name(&relative_path);
 
-        if linked_files_dir.exists() {
-            for entry in fs::read_dir(linked_files_dir)? {
-                let entry = entry?;
+            let is_graphml = path.extension().is_some_and(|ext| ext == "graphml");
 
-                if entry.file_type()?.is_file() {
-                    self.zip_writer.start_file_from_path(
-                        Path::new(&*corpus.name).join(entry.file_name()),
-                        SimpleFileOptions::default(),
-                    )?;
-                    io::copy(&mut File::open(entry.path())?, &mut self.zip_writer)?;
-                } else {
-                    bail!(
-                        "unexpected file {} in corpus export",
-                        entry.path().display(),
+            if is_graphml && fs::metadata(&path)?.len() >= self.stream_threshold {
+                // the GraphML is too large to be read into memory, so the corpus configuration is
+                // replaced while copying it into a new file and it is verified while reading it
+                let path = match config.filter(|_| path == main_graphml_path) {
+                    Some(config) => {
+                        let replaced_path = path.with_extension("graphml.replaced");
+                        replace_config_streaming(&path, &replaced_path, config)?;
+                        replaced_path
+                    }
+                    None => path,
+                };
+
+                if self.verify_graphml {
+                    check_graphml(BufReader::new(File::open(&path)?)).map_err(|err| {
+                        anyhow!(
+                            "GraphML {} of corpus {} is not well-formed: {err}",
+                            relative_path.display(),
+                            corpus.name
+                        )
+                    })?;
+
+                    info!(
+                        corpus_name = &*corpus.name,
+                        path = %relative_path.display(),
+                        "verified GraphML"
+                    );
+                }
+
+                info!(
+                    corpus_name = &*corpus.name,
+                    path = %relative_path.display(),
+                    "streamed GraphML"
+                );
+
+                entries.push(Entry::File { name, path });
+            } else if is_graphml {
+                let mut graphml_string = fs::read_to_string(&path)?;
+
+                // only the GraphML of the top-level corpus contains the corpus configuration, which
+                // is only replaced if given
+                if let Some(config) = config.filter(|_| path == main_graphml_path) {
+                    let range = CDATA_REGEX
+                        .find_iter(&graphml_string)
+                        .exactly_one()
+                        .map_err(|err| anyhow::Error::msg(err.to_string()))?
+                        .range();
+
+                    graphml_string.replace_range(
+                        range,
+                        &format!("<![CDATA[{}]]>", toml::to_string_pretty(&config)?),
                     );
                 }
+
+                if self.verify_graphml {
+                    check_graphml(graphml_string.as_bytes()).map_err(|err| {
+                        anyhow!(
+                            "GraphML {} of corpus {} is not well-formed: {err}",
+                            relative_path.display(),
+                            corpus.name
+                        )
+                    })?;
+
+                    info!(
+                        corpus_name = &*corpus.name,
+                        path = %relative_path.display(),
+                        "verified GraphML"
+                    );
+                }
+
+                entries.push(Entry::Content {
+                    name,
+                    content: graphml_string,
+                });
+            } else {
+                entries.push(Entry::File { name, path });
             }
         }
 
+        for (node_name, content) in &corpus.linked_files {
+            entries.push(Entry::Content {
+                name: node_name.into(),
+                content: content.clone(),
+            });
+        }
+
+        for entry in &entries {
+            ensure!(
+                self.entry_names.insert(entry.name().to_path_buf()),
+                "corpus {} leads to duplicate entry {} in output ZIP file",
+                corpus.name,
+                entry.name().display()
+            );
+        }
+
+        if self.zip_threads > 1 {
+            if self.pending.len() >= self.zip_threads {
+                self.append_pending()?;
+            }
+
+            self.pending.push_back(thread::spawn(move || {
+                // keep the exported files until they are compressed
+                let _temp_dir = temp_dir;
+                compress_entries(&entries)
+            }));
+        } else {
+            write_entries(&mut self.zip_writer, &entries)?;
+        }
+
         // unload corpus to free memory
         corpus.storage.unload(corpus.original_name)?;
 
@@ -114,47 +278,409 @@ impl<'a> CorpusWriter<'a> {
         Ok(())
     }
 
-    pub(crate) fn finish(self) -> anyhow::Result<()> {
-        self.zip_writer.finish()?.persist(self.path)?;
+    /// Copies the GraphML file of an unmodified corpus at `graphml_path` within the ZIP file at
+    /// `input_path`, together with the directory of its linked files, into the output without
+    /// decompressing it, which avoids exporting the corpus
+    pub(crate) fn copy_corpus(
+        &mut self,
+        corpus: &Corpus<'_>,
+        input_path: &Path,
+        graphml_path: &Path,
+    ) -> anyhow::Result<()> {
+        let _span = info_span!("copy_corpus", corpus_name = &*corpus.name).entered();
+
+        info!(corpus_name = &*corpus.name, "copying unmodified corpus");
 
-        info!(
-            path = %self.path.display(),
-            count = self.corpus_count,
-            "written corpora",
-        );
+        // keep the order of the corpora in the output
+        while !self.pending.is_empty() {
+            self.append_pending()?;
+        }
+
+        let linked_files_dir = graphml_path.with_extension("");
+        // entries are written relative to the directory of the GraphML file, i.e. at the root of the
+        // output ZIP file like exported corpora
+        let base_dir = graphml_path.parent().unwrap_or(Path::new(""));
+        let mut archive = ZipArchive::new(File::open(input_path)?)?;
+
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i)?;
+
+            let Some(file_path) = file.enclosed_name() else {
+                continue;
+            };
+
+            if file_path == graphml_path || file_path.starts_with(&linked_files_dir) {
+                let entry_name = corpus.entry_name(file_path.strip_prefix(base_dir)?);
+
+                ensure!(
+                    self.entry_names.insert(entry_name.clone()),
+                    "corpus {} leads to duplicate entry {} in output ZIP file",
+                    corpus.name,
+                    entry_name.display()
+                );
+
+                let entry_name = entry_name.iter().map(|c| c.to_string_lossy()).join("/");
+                self.zip_writer.raw_copy_file_rename(file, entry_name)?;
+            }
+        }
+
+        // unload corpus to free memory
+        corpus.storage.unload(corpus.original_name)?;
+
+        self.corpus_count += 1;
+
+        Ok(())
+    }
+
+    /// Waits for the oldest corpus being compressed in the background and appends it to the output
+    /// without compressing it again
+    fn append_pending(&mut self) -> anyhow::Result<()> {
+        let Some(handle) = self.pending.pop_front() else {
+            return Ok(());
+        };
+
+        let file = handle
+            .join()
+            .map_err(|_| anyhow!("compression thread panicked"))??;
+
+        let mut archive = ZipArchive::new(file)?;
+
+        for i in 0..archive.len() {
+            self.zip_writer.raw_copy_file(archive.by_index_raw(i)?)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> anyhow::Result<()> {
+        while !self.pending.is_empty() {
+            self.append_pending()?;
+        }
+
+        let mut file = self.zip_writer.finish()?;
+
+        match self.path {
+            Some(path) => {
+                file.persist(path)?;
+
+                info!(
+                    path = %path.display(),
+                    count = self.corpus_count,
+                    "written corpora",
+                );
+            }
+            None => {
+                // the ZIP file needs to be seekable while writing, so it is only streamed to stdout
+                // once finished
+                file.rewind()?;
+
+                let mut stdout = io::stdout().lock();
+                io::copy(&mut file, &mut stdout)?;
+                stdout.flush()?;
+
+                info!(count = self.corpus_count, "written corpora to stdout");
+            }
+        }
 
         Ok(())
     }
 }
 
+/// Checks that the GraphML is well-formed XML with a single `graphml` root element, e.g. to detect
+/// truncated exports, streaming over it without building a tree
+fn check_graphml(graphml: impl BufRead) -> anyhow::Result<()> {
+    let mut reader = Reader::from_reader(graphml);
+    let mut buf = Vec::new();
+    let mut depth: usize = 0;
+    let mut root_count = 0;
+
+    loop {
+        buf.clear();
+
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|err| anyhow!("{err} at position {}", reader.buffer_position()))?;
+
+        match event {
+            Event::Start(start) => {
+                if depth == 0 {
+                    ensure!(
+                        start.name().as_ref() == b"graphml",
+                        "unexpected root element `{}`",
+                        String::from_utf8_lossy(start.name().as_ref())
+                    );
+                    root_count += 1;
+                }
+
+                depth += 1;
+            }
+            Event::Empty(start) => {
+                ensure!(
+                    depth > 0,
+                    "unexpected empty root element `{}`",
+                    String::from_utf8_lossy(start.name().as_ref())
+                );
+            }
+            Event::End(_) => {
+                depth = depth.checked_sub(1).ok_or_else(|| {
+                    anyhow!(
+                        "unexpected end tag at position {}",
+                        reader.buffer_position()
+                    )
+                })?;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    ensure!(depth == 0, "truncated, {depth} elements not closed");
+    ensure!(
+        root_count == 1,
+        "expected exactly one root element, found {root_count}"
+    );
+
+    Ok(())
+}
+
+/// Copies the GraphML file at `path` to `target_path` line by line, replacing its single CDATA
+/// section (containing the corpus configuration) by the given config, without reading the whole
+/// file into memory
+fn replace_config_streaming(
+    path: &Path,
+    target_path: &Path,
+    config: &toml::Table,
+) -> anyhow::Result<()> {
+    const CDATA_START: &[u8] = b"<![CDATA[";
+    const CDATA_END: &[u8] = b"]]>";
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut writer = BufWriter::new(File::create(target_path)?);
+    let mut line = Vec::new();
+    let mut replaced = false;
+
+    loop {
+        line.clear();
+
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+
+        let Some(start) = find_bytes(&line, CDATA_START) else {
+            writer.write_all(&line)?;
+            continue;
+        };
+
+        ensure!(!replaced, "GraphML contains more than one CDATA section");
+
+        // the CDATA section may span multiple lines
+        let mut section = line.split_off(start);
+
+        let end = loop {
+            if let Some(end) = find_bytes(&section, CDATA_END) {
+                break end + CDATA_END.len();
+            }
+
+            ensure!(
+                reader.read_until(b'\n', &mut section)? > 0,
+                "GraphML ends within a CDATA section"
+            );
+        };
+
+        ensure!(
+            find_bytes(&section[end..], CDATA_START).is_none(),
+            "GraphML contains more than one CDATA section"
+        );
+
+        writer.write_all(&line)?;
+        write!(writer, "<![CDATA[{}]]>", toml::to_string_pretty(config)?)?;
+        writer.write_all(&section[end..])?;
+
+        replaced = true;
+    }
+
+    ensure!(replaced, "GraphML contains no CDATA section");
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Collects the paths of all files within `dir` and its subdirectories
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+
+        if file_type.is_file() {
+            paths.push(entry.path());
+        } else if file_type.is_dir() {
+            collect_files(&entry.path(), paths)?;
+        } else {
+            bail!(
+                "unexpected file {} in corpus export",
+                entry.path().display(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Entry of the output ZIP file
+enum Entry {
+    Content {
+        name: PathBuf,
+        content: String,
+    },
+    /// Entry whose content is copied from the file at `path`
+    File {
+        name: PathBuf,
+        path: PathBuf,
+    },
+}
+
+impl Entry {
+    fn name(&self) -> &Path {
+        match self {
+            Entry::Content { name, .. } | Entry::File { name, .. } => name,
+        }
+    }
+}
+
+fn write_entries(
+    zip_writer: &mut ZipWriter<impl Write + Seek>,
+    entries: &[Entry],
+) -> anyhow::Result<()> {
+    for entry in entries {
+        match entry {
+            Entry::Content { name, content } => {
+                zip_writer.start_file_from_path(name, SimpleFileOptions::default())?;
+                zip_writer.write_all(content.as_bytes())?;
+            }
+            Entry::File { name, path } => {
+                zip_writer.start_file_from_path(name, SimpleFileOptions::default())?;
+                io::copy(&mut File::open(path)?, zip_writer)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Compresses the entries into a temporary ZIP file
+fn compress_entries(entries: &[Entry]) -> anyhow::Result<NamedTempFile> {
+    let mut zip_writer = ZipWriter::new(NamedTempFile::new()?);
+    write_entries(&mut zip_writer, entries)?;
+    Ok(zip_writer.finish()?)
+}
+
 pub(crate) struct Corpus<'a> {
-    storage: Rc<annis_util::TempStorage>,
+    storage: annis_util::StorageSession,
     original_name: &'a str,
     name: Cow<'a, str>,
+    /// Node names and contents of the linked files added by [`Self::add_linked_file`], which are
+    /// not part of the storage and only written to the output
+    linked_files: Vec<(String, String)>,
+    result_order: QueryResultOrder,
+}
+
+/// Order of the matches of the queries on an outbound corpus, e.g. when renaming it or adding
+/// PartOf edges, which determines the order of the resulting update events
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub(crate) enum QueryResultOrder {
+    /// Sort the matches by the names of their nodes, which is reproducible across runs
+    #[default]
+    Sorted,
+    /// Order of graphannis by the position of the matched nodes in the text
+    Normal,
+    /// Reverse order of graphannis by the position of the matched nodes in the text
+    Inverted,
+    /// Random order, e.g. for checking that the output doesn't depend on the order
+    Randomized,
+    /// Order in which graphannis finds the matches
+    NotSorted,
 }
 
 impl<'a> Corpus<'a> {
-    pub(crate) fn from_inbound_corpus(corpus: &'a inbound::annis::Corpus<'_>) -> Self {
+    pub(crate) fn from_inbound_corpus(corpus: &inbound::annis::Corpus<'a>) -> Self {
         Self {
-            storage: Rc::clone(corpus.storage()),
+            storage: corpus.storage_handle().session(),
             original_name: corpus.name(),
             name: corpus.name().into(),
+            linked_files: Vec::new(),
+            result_order: QueryResultOrder::default(),
         }
     }
 
+    /// Sets the order of the matches of queries on this corpus
+    pub(crate) fn with_result_order(mut self, result_order: QueryResultOrder) -> Self {
+        self.result_order = result_order;
+        self
+    }
+
+    /// Creates a new, empty corpus named like the given inbound corpus in a separate storage, which
+    /// contains only the corpus node and does not share any data with the inbound corpus
+    pub(crate) fn standalone(corpus: &inbound::annis::Corpus<'a>) -> anyhow::Result<Self> {
+        let storage = annis_util::StorageHandle::new()?.session();
+
+        let mut update = GraphUpdate::new();
+        update.add_event(UpdateEvent::AddNode {
+            node_name: corpus.name().into(),
+            node_type: CORPUS.into(),
+        })?;
+        storage.apply_update(corpus.name(), &mut update)?;
+
+        Ok(Self {
+            storage,
+            original_name: corpus.name(),
+            name: corpus.name().into(),
+            linked_files: Vec::new(),
+            result_order: QueryResultOrder::default(),
+        })
+    }
+
+    /// Name of the entry in the output ZIP file for a file exported for this corpus (given relative
+    /// to the export directory), where the original corpus name is replaced by the current one
+    fn entry_name(&self, relative_path: &Path) -> PathBuf {
+        let mut components = relative_path.components();
+
+        let renamed_first = match components.next() {
+            Some(first) if first.as_os_str() == self.original_name => self.name.to_string(),
+            Some(first) if *first.as_os_str() == *format!("{}.graphml", self.original_name) => {
+                format!("{}.graphml", self.name)
+            }
+            _ => return relative_path.to_path_buf(),
+        };
+
+        Path::new(&renamed_first).join(components.as_path())
+    }
+
     pub(crate) fn begin_update(&self) -> Update<'_> {
         Update {
-            corpus: self,
+            corpus: Some(self),
+            corpus_name: self.original_name,
             update: Some(GraphUpdate::new()),
+            created_node_names: HashSet::new(),
+            event_counts: EventCounts::default(),
         }
     }
 
-    pub(crate) fn update_name(&mut self, op: impl FnOnce(&str) -> String) -> anyhow::Result<()> {
+    /// Renames the corpus, where `inspect_update` is called with the update renaming the nodes
+    /// before it is applied
+    pub(crate) fn update_name(
+        &mut self,
+        op: impl FnOnce(&str) -> String,
+        inspect_update: impl FnOnce(&Update<'_>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
         let new_name = op(&self.name);
 
-        let name_encoded = urlencoding::encode(&self.name);
-        let new_name_encoded = urlencoding::encode(&new_name);
-
         info!(old_name = &*self.name, new_name, "renaming corpus");
 
         let mut update = self.begin_update();
@@ -165,33 +691,154 @@ impl<'a> Corpus<'a> {
                 .exactly_one()
                 .map_err(|_| anyhow!("unexpected number of nodes in query match"))?;
 
-            let new_node_name = if node_name == self.name {
-                // node name of corpus node is *not* URL-encoded
-                new_name.clone()
-            } else if let Some((corpus_name_encoded, rest)) = node_name.split_once('/') {
-                // corpus name within node name of non-corpus node *is* URL-encoded
-                ensure!(
-                    corpus_name_encoded == name_encoded,
-                    "unexpected corpus name in node name: '{}' != '{}'",
-                    corpus_name_encoded,
-                    name_encoded,
-                );
-                format!("{new_name_encoded}/{rest}")
-            } else {
-                bail!("unexpected node name: '{node_name}'");
-            };
+            let new_node_name = node_name::rename_corpus(&node_name, &self.name, &new_name)?;
 
             update.add_node_anno(node_name, ANNIS_NS.into(), NODE_NAME.into(), new_node_name)?;
         }
 
+        inspect_update(&update)?;
         update.apply()?;
         self.name = new_name.into();
 
         Ok(())
     }
 
+    /// Adds a file of the given name and content linked to the corpus node, which must be called
+    /// after renaming the corpus since the file is stored under the current corpus name
+    pub(crate) fn add_linked_file(
+        &mut self,
+        file_name: &str,
+        content: String,
+    ) -> anyhow::Result<()> {
+        let node_name = format!("{}/{file_name}", node_name::encode(&self.name));
+
+        let mut update = self.begin_update();
+
+        update.add_node(node_name.clone(), FILE.into())?;
+
+        // annis:file = <path relative to the GraphML file>
+        update.add_node_anno(
+            node_name.clone(),
+            ANNIS_NS.into(),
+            FILE.into(),
+            node_name.clone(),
+        )?;
+
+        // PartOf/annis/ from file to corpus
+        update.add_edge(
+            node_name.clone(),
+            self.name.to_string(),
+            &AnnotationComponentType::PartOf,
+            ANNIS_NS.into(),
+            "".into(),
+        )?;
+
+        update.apply()?;
+
+        info!(corpus_name = &*self.name, node_name, "added linked file");
+
+        self.linked_files.push((node_name, content));
+
+        Ok(())
+    }
+
+    /// Number of nodes carrying each node annotation key, see
+    /// [`annis_util::node_anno_frequencies`]
+    pub(crate) fn node_anno_frequencies(&self) -> anyhow::Result<BTreeMap<String, u64>> {
+        annis_util::node_anno_frequencies(&self.storage, self.original_name)
+    }
+
+    /// Deletes all node and edge annotations whose keys match any of the given patterns, where
+    /// `inspect_update` is called with the update deleting them before it is applied
+    ///
+    /// Returns the number of deleted annotations per key (given as `ns:name`). Annotations of the
+    /// `annis` namespace are never deleted, and edge annotations only in dominance and pointing
+    /// components.
+    pub(crate) fn strip_annos(
+        &self,
+        patterns: &[AnnoKeyPattern],
+        inspect_update: impl FnOnce(&Update<'_>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<BTreeMap<String, usize>> {
+        let matches = |anno_key: &AnnoKey| {
+            anno_key.ns != ANNIS_NS
+                && patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(&anno_key.ns, &anno_key.name))
+        };
+
+        let node_anno_keys = annis_util::node_anno_keys(&self.storage, self.original_name)?
+            .into_iter()
+            .filter(matches)
+            .collect_vec();
+
+        let components = self
+            .storage
+            .list_components(self.original_name, None, None)?
+            .into_iter()
+            .filter(|component| {
+                matches!(
+                    component.get_type(),
+                    AnnotationComponentType::Dominance | AnnotationComponentType::Pointing
+                )
+            })
+            .collect_vec();
+
+        let mut update = self.begin_update();
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+
+        // AQL cannot select all annotation keys (e.g. names containing dots), so the nodes and
+        // edges are looked up by their exact keys, one document at a time
+        annis_util::CorpusGraph::new(&self.storage, self.original_name)?.for_each_part(|part| {
+            for anno_key in &node_anno_keys {
+                for node_name in part.node_names_with_anno(anno_key)? {
+                    update.delete_node_anno(
+                        node_name,
+                        anno_key.ns.to_string(),
+                        anno_key.name.to_string(),
+                    )?;
+                    *counts
+                        .entry(format!("{}:{}", anno_key.ns, anno_key.name))
+                        .or_default() += 1;
+                }
+            }
+
+            for component in &components {
+                for (source_node, target_node, anno_keys) in part.edges_with_annos(component)? {
+                    for anno_key in anno_keys.into_iter().filter(matches) {
+                        update.delete_edge_anno(
+                            source_node.clone(),
+                            target_node.clone(),
+                            component,
+                            anno_key.ns.to_string(),
+                            anno_key.name.to_string(),
+                        )?;
+                        *counts
+                            .entry(format!("{}:{}", anno_key.ns, anno_key.name))
+                            .or_default() += 1;
+                    }
+                }
+            }
+
+            Ok(())
+        })?;
+
+        inspect_update(&update)?;
+        update.apply()?;
+
+        Ok(counts)
+    }
+
+    /// Node names of the matches of the given AQL query, ordered according to the result order of
+    /// this corpus
     pub(crate) fn query(&self, query: &str) -> anyhow::Result<impl Iterator<Item = Vec<String>>> {
-        Ok(self
+        let order = match self.result_order {
+            QueryResultOrder::Sorted | QueryResultOrder::Normal => ResultOrder::Normal,
+            QueryResultOrder::Inverted => ResultOrder::Inverted,
+            QueryResultOrder::Randomized => ResultOrder::Randomized,
+            QueryResultOrder::NotSorted => ResultOrder::NotSorted,
+        };
+
+        let mut matches: Vec<_> = self
             .storage
             .find(
                 SearchQuery {
@@ -202,28 +849,105 @@ impl<'a> Corpus<'a> {
                 },
                 0,
                 None,
-                ResultOrder::Normal,
+                order,
             )?
             .into_iter()
-            .map(|m| node_names_from_match(&m)))
+            .map(|m| node_names_from_match(&m))
+            .collect();
+
+        if self.result_order == QueryResultOrder::Sorted {
+            matches.sort_unstable();
+        }
+
+        Ok(matches.into_iter())
     }
 }
 
 pub(crate) struct Update<'a> {
-    corpus: &'a Corpus<'a>,
+    /// Corpus this update is applied to, or `None` for a detached update
+    corpus: Option<&'a Corpus<'a>>,
+    corpus_name: &'a str,
     update: Option<GraphUpdate>,
+    created_node_names: HashSet<String>,
+    event_counts: EventCounts,
+}
+
+/// Numbers of the events of an [`Update`] by type
+#[derive(Clone, Copy, Default)]
+pub(crate) struct EventCounts {
+    pub(crate) nodes: usize,
+    pub(crate) node_labels: usize,
+    pub(crate) edges: usize,
+    /// Edge labels and deletions
+    pub(crate) other: usize,
 }
 
-impl Update<'_> {
+impl EventCounts {
+    pub(crate) fn total(self) -> usize {
+        self.nodes + self.node_labels + self.edges + self.other
+    }
+
+    fn count(&mut self, event: &UpdateEvent) {
+        match event {
+            UpdateEvent::AddNode { .. } => self.nodes += 1,
+            UpdateEvent::AddNodeLabel { .. } => self.node_labels += 1,
+            UpdateEvent::AddEdge { .. } => self.edges += 1,
+            _ => self.other += 1,
+        }
+    }
+}
+
+impl Sub for EventCounts {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            nodes: self.nodes - rhs.nodes,
+            node_labels: self.node_labels - rhs.node_labels,
+            edges: self.edges - rhs.edges,
+            other: self.other - rhs.other,
+        }
+    }
+}
+
+impl<'a> Update<'a> {
+    /// Creates an update for the corpus of the given name that is not tied to any storage, so it
+    /// cannot be applied but only turned into a [`GraphUpdate`], see [`Self::into_graph_update`]
+    pub(crate) fn detached(corpus_name: &'a str) -> Self {
+        Self {
+            corpus: None,
+            corpus_name,
+            update: Some(GraphUpdate::new()),
+            created_node_names: HashSet::new(),
+            event_counts: EventCounts::default(),
+        }
+    }
+
+    /// Original name of the corpus this update applies to
+    pub(crate) fn corpus_name(&self) -> &str {
+        self.corpus_name
+    }
+
+    /// Numbers of the events added to this update so far by type
+    pub(crate) fn event_counts(&self) -> EventCounts {
+        self.event_counts
+    }
+
+    /// Whether a node of the given name has already been added within this update
+    pub(crate) fn is_node_created(&self, node_name: &str) -> bool {
+        self.created_node_names.contains(node_name)
+    }
+
     pub(crate) fn add_node(&mut self, node_name: String, node_type: String) -> anyhow::Result<()> {
-        Ok(self
-            .update
-            .as_mut()
-            .unwrap()
-            .add_event(UpdateEvent::AddNode {
-                node_name,
-                node_type,
-            })?)
+        ensure!(
+            self.created_node_names.insert(node_name.clone()),
+            "node {node_name} is added more than once",
+        );
+
+        self.push_event(UpdateEvent::AddNode {
+            node_name,
+            node_type,
+        })
     }
 
     pub(crate) fn add_node_anno(
@@ -233,16 +957,12 @@ impl Update<'_> {
         anno_name: String,
         anno_value: String,
     ) -> anyhow::Result<()> {
-        Ok(self
-            .update
-            .as_mut()
-            .unwrap()
-            .add_event(UpdateEvent::AddNodeLabel {
-                node_name,
-                anno_ns,
-                anno_name,
-                anno_value,
-            })?)
+        self.push_event(UpdateEvent::AddNodeLabel {
+            node_name,
+            anno_ns,
+            anno_name,
+            anno_value,
+        })
     }
 
     pub(crate) fn add_edge(
@@ -253,34 +973,445 @@ impl Update<'_> {
         layer: String,
         component_name: String,
     ) -> anyhow::Result<()> {
-        Ok(self
-            .update
-            .as_mut()
-            .unwrap()
-            .add_event(UpdateEvent::AddEdge {
-                source_node,
-                target_node,
-                layer,
-                component_type: component_type.to_string(),
-                component_name,
-            })?)
+        self.push_event(UpdateEvent::AddEdge {
+            source_node,
+            target_node,
+            layer,
+            component_type: component_type.to_string(),
+            component_name,
+        })
     }
 
+    pub(crate) fn delete_node_anno(
+        &mut self,
+        node_name: String,
+        anno_ns: String,
+        anno_name: String,
+    ) -> anyhow::Result<()> {
+        self.push_event(UpdateEvent::DeleteNodeLabel {
+            node_name,
+            anno_ns,
+            anno_name,
+        })
+    }
+
+    pub(crate) fn delete_edge_anno(
+        &mut self,
+        source_node: String,
+        target_node: String,
+        component: &AnnotationComponent,
+        anno_ns: String,
+        anno_name: String,
+    ) -> anyhow::Result<()> {
+        self.push_event(UpdateEvent::DeleteEdgeLabel {
+            source_node,
+            target_node,
+            layer: component.layer.to_string(),
+            component_type: component.get_type().to_string(),
+            component_name: component.name.to_string(),
+            anno_ns,
+            anno_name,
+        })
+    }
+
+    /// Adds an arbitrary event, e.g. one read from an update dump
+    pub(crate) fn add_event(&mut self, event: UpdateEvent) -> anyhow::Result<()> {
+        if let UpdateEvent::AddNode { node_name, .. } = &event {
+            self.created_node_names.insert(node_name.clone());
+        }
+
+        self.push_event(event)
+    }
+
+    fn push_event(&mut self, event: UpdateEvent) -> anyhow::Result<()> {
+        self.event_counts.count(&event);
+        Ok(self.update.as_mut().unwrap().add_event(event)?)
+    }
+
+    /// Writes the events of this update to the given file, see [`UpdateDump`]
+    pub(crate) fn dump(&self, path: &Path) -> anyhow::Result<()> {
+        let mut update_dump = UpdateDump::create(path)?;
+        update_dump.write(self)?;
+        update_dump.finish()
+    }
+
+    /// Applies this update to the corpus, logging the numbers of events by type and the time it
+    /// took, which are also recorded in the `apply_update` span
     pub(crate) fn apply(mut self) -> anyhow::Result<()> {
+        let corpus = self
+            .corpus
+            .ok_or_else(|| anyhow!("a detached update cannot be applied"))?;
         let mut update = self.update.take().unwrap();
+        let count = update.len()?;
+        let counts = self.event_counts;
+        let span = info_span!(
+            "apply_update",
+            corpus_name = &*corpus.name,
+            count,
+            nodes = counts.nodes,
+            node_labels = counts.node_labels,
+            edges = counts.edges,
+            other = counts.other,
+            duration_ms = field::Empty
+        )
+        .entered();
 
         info!(
-            corpus_name = &*self.corpus.name,
-            count = update.len()?,
+            corpus_name = &*corpus.name,
+            count,
+            nodes = counts.nodes,
+            node_labels = counts.node_labels,
+            edges = counts.edges,
+            other = counts.other,
             "applying updates to corpus",
         );
 
-        Ok(self
-            .corpus
+        let start = Instant::now();
+
+        corpus
             .storage
-            .apply_update(self.corpus.original_name, &mut update)?)
+            .apply_update(corpus.original_name, &mut update)?;
+
+        let duration_ms = start.elapsed().as_millis();
+        span.record("duration_ms", duration_ms);
+
+        info!(
+            corpus_name = &*corpus.name,
+            duration_ms, "applied updates to corpus"
+        );
+
+        Ok(())
+    }
+
+    /// Events of this update, e.g. of a detached update (see [`Self::detached`])
+    pub(crate) fn into_graph_update(mut self) -> GraphUpdate {
+        self.update.take().unwrap()
+    }
+}
+
+/// Pattern for annotation keys, given as `[NS:]NAME` where both parts may contain the wildcards `*`
+/// (any number of characters) and `?` (a single character), and a missing namespace matches any
+/// namespace
+#[derive(Clone, Debug)]
+pub(crate) struct AnnoKeyPattern {
+    ns: Option<Regex>,
+    name: Regex,
+}
+
+impl FromStr for AnnoKeyPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ns, name) = match s.split_once(':') {
+            Some((ns, name)) => (Some(glob_regex(ns)?), name),
+            None => (None, s),
+        };
+
+        ensure!(!name.is_empty(), "annotation name must not be empty");
+
+        Ok(Self {
+            ns,
+            name: glob_regex(name)?,
+        })
+    }
+}
+
+impl AnnoKeyPattern {
+    pub(crate) fn matches(&self, ns: &str, name: &str) -> bool {
+        self.ns
+            .as_ref()
+            .map_or(true, |ns_regex| ns_regex.is_match(ns))
+            && self.name.is_match(name)
+    }
+}
+
+/// Converts a glob pattern with the wildcards `*` and `?` into a regex matching the whole string
+pub(crate) fn glob_regex(glob: &str) -> anyhow::Result<Regex> {
+    let regex = glob
+        .split('*')
+        .map(|part| part.split('?').map(regex::escape).join("."))
+        .join(".*");
+
+    Ok(Regex::new(&format!("^{regex}$"))?)
+}
+
+/// Graph update event together with the name of the corpus it applies to, as written to update
+/// dumps
+#[derive(Deserialize, Serialize)]
+struct DumpedUpdateEvent {
+    corpus: String,
+    event: UpdateEvent,
+}
+
+/// Writer of graph update events to a file in the JSON Lines format, one event together with the
+/// name of its corpus per line
+pub(crate) struct UpdateDump {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    count: usize,
+}
+
+impl UpdateDump {
+    pub(crate) fn create(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            path: path.into(),
+            writer: BufWriter::new(File::create(path)?),
+            count: 0,
+        })
+    }
+
+    pub(crate) fn write(&mut self, update: &Update<'_>) -> anyhow::Result<()> {
+        for event in update.update.as_ref().unwrap().iter()? {
+            let (_, event) = event?;
+
+            serde_json::to_writer(
+                &mut self.writer,
+                &DumpedUpdateEvent {
+                    corpus: update.corpus_name().into(),
+                    event,
+                },
+            )?;
+            writeln!(self.writer)?;
+
+            self.count += 1;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+
+        info!(path = %self.path.display(), count = self.count, "dumped updates");
+
+        Ok(())
     }
 }
 
+/// Reads the graph update events written by an [`UpdateDump`], grouped by corpus name
+pub(crate) fn read_update_dump(path: &Path) -> anyhow::Result<BTreeMap<String, Vec<UpdateEvent>>> {
+    let mut events: BTreeMap<String, Vec<UpdateEvent>> = BTreeMap::new();
+
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let dumped_event: DumpedUpdateEvent = serde_json::from_str(&line)
+            .map_err(|err| anyhow!("invalid update event in line {}: {err}", i + 1))?;
+
+        events
+            .entry(dumped_event.corpus)
+            .or_default()
+            .push(dumped_event.event);
+    }
+
+    info!(
+        path = %path.display(),
+        corpora = events.len(),
+        count = events.values().map(Vec::len).sum::<usize>(),
+        "read updates"
+    );
+
+    Ok(events)
+}
+
 static CDATA_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"<!\[CDATA\[(?s:.)*?]]>").unwrap());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_regex_matches_whole_string() -> anyhow::Result<()> {
+        let regex = glob_regex("infl*")?;
+        assert!(regex.is_match("infl"));
+        assert!(regex.is_match("infl.case"));
+        assert!(!regex.is_match("xinfl"));
+
+        let regex = glob_regex("p?s")?;
+        assert!(regex.is_match("pos"));
+        assert!(!regex.is_match("ps"));
+        assert!(!regex.is_match("poss"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn glob_regex_escapes_other_characters() -> anyhow::Result<()> {
+        let regex = glob_regex("infl.case")?;
+        assert!(regex.is_match("infl.case"));
+        assert!(!regex.is_match("inflxcase"));
+
+        let regex = glob_regex("a+(b)")?;
+        assert!(regex.is_match("a+(b)"));
+        assert!(!regex.is_match("aab"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn anno_key_pattern_without_ns_matches_any_ns() -> anyhow::Result<()> {
+        let pattern: AnnoKeyPattern = "lemma".parse()?;
+        assert!(pattern.matches("", "lemma"));
+        assert!(pattern.matches("default_ns", "lemma"));
+        assert!(!pattern.matches("default_ns", "lemmas"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn anno_key_pattern_with_ns() -> anyhow::Result<()> {
+        let pattern: AnnoKeyPattern = "default_ns:infl.*".parse()?;
+        assert!(pattern.matches("default_ns", "infl.case"));
+        assert!(!pattern.matches("default_ns", "infl"));
+        assert!(!pattern.matches("other", "infl.case"));
+
+        let pattern: AnnoKeyPattern = "*:pos".parse()?;
+        assert!(pattern.matches("", "pos"));
+        assert!(pattern.matches("default_ns", "pos"));
+
+        let pattern: AnnoKeyPattern = ":pos".parse()?;
+        assert!(pattern.matches("", "pos"));
+        assert!(!pattern.matches("default_ns", "pos"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn anno_key_pattern_rejects_empty_name() {
+        for s in ["", "default_ns:"] {
+            assert_eq!(
+                s.parse::<AnnoKeyPattern>().unwrap_err().to_string(),
+                "annotation name must not be empty"
+            );
+        }
+    }
+
+    /// Corpus of the given name with annotations whose names contain dots
+    fn corpus_with_annos(name: &'static str) -> anyhow::Result<Corpus<'static>> {
+        let corpus = Corpus {
+            storage: annis_util::StorageHandle::new()?.session(),
+            original_name: name,
+            name: name.into(),
+            linked_files: Vec::new(),
+            result_order: QueryResultOrder::default(),
+        };
+
+        // the corpus name is encoded in all node names but that of the corpus node
+        let doc = format!("{}/doc", node_name::encode(name));
+        let n1 = format!("{doc}#n1");
+        let n2 = format!("{doc}#n2");
+
+        let mut update = GraphUpdate::new();
+
+        for (node_name, node_type) in [
+            (name, "corpus"),
+            (&*doc, "corpus"),
+            (&*n1, "node"),
+            (&*n2, "node"),
+        ] {
+            update.add_event(UpdateEvent::AddNode {
+                node_name: node_name.into(),
+                node_type: node_type.into(),
+            })?;
+        }
+
+        for (source_node, target_node, component_type) in [
+            (&*doc, name, "PartOf"),
+            (&*n1, &*doc, "PartOf"),
+            (&*n2, &*doc, "PartOf"),
+            (&*n1, &*n2, "Dominance"),
+        ] {
+            update.add_event(UpdateEvent::AddEdge {
+                source_node: source_node.into(),
+                target_node: target_node.into(),
+                layer: if component_type == "PartOf" {
+                    ANNIS_NS.into()
+                } else {
+                    DEFAULT_NS.into()
+                },
+                component_type: component_type.into(),
+                component_name: String::new(),
+            })?;
+        }
+
+        for (node_name, anno_ns, anno_name) in [
+            (&*doc, ANNIS_NS, DOC),
+            (&*n1, DEFAULT_NS, "infl.case"),
+            (&*n2, DEFAULT_NS, "infl.case"),
+            (&*n2, DEFAULT_NS, "pos"),
+        ] {
+            update.add_event(UpdateEvent::AddNodeLabel {
+                node_name: node_name.into(),
+                anno_ns: anno_ns.into(),
+                anno_name: anno_name.into(),
+                anno_value: "x".into(),
+            })?;
+        }
+
+        update.add_event(UpdateEvent::AddEdgeLabel {
+            source_node: n1,
+            target_node: n2,
+            layer: DEFAULT_NS.into(),
+            component_type: "Dominance".into(),
+            component_name: String::new(),
+            anno_ns: DEFAULT_NS.into(),
+            anno_name: "func.x".into(),
+            anno_value: "x".into(),
+        })?;
+
+        corpus.storage.apply_update(name, &mut update)?;
+
+        Ok(corpus)
+    }
+
+    #[test]
+    fn strip_annos_deletes_annos_with_dotted_names() -> anyhow::Result<()> {
+        let corpus = corpus_with_annos("corpus")?;
+
+        let patterns = ["infl.*".parse()?, "default_ns:func.x".parse()?];
+        let counts = corpus.strip_annos(&patterns, |_| Ok(()))?;
+
+        assert_eq!(
+            counts,
+            BTreeMap::from([
+                ("default_ns:func.x".into(), 1),
+                ("default_ns:infl.case".into(), 2),
+            ])
+        );
+        assert_eq!(
+            corpus.node_anno_frequencies()?,
+            BTreeMap::from([("default_ns:pos".into(), 1)])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn annos_are_found_in_corpus_with_encoded_name() -> anyhow::Result<()> {
+        let corpus = corpus_with_annos("Bärmann corpus")?;
+
+        assert_eq!(
+            corpus.node_anno_frequencies()?,
+            BTreeMap::from([
+                ("default_ns:infl.case".into(), 2),
+                ("default_ns:pos".into(), 1),
+            ])
+        );
+
+        let patterns = ["infl.*".parse()?];
+        let counts = corpus.strip_annos(&patterns, |_| Ok(()))?;
+
+        assert_eq!(counts, BTreeMap::from([("default_ns:infl.case".into(), 2)]));
+        assert_eq!(
+            corpus.node_anno_frequencies()?,
+            BTreeMap::from([("default_ns:pos".into(), 1)])
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tracing::info;
+
+use crate::node_name;
+
+/// Writer of intermediate artifacts of the conversion phases, written to a directory for
+/// inspection
+pub(crate) struct ArtifactWriter {
+    dir: PathBuf,
+}
+
+impl ArtifactWriter {
+    pub(crate) fn new(dir: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.into() })
+    }
+
+    /// Path of the artifact with the given name, which is prefixed by the encoded corpus name (see
+    /// [`node_name::encode`]) for artifacts of a corpus, e.g. `CORPUS.align.json`
+    pub(crate) fn path(&self, corpus_name: Option<&str>, name: &str) -> PathBuf {
+        match corpus_name {
+            Some(corpus_name) => self
+                .dir
+                .join(format!("{}.{name}", node_name::encode(corpus_name))),
+            None => self.dir.join(name),
+        }
+    }
+
+    pub(crate) fn write_json(
+        &self,
+        corpus_name: Option<&str>,
+        name: &str,
+        value: &impl Serialize,
+    ) -> anyhow::Result<()> {
+        let path = self.path(corpus_name, name);
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&path)?), value)?;
+
+        info!(path = %path.display(), "written artifact");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn path_encodes_corpus_name() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let writer = ArtifactWriter::new(dir.path())?;
+
+        assert_eq!(
+            writer.path(Some("../sub/corpus"), "align.json"),
+            dir.path().join("..%2Fsub%2Fcorpus.align.json")
+        );
+        assert_eq!(
+            writer.path(None, "import.json"),
+            dir.path().join("import.json")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use tracing::info;
+
+/// Link between a node of an input corpus and a node of the corresponding output corpus
+pub(crate) struct Link {
+    pub(crate) corpus: String,
+    /// Name of the node in the input corpus, `None` for nodes that exist only in the output corpus
+    pub(crate) original_node_name: Option<String>,
+    pub(crate) treebank_node_name: String,
+    pub(crate) iri: String,
+}
+
+/// Writer of a CSV table linking the nodes of the input corpora to the nodes of the output corpora
+/// and the IRIs of the TTL nodes, so that e.g. standalone corpora can be queried jointly with the
+/// input corpora
+pub(crate) struct LinkTableWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    count: usize,
+}
+
+impl LinkTableWriter {
+    pub(crate) fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "corpus,original_node_name,treebank_node_name,iri")?;
+
+        Ok(Self {
+            path: path.into(),
+            writer,
+            count: 0,
+        })
+    }
+
+    pub(crate) fn write(&mut self, link: &Link) -> anyhow::Result<()> {
+        writeln!(
+            self.writer,
+            "{},{},{},{}",
+            csv_field(&link.corpus),
+            csv_field(link.original_node_name.as_deref().unwrap_or_default()),
+            csv_field(&link.treebank_node_name),
+            csv_field(&link.iri),
+        )?;
+
+        self.count += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+
+        info!(path = %self.path.display(), count = self.count, "written link table");
+
+        Ok(())
+    }
+}
+
+/// Quotes the field if it contains a character with a special meaning in CSV
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::bail;
+use tracing::info;
+
+/// Interval in which a held lock is checked again when waiting for it
+const WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Lock on an output path, held as a lock file `OUTPUT.lock` next to the output while it exists,
+/// so that concurrent conversions don't write the same output
+///
+/// The lock is advisory, i.e. it only protects against other conversions taking the lock. It is
+/// released by deleting the lock file when dropped.
+pub(crate) struct OutputLock {
+    path: PathBuf,
+}
+
+impl OutputLock {
+    /// Takes the lock on the given output path, failing if it is held by another conversion unless
+    /// `wait` is set, in which case this waits until the lock is released
+    pub(crate) fn acquire(output_path: &Path, wait: bool) -> anyhow::Result<Self> {
+        let mut file_name = output_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".lock");
+        let path = output_path.with_file_name(file_name);
+        let mut waiting = false;
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    write_owner(&mut file)?;
+                    info!(path = %path.display(), "acquired output lock");
+                    return Ok(Self { path });
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    if !wait {
+                        bail!(
+                            "another conversion is writing this output, as indicated by the lock \
+                             file {} ({}), use --wait to wait for it to finish or delete the lock \
+                             file if no other conversion is running",
+                            path.display(),
+                            owner(&path)
+                        );
+                    }
+
+                    if !waiting {
+                        info!(
+                            path = %path.display(),
+                            owner = owner(&path),
+                            "waiting for another conversion writing this output"
+                        );
+                        waiting = true;
+                    }
+
+                    thread::sleep(WAIT_INTERVAL);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        // a lock file that cannot be deleted is reported by the next conversion
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn write_owner(file: &mut File) -> io::Result<()> {
+    writeln!(file, "pid {}", process::id())
+}
+
+/// Description of the conversion holding the lock, as written into the lock file
+fn owner(path: &Path) -> String {
+    fs::read_to_string(path)
+        .map_or_else(|_| "unknown owner".into(), |owner| owner.trim().to_owned())
+}
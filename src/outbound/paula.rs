@@ -0,0 +1,224 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use tracing::info;
+
+use super::treebank::{Child, Document};
+use crate::node_name;
+
+const PAULA_VERSION: &str = "1.1";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// Writer of PAULA documents, creating one directory per document containing
+///
+/// - the primary text (`DOC.text.xml`),
+/// - the tokens as markables over the text (`DOC.tok.xml`),
+/// - the nonterminals as structs with one rel per dominance edge (`NS.DOC.LAYER_struct.xml`),
+/// - the categories of the nonterminals as feats (`NS.DOC.LAYER_struct_ANNO.xml`) and
+/// - the annotation set listing all of these files (`DOC.anno.xml`),
+///
+/// where `DOC` is the encoded document name (see [`node_name::encode`]), so that the documents of
+/// subcorpora don't end up in nested directories, and `NS` is the annotation namespace, which
+/// PAULA derives from the file name prefix.
+pub(crate) struct PaulaWriter {
+    dir: PathBuf,
+    layer: String,
+    anno_ns: String,
+    anno: String,
+}
+
+impl PaulaWriter {
+    pub(crate) fn new(dir: PathBuf, layer: String, anno_ns: String, anno: String) -> Self {
+        Self {
+            dir,
+            layer,
+            anno_ns,
+            anno,
+        }
+    }
+
+    pub(crate) fn write(&self, document: &Document) -> anyhow::Result<()> {
+        let doc = node_name::encode(&document.document);
+        let doc_dir = self
+            .dir
+            .join(&*node_name::encode(&document.corpus))
+            .join(&*doc);
+        fs::create_dir_all(&doc_dir)?;
+
+        let ns = &self.anno_ns;
+        let text_file = format!("{doc}.text.xml");
+        let tok_file = format!("{doc}.tok.xml");
+        let struct_file = format!("{ns}.{doc}.{}_struct.xml", self.layer);
+        let feat_file = format!("{ns}.{doc}.{}_struct_{}.xml", self.layer, self.anno);
+        let anno_file = format!("{doc}.anno.xml");
+
+        let (text, token_ranges) = document.text();
+        let children = document.children();
+
+        write_paula_file(&doc_dir.join(&text_file), "text", &text_file, |w| {
+            w.create_element("body")
+                .write_text_content(BytesText::new(&text))?;
+            Ok(())
+        })?;
+
+        write_paula_file(&doc_dir.join(&tok_file), "mark", &tok_file, |w| {
+            w.create_element("markList")
+                .with_attributes([
+                    ("xmlns:xlink", XLINK_NS),
+                    ("type", "tok"),
+                    ("xml:base", text_file.as_str()),
+                ])
+                .write_inner_content(|w| {
+                    for (i, range) in token_ranges.iter().enumerate() {
+                        // string ranges are 1-based
+                        let (start, len) = (range.start + 1, range.len());
+
+                        w.create_element("mark")
+                            .with_attributes([
+                                ("id", token_id(i).as_str()),
+                                (
+                                    "xlink:href",
+                                    format!("#xpointer(string-range(//body,'',{start},{len}))")
+                                        .as_str(),
+                                ),
+                            ])
+                            .write_empty()?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+        write_paula_file(&doc_dir.join(&struct_file), "struct", &struct_file, |w| {
+            w.create_element("structList")
+                .with_attributes([("xmlns:xlink", XLINK_NS), ("type", self.layer.as_str())])
+                .write_inner_content(|w| {
+                    let mut rel_index = 0;
+
+                    for (parent, children) in children.iter().enumerate() {
+                        w.create_element("struct")
+                            .with_attribute(("id", nonterminal_id(parent).as_str()))
+                            .write_inner_content(|w| {
+                                for &child in children {
+                                    rel_index += 1;
+
+                                    let href = match child {
+                                        Child::Token(i) => format!("{tok_file}#{}", token_id(i)),
+                                        Child::Nonterminal(i) => format!("#{}", nonterminal_id(i)),
+                                    };
+
+                                    w.create_element("rel")
+                                        .with_attributes([
+                                            ("id", format!("rel_{rel_index}").as_str()),
+                                            ("xlink:href", href.as_str()),
+                                        ])
+                                        .write_empty()?;
+                                }
+                                Ok(())
+                            })?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+        write_paula_file(&doc_dir.join(&feat_file), "feat", &feat_file, |w| {
+            w.create_element("featList")
+                .with_attributes([
+                    ("xmlns:xlink", XLINK_NS),
+                    ("type", self.anno.as_str()),
+                    ("xml:base", struct_file.as_str()),
+                ])
+                .write_inner_content(|w| {
+                    for (i, cat) in document.nonterminals.iter().enumerate() {
+                        if let Some(cat) = cat {
+                            w.create_element("feat")
+                                .with_attributes([
+                                    ("xlink:href", format!("#{}", nonterminal_id(i)).as_str()),
+                                    ("value", cat.as_str()),
+                                ])
+                                .write_empty()?;
+                        }
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+        write_paula_file(&doc_dir.join(&anno_file), "struct", &anno_file, |w| {
+            w.create_element("structList")
+                .with_attributes([("xmlns:xlink", XLINK_NS), ("type", "annoSet")])
+                .write_inner_content(|w| {
+                    w.create_element("struct")
+                        .with_attribute(("id", "anno_1"))
+                        .write_inner_content(|w| {
+                            for (i, file) in [&text_file, &tok_file, &struct_file, &feat_file]
+                                .into_iter()
+                                .enumerate()
+                            {
+                                w.create_element("rel")
+                                    .with_attributes([
+                                        ("id", format!("rel_{}", i + 1).as_str()),
+                                        ("xlink:href", file.as_str()),
+                                    ])
+                                    .write_empty()?;
+                            }
+                            Ok(())
+                        })?;
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+        info!(path = %doc_dir.display(), "written PAULA document");
+
+        Ok(())
+    }
+}
+
+fn token_id(index: usize) -> String {
+    format!("tok_{}", index + 1)
+}
+
+fn nonterminal_id(index: usize) -> String {
+    format!("struct_{}", index + 1)
+}
+
+/// Writes a PAULA file of the given type (determining the DTD), where `write_content` writes the
+/// content following the header
+fn write_paula_file(
+    path: &Path,
+    dtd_type: &str,
+    file_name: &str,
+    write_content: impl FnOnce(&mut Writer<BufWriter<File>>) -> quick_xml::Result<()>,
+) -> anyhow::Result<()> {
+    let mut writer = Writer::new_with_indent(BufWriter::new(File::create(path)?), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new(
+        "1.0",
+        Some("UTF-8"),
+        Some("no"),
+    )))?;
+    writer.write_event(Event::DocType(BytesText::from_escaped(format!(
+        "paula SYSTEM \"paula_{dtd_type}.dtd\""
+    ))))?;
+
+    let paula_id = file_name.strip_suffix(".xml").unwrap_or(file_name);
+
+    writer
+        .create_element("paula")
+        .with_attribute(("version", PAULA_VERSION))
+        .write_inner_content(|w| {
+            w.create_element("header")
+                .with_attribute(("paula_id", paula_id))
+                .write_empty()?;
+            write_content(w)
+        })?;
+
+    writer.into_inner().flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, ensure};
+use serde::Deserialize;
+use tracing::info;
+
+/// Pipeline of external commands (e.g. annatto) run on the export of each corpus before it is
+/// written into the output ZIP file, read from a TOML file of the form
+///
+/// ```toml
+/// [[steps]]
+/// command = "annatto"
+/// args = ["run", "enrich.toml"]
+///
+/// [[steps]]
+/// command = "sed"
+/// args = ["-i", "s/Ms\\./Manuscript/", "{graphml}"]
+/// ```
+///
+/// where each step is run in the export directory of the corpus, which contains the GraphML file
+/// `CORPUS.graphml` and the directory `CORPUS` with the linked files. The placeholders `{dir}`,
+/// `{graphml}` and `{corpus}` in `args` are replaced by the export directory, the path of the
+/// GraphML file and the corpus name, which are also passed as the environment variables
+/// `CORPUS_DIR`, `CORPUS_GRAPHML` and `CORPUS_NAME`. The steps may modify or add files in the
+/// export directory, and a failing step aborts the conversion.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PostProcess {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+impl PostProcess {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let post_process: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        info!(
+            path = %path.display(),
+            steps = post_process.steps.len(),
+            "read post-processing pipeline"
+        );
+
+        Ok(post_process)
+    }
+
+    /// Runs all steps in order on the export of the given corpus in `dir`
+    pub(crate) fn run(
+        &self,
+        dir: &Path,
+        graphml_path: &Path,
+        corpus_name: &str,
+    ) -> anyhow::Result<()> {
+        let dir_str = dir.to_string_lossy();
+        let graphml_str = graphml_path.to_string_lossy();
+
+        for step in &self.steps {
+            info!(
+                corpus_name,
+                command = step.command,
+                "running post-processing step"
+            );
+
+            let status = Command::new(&step.command)
+                .args(step.args.iter().map(|arg| {
+                    arg.replace("{dir}", &dir_str)
+                        .replace("{graphml}", &graphml_str)
+                        .replace("{corpus}", corpus_name)
+                }))
+                .current_dir(dir)
+                .env("CORPUS_DIR", dir)
+                .env("CORPUS_GRAPHML", graphml_path)
+                .env("CORPUS_NAME", corpus_name)
+                .stdin(Stdio::null())
+                // the output ZIP file may be written to stdout
+                .stdout(io::stderr())
+                .status()
+                .map_err(|err| {
+                    anyhow!(
+                        "running post-processing command {} failed: {err}",
+                        step.command
+                    )
+                })?;
+
+            ensure!(
+                status.success(),
+                "post-processing command {} failed for corpus {corpus_name} with {status}",
+                step.command
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use quick_xml::events::{BytesDecl, Event};
+use quick_xml::Writer;
+use tracing::info;
+
+use super::treebank::{Child, Document};
+use crate::node_name;
+
+const XMI_NS: &str = "http://www.omg.org/XMI";
+const XSI_NS: &str = "http://www.w3.org/2001/XMLSchema-instance";
+const SALT_CORE_NS: &str = "http://www.corpus-tools.org/salt/SaltCore";
+const SALT_COMMON_NS: &str = "http://www.corpus-tools.org/salt/SaltCommon";
+const SALT_CORPUS_STRUCTURE_NS: &str = "http://www.corpus-tools.org/salt/SaltCorpusStructure";
+const SALT_DOCUMENT_STRUCTURE_NS: &str = "http://www.corpus-tools.org/salt/SaltDocumentStructure";
+
+/// Writer of SaltXML documents, creating one document graph `CORPUS/DOC.salt` per document and a
+/// `saltProject.salt` describing the corpus structure once finished
+///
+/// The corpus and document names are encoded (see [`node_name::encode`]) in paths and IDs, so that
+/// the documents of subcorpora don't end up in nested directories.
+pub(crate) struct SaltXmlWriter {
+    dir: PathBuf,
+    layer: String,
+    anno_ns: String,
+    anno: String,
+    /// Names of the written documents by corpus name
+    documents: BTreeMap<String, Vec<String>>,
+}
+
+impl SaltXmlWriter {
+    pub(crate) fn new(dir: PathBuf, layer: String, anno_ns: String, anno: String) -> Self {
+        Self {
+            dir,
+            layer,
+            anno_ns,
+            anno,
+            documents: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn write(&mut self, document: &Document) -> anyhow::Result<()> {
+        let corpus = node_name::encode(&document.corpus);
+        let doc = node_name::encode(&document.document);
+
+        let corpus_dir = self.dir.join(&*corpus);
+        fs::create_dir_all(&corpus_dir)?;
+
+        let path = corpus_dir.join(format!("{doc}.salt"));
+        let doc_id = format!("salt:/{corpus}/{doc}");
+
+        let (text, token_ranges) = document.text();
+        let children = document.children();
+
+        // nodes are referenced by their position: the text, then the tokens, then the structures
+        let token_ref = |i: usize| format!("//@nodes.{}", 1 + i);
+        let structure_ref = |i: usize| format!("//@nodes.{}", 1 + document.tokens.len() + i);
+        let dominance_edge_refs = (0..document.edges.len())
+            .map(|i| format!("//@edges.{}", document.tokens.len() + i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let structure_refs = (0..document.nonterminals.len())
+            .map(structure_ref)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        write_salt_file(&path, |w| {
+            w.create_element("sDocumentStructure:SDocumentGraph")
+                .with_attributes([
+                    ("xmlns:xmi", XMI_NS),
+                    ("xmlns:xsi", XSI_NS),
+                    ("xmlns:sDocumentStructure", SALT_DOCUMENT_STRUCTURE_NS),
+                    ("xmlns:saltCore", SALT_CORE_NS),
+                    ("xmi:version", "2.0"),
+                ])
+                .write_inner_content(|w| {
+                    write_id_label(w, &doc_id)?;
+
+                    w.create_element("nodes")
+                        .with_attribute(("xsi:type", "sDocumentStructure:STextualDS"))
+                        .write_inner_content(|w| {
+                            write_id_label(w, &format!("{doc_id}#sText1"))?;
+                            write_feature_label(w, "SNAME", "T::sText1")?;
+                            write_feature_label(w, "SDATA", &format!("T::{text}"))
+                        })?;
+
+                    for i in 0..document.tokens.len() {
+                        w.create_element("nodes")
+                            .with_attribute(("xsi:type", "sDocumentStructure:SToken"))
+                            .write_inner_content(|w| {
+                                write_id_label(w, &format!("{doc_id}#sTok{}", i + 1))?;
+                                write_feature_label(w, "SNAME", &format!("T::sTok{}", i + 1))
+                            })?;
+                    }
+
+                    for (i, cat) in document.nonterminals.iter().enumerate() {
+                        w.create_element("nodes")
+                            .with_attributes([
+                                ("xsi:type", "sDocumentStructure:SStructure"),
+                                ("layers", "//@layers.0"),
+                            ])
+                            .write_inner_content(|w| {
+                                write_id_label(w, &format!("{doc_id}#structure{}", i + 1))?;
+                                write_feature_label(w, "SNAME", &format!("T::structure{}", i + 1))?;
+
+                                if let Some(cat) = cat {
+                                    w.create_element("labels")
+                                        .with_attributes([
+                                            ("xsi:type", "saltCore:SAnnotation"),
+                                            ("namespace", self.anno_ns.as_str()),
+                                            ("name", self.anno.as_str()),
+                                            ("value", format!("T::{cat}").as_str()),
+                                        ])
+                                        .write_empty()?;
+                                }
+
+                                Ok(())
+                            })?;
+                    }
+
+                    for (i, range) in token_ranges.iter().enumerate() {
+                        w.create_element("edges")
+                            .with_attributes([
+                                ("xsi:type", "sDocumentStructure:STextualRelation"),
+                                ("source", token_ref(i).as_str()),
+                                ("target", "//@nodes.0"),
+                            ])
+                            .write_inner_content(|w| {
+                                write_id_label(w, &format!("{doc_id}#sTextRel{}", i + 1))?;
+                                write_feature_label(w, "SSTART", &format!("N::{}", range.start))?;
+                                write_feature_label(w, "SEND", &format!("N::{}", range.end))
+                            })?;
+                    }
+
+                    let mut relation_index = 0;
+
+                    for (parent, children) in children.iter().enumerate() {
+                        for &child in children {
+                            relation_index += 1;
+
+                            let target = match child {
+                                Child::Token(i) => token_ref(i),
+                                Child::Nonterminal(i) => structure_ref(i),
+                            };
+
+                            w.create_element("edges")
+                                .with_attributes([
+                                    ("xsi:type", "sDocumentStructure:SDominanceRelation"),
+                                    ("source", structure_ref(parent).as_str()),
+                                    ("target", target.as_str()),
+                                    ("layers", "//@layers.0"),
+                                ])
+                                .write_inner_content(|w| {
+                                    write_id_label(w, &format!("{doc_id}#sDomRel{relation_index}"))
+                                })?;
+                        }
+                    }
+
+                    w.create_element("layers")
+                        .with_attributes([
+                            ("xsi:type", "saltCore:SLayer"),
+                            ("nodes", structure_refs.as_str()),
+                            ("edges", dominance_edge_refs.as_str()),
+                        ])
+                        .write_inner_content(|w| {
+                            write_id_label(w, &format!("{doc_id}#l1"))?;
+                            write_feature_label(w, "SNAME", &format!("T::{}", self.layer))
+                        })?;
+
+                    Ok(())
+                })?;
+
+            Ok(())
+        })?;
+
+        info!(path = %path.display(), "written SaltXML document");
+
+        self.documents
+            .entry(document.corpus.clone())
+            .or_default()
+            .push(document.document.clone());
+
+        Ok(())
+    }
+
+    /// Writes the `saltProject.salt` describing all corpora and documents written so far
+    pub(crate) fn finish(self) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join("saltProject.salt");
+
+        write_salt_file(&path, |w| {
+            w.create_element("saltCommon:SaltProject")
+                .with_attributes([
+                    ("xmlns:xmi", XMI_NS),
+                    ("xmlns:xsi", XSI_NS),
+                    ("xmlns:sCorpusStructure", SALT_CORPUS_STRUCTURE_NS),
+                    ("xmlns:saltCommon", SALT_COMMON_NS),
+                    ("xmlns:saltCore", SALT_CORE_NS),
+                    ("xmi:version", "2.0"),
+                ])
+                .write_inner_content(|w| {
+                    for (g, (corpus, documents)) in self.documents.iter().enumerate() {
+                        let encoded_corpus = node_name::encode(corpus);
+
+                        w.create_element("sCorpusGraphs").write_inner_content(|w| {
+                            w.create_element("nodes")
+                                .with_attribute(("xsi:type", "sCorpusStructure:SCorpus"))
+                                .write_inner_content(|w| {
+                                    write_id_label(w, &format!("salt:/{encoded_corpus}"))?;
+                                    write_feature_label(w, "SNAME", &format!("T::{corpus}"))
+                                })?;
+
+                            for document in documents {
+                                w.create_element("nodes")
+                                    .with_attribute(("xsi:type", "sCorpusStructure:SDocument"))
+                                    .write_inner_content(|w| {
+                                        write_id_label(
+                                            w,
+                                            &format!(
+                                                "salt:/{encoded_corpus}/{}",
+                                                node_name::encode(document)
+                                            ),
+                                        )?;
+                                        write_feature_label(w, "SNAME", &format!("T::{document}"))
+                                    })?;
+                            }
+
+                            for d in 0..documents.len() {
+                                w.create_element("edges")
+                                    .with_attributes([
+                                        ("xsi:type", "sCorpusStructure:SCorpusDocumentRelation"),
+                                        (
+                                            "source",
+                                            format!("//@sCorpusGraphs.{g}/@nodes.0").as_str(),
+                                        ),
+                                        (
+                                            "target",
+                                            format!("//@sCorpusGraphs.{g}/@nodes.{}", d + 1)
+                                                .as_str(),
+                                        ),
+                                    ])
+                                    .write_empty()?;
+                            }
+
+                            Ok(())
+                        })?;
+                    }
+
+                    Ok(())
+                })?;
+
+            Ok(())
+        })?;
+
+        info!(path = %path.display(), "written SaltXML project");
+
+        Ok(())
+    }
+}
+
+fn write_id_label(w: &mut Writer<BufWriter<File>>, id: &str) -> quick_xml::Result<()> {
+    w.create_element("labels")
+        .with_attributes([
+            ("xsi:type", "saltCore:SElementId"),
+            ("namespace", "salt"),
+            ("name", "id"),
+            ("value", format!("T::{id}").as_str()),
+        ])
+        .write_empty()?;
+
+    Ok(())
+}
+
+fn write_feature_label(
+    w: &mut Writer<BufWriter<File>>,
+    name: &str,
+    value: &str,
+) -> quick_xml::Result<()> {
+    w.create_element("labels")
+        .with_attributes([
+            ("xsi:type", "saltCore:SFeature"),
+            ("namespace", "salt"),
+            ("name", name),
+            ("value", value),
+        ])
+        .write_empty()?;
+
+    Ok(())
+}
+
+fn write_salt_file(
+    path: &Path,
+    write_content: impl FnOnce(&mut Writer<BufWriter<File>>) -> quick_xml::Result<()>,
+) -> anyhow::Result<()> {
+    let mut writer = Writer::new_with_indent(BufWriter::new(File::create(path)?), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    write_content(&mut writer)?;
+
+    writer.into_inner().flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(corpus: &str, document: &str) -> Document {
+        Document {
+            corpus: corpus.into(),
+            document: document.into(),
+            tokens: vec!["der".into(), "man".into()],
+            nonterminals: vec![Some("NP".into())],
+            edges: vec![(0, Child::Token(0)), (0, Child::Token(1))],
+        }
+    }
+
+    #[test]
+    fn writes_documents_of_subcorpora_into_corpus_directory() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut writer = SaltXmlWriter::new(
+            dir.path().to_owned(),
+            "syntax".into(),
+            "syntax".into(),
+            "cat".into(),
+        );
+
+        writer.write(&document("rem", "sub/doc"))?;
+        writer.finish()?;
+
+        let content = fs::read_to_string(dir.path().join("rem").join("sub%2Fdoc.salt"))?;
+        assert!(content.contains("T::salt:/rem/sub%2Fdoc"));
+        assert!(content.contains("T::NP"));
+
+        let project = fs::read_to_string(dir.path().join("saltProject.salt"))?;
+        assert!(project.contains("T::salt:/rem/sub%2Fdoc"));
+        assert!(project.contains("T::sub/doc"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use anyhow::ensure;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{node_name, tree_stats};
+
+/// Per-document sidecar containing the mapping between ANNIS tokens and TTL words, the sentence
+/// boundaries and statistics about the merged tree layer
+#[derive(Serialize)]
+pub(crate) struct Sidecar {
+    pub(crate) corpus: String,
+    pub(crate) document: String,
+    pub(crate) tokens: Vec<Token>,
+    pub(crate) sentences: Vec<Sentence>,
+    pub(crate) tree: TreeStatistics,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Token {
+    pub(crate) annis_node_name: String,
+    pub(crate) iri: String,
+    /// 1-based index of the sentence
+    pub(crate) sentence: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Sentence {
+    /// 1-based index of the sentence
+    pub(crate) index: usize,
+    pub(crate) iri: String,
+    /// 0-based index into [`Sidecar::tokens`] of the first token of the sentence
+    pub(crate) first_token: Option<usize>,
+    /// 0-based index into [`Sidecar::tokens`] of the last token of the sentence
+    pub(crate) last_token: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct TreeStatistics {
+    pub(crate) nonterminals: usize,
+    pub(crate) dominance_edges: usize,
+    /// Maximum number of dominance edges from a nonterminal down to a token
+    pub(crate) max_depth: usize,
+}
+
+/// Writer of the sidecars of all documents to `DIR/CORPUS/DOC.json`, where the corpus and document
+/// names are encoded (see [`node_name::encode`]), so that documents of subcorpora (named
+/// `SUBCORPUS/DOC`) end up in the corpus directory
+pub(crate) struct SidecarWriter {
+    dir: PathBuf,
+}
+
+impl SidecarWriter {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub(crate) fn write(&self, sidecar: &Sidecar) -> anyhow::Result<()> {
+        let corpus = node_name::encode(&sidecar.corpus);
+        ensure!(
+            corpus != "." && corpus != "..",
+            "corpus name {:?} cannot be used as sidecar directory",
+            sidecar.corpus
+        );
+
+        let corpus_dir = self.dir.join(&*corpus);
+        fs::create_dir_all(&corpus_dir)?;
+
+        let path = corpus_dir.join(format!("{}.json", node_name::encode(&sidecar.document)));
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&path)?), sidecar)?;
+
+        info!(path = %path.display(), "written sidecar");
+
+        Ok(())
+    }
+}
+
+/// Computes the maximum depth of the trees given by the `(child, parent)` edges, ignoring cycles
+pub(crate) fn max_depth<N: Copy + Eq + Hash>(edges: &[(N, N)]) -> usize {
+    tree_stats::subtree_stats(edges)
+        .into_values()
+        .map(|stats| stats.depth)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn sidecar(corpus: &str, document: &str) -> Sidecar {
+        Sidecar {
+            corpus: corpus.into(),
+            document: document.into(),
+            tokens: Vec::new(),
+            sentences: Vec::new(),
+            tree: TreeStatistics {
+                nonterminals: 0,
+                dominance_edges: 0,
+                max_depth: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn writes_document_of_subcorpus_into_corpus_dir() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let writer = SidecarWriter::new(dir.path().to_owned());
+
+        writer.write(&sidecar("Bärmann corpus", "sub/doc"))?;
+
+        assert!(dir
+            .path()
+            .join("B%C3%A4rmann%20corpus")
+            .join("sub%2Fdoc.json")
+            .is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_corpus_name_escaping_dir() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let writer = SidecarWriter::new(dir.path().join("sidecars"));
+
+        assert!(writer.write(&sidecar("..", "doc")).is_err());
+        writer.write(&sidecar("../corpus", "doc"))?;
+
+        assert!(dir
+            .path()
+            .join("sidecars")
+            .join("..%2Fcorpus")
+            .join("doc.json")
+            .is_file());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Range;
+
+use super::paula::PaulaWriter;
+use super::saltxml::SaltXmlWriter;
+
+/// Treebank layer of a single document to be exported in addition to the merged corpus
+pub(crate) struct Document {
+    pub(crate) corpus: String,
+    pub(crate) document: String,
+    /// Texts of the tokens in order
+    pub(crate) tokens: Vec<String>,
+    /// Category of each nonterminal, if any
+    pub(crate) nonterminals: Vec<Option<String>>,
+    /// Dominance edges given as index of the parent nonterminal and child
+    pub(crate) edges: Vec<(usize, Child)>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Child {
+    Token(usize),
+    Nonterminal(usize),
+}
+
+impl Document {
+    /// Primary text consisting of the tokens separated by spaces, together with the 0-based
+    /// character ranges of the tokens within the text
+    pub(crate) fn text(&self) -> (String, Vec<Range<usize>>) {
+        let mut text = String::new();
+        let mut text_len = 0;
+        let mut token_ranges = Vec::with_capacity(self.tokens.len());
+
+        for token in &self.tokens {
+            if !text.is_empty() {
+                text.push(' ');
+                text_len += 1;
+            }
+
+            let token_len = token.chars().count();
+            token_ranges.push(text_len..text_len + token_len);
+            text.push_str(token);
+            text_len += token_len;
+        }
+
+        (text, token_ranges)
+    }
+
+    /// Children of each nonterminal
+    pub(crate) fn children(&self) -> Vec<Vec<Child>> {
+        let mut children = vec![Vec::new(); self.nonterminals.len()];
+
+        for &(parent, child) in &self.edges {
+            children[parent].push(child);
+        }
+
+        children
+    }
+}
+
+/// Writer of the treebank layer in one of the additional export formats
+pub(crate) enum TreebankWriter {
+    Paula(PaulaWriter),
+    SaltXml(SaltXmlWriter),
+}
+
+impl TreebankWriter {
+    pub(crate) fn write(&mut self, document: &Document) -> anyhow::Result<()> {
+        match self {
+            TreebankWriter::Paula(writer) => writer.write(document),
+            TreebankWriter::SaltXml(writer) => writer.write(document),
+        }
+    }
+
+    pub(crate) fn finish(self) -> anyhow::Result<()> {
+        match self {
+            TreebankWriter::Paula(_) => Ok(()),
+            TreebankWriter::SaltXml(writer) => writer.finish(),
+        }
+    }
+}
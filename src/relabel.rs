@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::info;
+
+/// Ordered rules for relabeling the categories of nonterminals, read from a TOML file of the form
+///
+/// ```toml
+/// [[rules]]
+/// pattern = "-[0-9]+$"
+/// replacement = ""
+///
+/// [[rules]]
+/// pattern = "^NX$"
+/// replacement = "NP"
+/// ```
+///
+/// where each rule replaces all matches of its regular expression `pattern` with `replacement`
+/// (which may refer to capture groups as `$1` etc.), applied to the result of the previous rule.
+pub(crate) struct Relabeling {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    pattern: Regex,
+    replacement: String,
+}
+
+#[derive(Deserialize)]
+struct RelabelingFile {
+    #[serde(default)]
+    rules: Vec<RuleEntry>,
+}
+
+#[derive(Deserialize)]
+struct RuleEntry {
+    pattern: String,
+    replacement: String,
+}
+
+impl Relabeling {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let relabeling: Self = fs::read_to_string(path)?.parse()?;
+
+        info!(path = %path.display(), rules = relabeling.rules.len(), "read relabeling rules");
+
+        Ok(relabeling)
+    }
+
+    /// Applies all rules in order to the given category
+    pub(crate) fn apply<'a>(&self, category: &'a str) -> Cow<'a, str> {
+        let mut category = Cow::Borrowed(category);
+
+        for rule in &self.rules {
+            if let Cow::Owned(relabeled) = rule.pattern.replace_all(&category, &rule.replacement) {
+                category = Cow::Owned(relabeled);
+            }
+        }
+
+        category
+    }
+}
+
+impl FromStr for Relabeling {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: RelabelingFile = toml::from_str(s)?;
+
+        let rules = file
+            .rules
+            .into_iter()
+            .map(|entry| {
+                Ok(Rule {
+                    pattern: Regex::new(&entry.pattern).map_err(|err| {
+                        anyhow!("invalid relabeling pattern `{}`: {err}", entry.pattern)
+                    })?,
+                    replacement: entry.replacement,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self { rules })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn applies_rules_in_order() -> anyhow::Result<()> {
+        let relabeling = r#"
+            [[rules]]
+            pattern = "-[0-9]+$"
+            replacement = ""
+
+            [[rules]]
+            pattern = "^NX$"
+            replacement = "NP"
+            "#
+        .parse::<Relabeling>()?;
+
+        assert_eq!(relabeling.apply("NX-12"), "NP");
+        assert_eq!(relabeling.apply("NX"), "NP");
+        assert_eq!(relabeling.apply("VP-1"), "VP");
+        assert_eq!(relabeling.apply("NXP"), "NXP");
+
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_all_matches_with_capture_groups() -> anyhow::Result<()> {
+        let relabeling = r#"
+            [[rules]]
+            pattern = "([A-Z]+)_([a-z]+)"
+            replacement = "$2:$1"
+            "#
+        .parse::<Relabeling>()?;
+
+        assert_eq!(relabeling.apply("NP_subj VP_pred"), "subj:NP pred:VP");
+
+        Ok(())
+    }
+
+    #[test]
+    fn borrows_unchanged_categories() -> anyhow::Result<()> {
+        let relabeling = r#"
+            [[rules]]
+            pattern = "^NX$"
+            replacement = "NP"
+            "#
+        .parse::<Relabeling>()?;
+
+        assert!(matches!(relabeling.apply("VP"), Cow::Borrowed("VP")));
+        assert!(matches!(
+            "".parse::<Relabeling>()?.apply("NX"),
+            Cow::Borrowed("NX")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let err = r#"
+            [[rules]]
+            pattern = "(NP"
+            replacement = "NP"
+            "#
+        .parse::<Relabeling>()
+        .err()
+        .map(|err| err.to_string())
+        .unwrap_or_default();
+
+        assert!(err.starts_with("invalid relabeling pattern `(NP`: "));
+    }
+
+    #[test]
+    fn rejects_rules_without_replacement() {
+        assert!(r#"
+            [[rules]]
+            pattern = "^NX$"
+            "#
+        .parse::<Relabeling>()
+        .is_err());
+    }
+
+    #[test]
+    fn from_file_reads_rules() -> anyhow::Result<()> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        file.write_all(b"[[rules]]\npattern = \"^NX$\"\nreplacement = \"NP\"\n")?;
+
+        assert_eq!(Relabeling::from_file(file.path())?.apply("NX"), "NP");
+
+        Ok(())
+    }
+}
@@ -1,37 +1,603 @@
 use std::borrow::Cow;
-use std::sync::LazyLock;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use anyhow::{anyhow, bail, ensure};
+use graphannis_core::graph::{ANNIS_NS, DEFAULT_NS};
 use graphannis_core::types::AnnoKey;
+use itertools::Itertools;
+use serde::Deserialize;
+use tracing::info;
 
 pub(crate) const TOK_ANNO: &str = "tok_anno";
 pub(crate) const ANNOTATION: &str = "annotation";
 
-pub(crate) static ANNO_KEY_INFLECTION: LazyLock<AnnoKey> = LazyLock::new(|| AnnoKey {
-    ns: ANNOTATION.into(),
-    name: "inflection".into(),
-});
-
-pub(crate) static ANNO_KEY_LEMMA: LazyLock<AnnoKey> = LazyLock::new(|| AnnoKey {
-    ns: ANNOTATION.into(),
-    name: "lemma".into(),
-});
-
-pub(crate) static ANNO_KEY_NORM: LazyLock<AnnoKey> = LazyLock::new(|| AnnoKey {
-    ns: ANNOTATION.into(),
-    name: "norm".into(),
-});
-
-pub(crate) static ANNO_KEY_POS: LazyLock<AnnoKey> = LazyLock::new(|| AnnoKey {
-    ns: ANNOTATION.into(),
-    name: "pos".into(),
-});
-
-pub(crate) fn sanitize_anno(anno: Option<&str>) -> Option<Cow<'_, str>> {
-    anno.filter(|&anno| anno != "--").map(str::trim).map(|s| {
-        if s.contains('#') {
-            Cow::Owned(s.replace('#', "-"))
-        } else {
-            Cow::Borrowed(s)
+/// Corpus-specific settings, which differ between the Referenzkorpora (ReM, ReF, ReN etc.)
+#[derive(Debug)]
+pub(crate) struct Profile {
+    /// Name of the segmentation whose nodes are aligned with the TTL words
+    pub(crate) segmentation: String,
+    pub(crate) anno_key_inflection: AnnoKey,
+    pub(crate) anno_key_lemma: AnnoKey,
+    pub(crate) anno_key_norm: AnnoKey,
+    pub(crate) anno_key_pos: AnnoKey,
+    /// Keys compared with the TTL `WORD` instead of `anno_key_norm` in documents without it, tried
+    /// in order
+    pub(crate) anno_keys_norm_fallback: Vec<AnnoKey>,
+    pub(crate) sanitization: Sanitization,
+}
+
+/// Contents of a profile file of the form
+///
+/// ```toml
+/// segmentation = "tok_anno"
+///
+/// [annos]
+/// inflection = "annotation:inflection"
+/// lemma = "annotation:lemma"
+/// norm = "annotation:norm"
+/// pos = "annotation:pos"
+/// norm_fallbacks = ["default_ns:tok_anno", "annis:tok"]
+///
+/// [sanitization]
+/// remove = ["\u200B"]
+/// ```
+///
+/// where missing values are taken from the ReM profile (except that the default `norm_fallbacks`
+/// refer to the given segmentation) and `sanitization` is given as for [`Sanitization`].
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    segmentation: Option<String>,
+    #[serde(default)]
+    annos: ProfileAnnos,
+    sanitization: Option<Sanitization>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileAnnos {
+    inflection: Option<String>,
+    lemma: Option<String>,
+    norm: Option<String>,
+    pos: Option<String>,
+    norm_fallbacks: Option<Vec<String>>,
+}
+
+impl Profile {
+    /// Profile of the Referenzkorpus Mittelhochdeutsch (ReM)
+    pub(crate) fn rem() -> Self {
+        Self::with_anno_keys(ANNOTATION, "inflection")
+    }
+
+    /// Profile of the Referenzkorpus Frühneuhochdeutsch (ReF), whose inflection is annotated as
+    /// `infl`
+    pub(crate) fn ref_() -> Self {
+        Self::with_anno_keys(ANNOTATION, "infl")
+    }
+
+    /// Profile of the Referenzkorpus Mittelniederdeutsch/Niederrheinisch (ReN), whose annotations
+    /// are in the default namespace and whose inflection is annotated as `infl`
+    pub(crate) fn ren() -> Self {
+        Self::with_anno_keys(DEFAULT_NS, "infl")
+    }
+
+    /// Profile with the segmentation `tok_anno`, the annotations `lemma`, `norm`, `pos` and
+    /// `inflection_name` in the namespace `ns` and the default sanitization rules
+    fn with_anno_keys(ns: &str, inflection_name: &str) -> Self {
+        let anno_key = |name: &str| AnnoKey {
+            ns: ns.into(),
+            name: name.into(),
+        };
+
+        Self {
+            segmentation: TOK_ANNO.into(),
+            anno_key_inflection: anno_key(inflection_name),
+            anno_key_lemma: anno_key("lemma"),
+            anno_key_norm: anno_key("norm"),
+            anno_key_pos: anno_key("pos"),
+            anno_keys_norm_fallback: default_norm_fallbacks(TOK_ANNO),
+            sanitization: Sanitization::default(),
+        }
+    }
+
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let profile: Self = fs::read_to_string(path)?.parse()?;
+
+        info!(path = %path.display(), segmentation = profile.segmentation, "read profile");
+
+        Ok(profile)
+    }
+
+    /// Replaces each annotation key of this profile that is not among the `available` keys of the
+    /// input corpora by the only available key of the same name in another namespace, e.g.
+    /// `default_ns:norm` instead of `annotation:norm` in re-exported corpora
+    ///
+    /// Fails if there are several such candidates. Keys without candidates are kept, so that the
+    /// missing annotations are reported during the alignment.
+    pub(crate) fn detect_anno_keys(&mut self, available: &BTreeSet<AnnoKey>) -> anyhow::Result<()> {
+        for (label, anno_key) in [
+            ("inflection", &mut self.anno_key_inflection),
+            ("lemma", &mut self.anno_key_lemma),
+            ("norm", &mut self.anno_key_norm),
+            ("pos", &mut self.anno_key_pos),
+        ] {
+            if available.contains(anno_key) {
+                continue;
+            }
+
+            let candidates = available
+                .iter()
+                .filter(|candidate| candidate.name == anno_key.name)
+                .collect_vec();
+
+            match candidates.as_slice() {
+                [] => {}
+                [candidate] => {
+                    info!(
+                        profile_key = format!("{}:{}", anno_key.ns, anno_key.name),
+                        detected_key = format!("{}:{}", candidate.ns, candidate.name),
+                        "annotation key of profile not found, using detected key"
+                    );
+
+                    *anno_key = (*candidate).clone();
+                }
+                _ => bail!(
+                    "{label} annotation {}:{} of the profile not found, candidates are {}: \
+                     specify one of them in a custom profile (see --profile)",
+                    anno_key.ns,
+                    anno_key.name,
+                    candidates
+                        .iter()
+                        .map(|candidate| format!("{}:{}", candidate.ns, candidate.name))
+                        .join(", ")
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: ProfileFile = toml::from_str(s)?;
+        let mut profile = Self::rem();
+
+        if let Some(segmentation) = file.segmentation {
+            profile.segmentation = segmentation;
+        }
+
+        for (anno_key, value) in [
+            (&mut profile.anno_key_inflection, file.annos.inflection),
+            (&mut profile.anno_key_lemma, file.annos.lemma),
+            (&mut profile.anno_key_norm, file.annos.norm),
+            (&mut profile.anno_key_pos, file.annos.pos),
+        ] {
+            if let Some(value) = value {
+                *anno_key = parse_anno_key(&value)?;
+            }
+        }
+
+        profile.anno_keys_norm_fallback = match file.annos.norm_fallbacks {
+            Some(norm_fallbacks) => norm_fallbacks
+                .iter()
+                .map(|value| parse_anno_key(value))
+                .collect::<anyhow::Result<_>>()?,
+            None => default_norm_fallbacks(&profile.segmentation),
+        };
+
+        if let Some(sanitization) = file.sanitization {
+            profile.sanitization = sanitization;
+        }
+
+        Ok(profile)
+    }
+}
+
+/// Source of the profile, given as `rem`, `ref`, `ren` or `custom=FILE`
+#[derive(Clone, Debug)]
+pub(crate) enum ProfileSource {
+    Rem,
+    Ref,
+    Ren,
+    Custom(PathBuf),
+}
+
+impl FromStr for ProfileSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            None if s == "rem" => Ok(Self::Rem),
+            None if s == "ref" => Ok(Self::Ref),
+            None if s == "ren" => Ok(Self::Ren),
+            Some(("custom", path)) if !path.is_empty() => Ok(Self::Custom(path.into())),
+            _ => bail!("profile must be given as `rem`, `ref`, `ren` or `custom=FILE`"),
+        }
+    }
+}
+
+impl ProfileSource {
+    pub(crate) fn load(&self) -> anyhow::Result<Profile> {
+        match self {
+            Self::Rem => Ok(Profile::rem()),
+            Self::Ref => Ok(Profile::ref_()),
+            Self::Ren => Ok(Profile::ren()),
+            Self::Custom(path) => Profile::from_file(path),
+        }
+    }
+
+    /// Name of the profile, i.e. `rem`, `ref`, `ren` or the file stem of the custom profile
+    pub(crate) fn name(&self) -> Cow<'_, str> {
+        match self {
+            Self::Rem => Cow::Borrowed("rem"),
+            Self::Ref => Cow::Borrowed("ref"),
+            Self::Ren => Cow::Borrowed("ren"),
+            Self::Custom(path) => path
+                .file_stem()
+                .map_or(Cow::Borrowed("custom"), |stem| stem.to_string_lossy()),
         }
+    }
+}
+
+/// Keys compared with the TTL `WORD` in documents without normalized forms: the value of the
+/// segmentation node itself and then the token it covers
+fn default_norm_fallbacks(segmentation: &str) -> Vec<AnnoKey> {
+    vec![
+        AnnoKey {
+            ns: DEFAULT_NS.into(),
+            name: segmentation.into(),
+        },
+        AnnoKey {
+            ns: ANNIS_NS.into(),
+            name: "tok".into(),
+        },
+    ]
+}
+
+/// Parses an annotation key given as `NS:NAME`
+fn parse_anno_key(s: &str) -> anyhow::Result<AnnoKey> {
+    let (ns, name) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("annotation key must be given as `NS:NAME`, got `{s}`"))?;
+
+    ensure!(!name.is_empty(), "annotation name must not be empty");
+
+    Ok(AnnoKey {
+        ns: ns.into(),
+        name: name.into(),
     })
 }
+
+/// Characters trimmed from the ends of annotation values in addition to whitespace: zero-width
+/// (non-)joiners only matter between other characters
+const TRIMMED_CHARS: [char; 2] = ['\u{200C}', '\u{200D}'];
+
+/// Rules for sanitizing ANNIS annotation values before comparing them to TTL values, read from a
+/// TOML file of the form
+///
+/// ```toml
+/// remove = ["\u200B", "\u2060", "\uFEFF"]
+///
+/// [replace]
+/// "#" = "-"
+/// ```
+///
+/// where `remove` lists characters removed anywhere and `replace` maps characters to their
+/// replacements. The values shown are the defaults.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Sanitization {
+    remove: Vec<char>,
+    replace: BTreeMap<char, String>,
+}
+
+impl Default for Sanitization {
+    fn default() -> Self {
+        Self {
+            remove: vec!['\u{200B}', '\u{2060}', '\u{FEFF}'],
+            replace: BTreeMap::from([('#', "-".into())]),
+        }
+    }
+}
+
+impl Sanitization {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let sanitization: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        info!(
+            path = %path.display(),
+            remove = sanitization.remove.len(),
+            replace = sanitization.replace.len(),
+            "read sanitization rules"
+        );
+
+        Ok(sanitization)
+    }
+
+    /// Sanitizes an ANNIS annotation value, where `--` stands for a missing value, whitespace is
+    /// normalized (see [`normalize_whitespace`]) and the configured characters are removed or
+    /// replaced
+    pub(crate) fn sanitize<'a>(&self, anno: Option<&'a str>) -> Option<Cow<'a, str>> {
+        let anno = anno.filter(|&anno| anno != "--")?;
+
+        let needs_sanitizing = |c: char| self.remove.contains(&c) || self.replace.contains_key(&c);
+
+        let anno = match normalize_whitespace(anno) {
+            Cow::Borrowed(s) if !s.contains(needs_sanitizing) => Cow::Borrowed(s),
+            s => {
+                let mut sanitized = String::with_capacity(s.len());
+
+                for c in s.chars().filter(|c| !self.remove.contains(c)) {
+                    match self.replace.get(&c) {
+                        Some(replacement) => sanitized.push_str(replacement),
+                        None => sanitized.push(c),
+                    }
+                }
+
+                Cow::Owned(sanitized)
+            }
+        };
+
+        Some(anno)
+    }
+}
+
+/// Trims Unicode whitespace (e.g. non-breaking spaces) and zero-width (non-)joiners from both ends
+/// and collapses each inner run of Unicode whitespace into a single space
+pub(crate) fn normalize_whitespace(s: &str) -> Cow<'_, str> {
+    let s = s.trim_matches(|c: char| c.is_whitespace() || TRIMMED_CHARS.contains(&c));
+
+    let is_normalized = s
+        .chars()
+        .tuple_windows()
+        .all(|(a, b)| !b.is_whitespace() || (b == ' ' && !a.is_whitespace()));
+
+    if is_normalized {
+        return Cow::Borrowed(s);
+    }
+
+    Cow::Owned(s.split_whitespace().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn anno_key(ns: &str, name: &str) -> AnnoKey {
+        AnnoKey {
+            ns: ns.into(),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn sanitize_treats_double_dash_as_missing() {
+        let sanitization = Sanitization::default();
+
+        assert_eq!(sanitization.sanitize(None), None);
+        assert_eq!(sanitization.sanitize(Some("--")), None);
+        assert_eq!(sanitization.sanitize(Some("---")).unwrap(), "---");
+    }
+
+    #[test]
+    fn sanitize_removes_and_replaces_characters() {
+        let sanitization = Sanitization::default();
+
+        assert_eq!(
+            sanitization.sanitize(Some("a\u{200B}b\u{FEFF}#c")).unwrap(),
+            "ab-c"
+        );
+    }
+
+    #[test]
+    fn sanitize_applies_configured_rules() {
+        let sanitization: Sanitization = toml::from_str(
+            r#"
+            remove = ["x"]
+            replace = { "ſ" = "s" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(sanitization.sanitize(Some("xaſx#")).unwrap(), "as#");
+    }
+
+    #[test]
+    fn sanitize_borrows_clean_values() {
+        let sanitization = Sanitization::default();
+
+        assert!(matches!(
+            sanitization.sanitize(Some("vrouwe")),
+            Some(Cow::Borrowed("vrouwe"))
+        ));
+    }
+
+    #[test]
+    fn sanitize_normalizes_whitespace_before_replacing() {
+        let sanitization = Sanitization::default();
+
+        assert_eq!(
+            sanitization
+                .sanitize(Some("\u{00A0}a \u{00A0} #\u{200D}"))
+                .unwrap(),
+            "a -"
+        );
+    }
+
+    #[test]
+    fn normalize_whitespace_trims_unicode_whitespace_and_joiners() {
+        assert_eq!(normalize_whitespace("\u{00A0}\u{200D}ab\u{200C} \n"), "ab");
+    }
+
+    #[test]
+    fn normalize_whitespace_keeps_inner_joiners() {
+        assert_eq!(normalize_whitespace("a\u{200D}b"), "a\u{200D}b");
+    }
+
+    #[test]
+    fn normalize_whitespace_collapses_inner_whitespace() {
+        assert_eq!(normalize_whitespace("a\u{00A0}b \t c"), "a b c");
+        assert_eq!(normalize_whitespace("a  b"), "a b");
+    }
+
+    #[test]
+    fn normalize_whitespace_borrows_normalized_values() {
+        assert!(matches!(
+            normalize_whitespace(" a b "),
+            Cow::Borrowed("a b")
+        ));
+    }
+
+    #[test]
+    fn profile_source_from_str_accepts_named_profiles() {
+        assert!(matches!("rem".parse(), Ok(ProfileSource::Rem)));
+        assert!(matches!("ref".parse(), Ok(ProfileSource::Ref)));
+        assert!(matches!("ren".parse(), Ok(ProfileSource::Ren)));
+    }
+
+    #[test]
+    fn profile_source_from_str_accepts_custom_profile() {
+        let source: ProfileSource = "custom=profiles/redi.toml".parse().unwrap();
+
+        assert!(
+            matches!(&source, ProfileSource::Custom(path) if path == Path::new("profiles/redi.toml"))
+        );
+        assert_eq!(source.name(), "redi");
+    }
+
+    #[test]
+    fn profile_source_from_str_rejects_invalid_profiles() {
+        for s in ["", "REM", "redi", "custom", "custom=", "rem=FILE"] {
+            let err = s.parse::<ProfileSource>().unwrap_err();
+
+            assert_eq!(
+                err.to_string(),
+                "profile must be given as `rem`, `ref`, `ren` or `custom=FILE`"
+            );
+        }
+    }
+
+    #[test]
+    fn named_profiles_differ_in_anno_keys() {
+        assert_eq!(
+            Profile::rem().anno_key_inflection,
+            anno_key(ANNOTATION, "inflection")
+        );
+        assert_eq!(
+            Profile::ref_().anno_key_inflection,
+            anno_key(ANNOTATION, "infl")
+        );
+        assert_eq!(
+            Profile::ren().anno_key_inflection,
+            anno_key(DEFAULT_NS, "infl")
+        );
+        assert_eq!(Profile::ren().anno_key_norm, anno_key(DEFAULT_NS, "norm"));
+
+        for profile in [Profile::rem(), Profile::ref_(), Profile::ren()] {
+            assert_eq!(profile.segmentation, TOK_ANNO);
+        }
+    }
+
+    #[test]
+    fn parse_anno_key_splits_at_first_colon() {
+        assert_eq!(
+            parse_anno_key("annotation:lemma").unwrap(),
+            anno_key("annotation", "lemma")
+        );
+        assert_eq!(parse_anno_key("ns:a:b").unwrap(), anno_key("ns", "a:b"));
+        assert_eq!(parse_anno_key(":tok").unwrap(), anno_key("", "tok"));
+    }
+
+    #[test]
+    fn parse_anno_key_rejects_invalid_keys() {
+        assert_eq!(
+            parse_anno_key("lemma").unwrap_err().to_string(),
+            "annotation key must be given as `NS:NAME`, got `lemma`"
+        );
+        assert_eq!(
+            parse_anno_key("annotation:").unwrap_err().to_string(),
+            "annotation name must not be empty"
+        );
+    }
+
+    #[test]
+    fn parse_takes_missing_values_from_rem() {
+        let profile: Profile = r#"
+            [annos]
+            lemma = "default_ns:lemma"
+            "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(profile.segmentation, TOK_ANNO);
+        assert_eq!(profile.anno_key_lemma, anno_key(DEFAULT_NS, "lemma"));
+        assert_eq!(profile.anno_key_norm, anno_key(ANNOTATION, "norm"));
+        assert_eq!(
+            profile.anno_keys_norm_fallback,
+            default_norm_fallbacks(TOK_ANNO)
+        );
+    }
+
+    #[test]
+    fn parse_derives_norm_fallbacks_from_segmentation() {
+        let profile: Profile = r#"segmentation = "tok_dipl""#.parse().unwrap();
+
+        assert_eq!(profile.segmentation, "tok_dipl");
+        assert_eq!(
+            profile.anno_keys_norm_fallback,
+            [anno_key(DEFAULT_NS, "tok_dipl"), anno_key(ANNIS_NS, "tok")]
+        );
+    }
+
+    #[test]
+    fn from_file_reads_all_values() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(
+            br##"
+            segmentation = "tok_norm"
+
+            [annos]
+            inflection = "a:infl"
+            lemma = "a:lemma"
+            norm = "a:norm"
+            pos = "a:pos"
+            norm_fallbacks = ["annis:tok"]
+
+            [sanitization]
+            remove = []
+            "##,
+        )
+        .unwrap();
+        let profile = Profile::from_file(file.path()).unwrap();
+
+        assert_eq!(profile.anno_key_inflection, anno_key("a", "infl"));
+        assert_eq!(profile.anno_key_lemma, anno_key("a", "lemma"));
+        assert_eq!(profile.anno_key_norm, anno_key("a", "norm"));
+        assert_eq!(profile.anno_key_pos, anno_key("a", "pos"));
+        assert_eq!(profile.anno_keys_norm_fallback, [anno_key(ANNIS_NS, "tok")]);
+        assert_eq!(
+            profile.sanitization.sanitize(Some("a\u{200B}#b")).unwrap(),
+            "a\u{200B}-b"
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unknown_fields_and_invalid_keys() {
+        assert!(r#"segmentaton = "tok_anno""#.parse::<Profile>().is_err());
+        assert_eq!(
+            "[annos]\nlemma = \"lemma\""
+                .parse::<Profile>()
+                .unwrap_err()
+                .to_string(),
+            "annotation key must be given as `NS:NAME`, got `lemma`"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(feature = "remote")]
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+#[cfg(not(feature = "remote"))]
+use anyhow::bail;
+#[cfg(feature = "remote")]
+use anyhow::{anyhow, ensure};
+#[cfg(feature = "remote")]
+use tracing::info;
+
+const HTTP_PREFIXES: [&str; 2] = ["http://", "https://"];
+const S3_PREFIX: &str = "s3://";
+/// Maximum size of an object uploaded to S3 in a single `PUT` request
+#[cfg(feature = "remote")]
+const S3_MAX_PUT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Whether the given path is a URL of a remote location rather than a local path
+pub(crate) fn is_remote(path: &Path) -> bool {
+    path.to_str().is_some_and(|s| {
+        s.starts_with(S3_PREFIX) || HTTP_PREFIXES.iter().any(|prefix| s.starts_with(prefix))
+    })
+}
+
+/// Downloads the file at the given remote location, streaming it into `writer`
+#[cfg(feature = "remote")]
+pub(crate) fn download(url: &Path, writer: &mut impl Write) -> anyhow::Result<()> {
+    let url = http_url(url, Method::Get)?;
+
+    info!(url = redact(&url), "downloading");
+
+    let response = ureq::get(&url).call()?;
+    let total = response
+        .header("Content-Length")
+        .and_then(|len| len.parse().ok());
+
+    std::io::copy(
+        &mut ProgressReader::new(response.into_reader(), total),
+        writer,
+    )?;
+
+    Ok(())
+}
+
+/// Uploads the local file at `path` to the given remote location in a single `PUT` request
+///
+/// Fails without uploading anything if the file exceeds the size S3 accepts in a single request,
+/// since multipart uploads are not supported.
+#[cfg(feature = "remote")]
+pub(crate) fn upload(path: &Path, url: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::open(path)?;
+    let total = file.metadata()?.len();
+
+    ensure!(
+        total <= S3_MAX_PUT_SIZE || !url.to_str().is_some_and(|url| url.starts_with(S3_PREFIX)),
+        "cannot upload {} MB to {}: S3 accepts at most 5 GB in a single upload, write the output \
+         to a local path instead and upload it with a tool supporting multipart uploads (e.g. \
+         `aws s3 cp`)",
+        total / (1024 * 1024),
+        url.display()
+    );
+
+    let url = http_url(url, Method::Put)?;
+
+    info!(url = redact(&url), "uploading");
+
+    ureq::put(&url)
+        .set("Content-Length", &total.to_string())
+        .send(ProgressReader::new(file, Some(total)))?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "remote"))]
+pub(crate) fn download(url: &Path, _writer: &mut impl Write) -> anyhow::Result<()> {
+    bail!(
+        "cannot download {}: remote locations require building with the `remote` feature",
+        url.display()
+    );
+}
+
+#[cfg(not(feature = "remote"))]
+pub(crate) fn upload(_path: &Path, url: &Path) -> anyhow::Result<()> {
+    bail!(
+        "cannot upload to {}: remote locations require building with the `remote` feature",
+        url.display()
+    );
+}
+
+#[cfg(feature = "remote")]
+#[derive(Clone, Copy)]
+enum Method {
+    Get,
+    Put,
+}
+
+/// Turns the given URL into an HTTP(S) URL, presigning it if it is an S3 URL
+///
+/// S3 credentials are taken from the usual AWS environment variables or profile files, the region
+/// from `AWS_REGION` and a custom endpoint (e.g. for MinIO) from `AWS_ENDPOINT_URL`.
+#[cfg(feature = "remote")]
+fn http_url(url: &Path, method: Method) -> anyhow::Result<String> {
+    /// Expiry of presigned S3 URLs, must be long enough to transfer large corpora
+    const PRESIGN_EXPIRY_SECS: u32 = 24 * 60 * 60;
+
+    let url = url
+        .to_str()
+        .ok_or_else(|| anyhow!("URL {} is not valid UTF-8", url.display()))?;
+
+    let Some(bucket_and_key) = url.strip_prefix(S3_PREFIX) else {
+        return Ok(url.into());
+    };
+
+    let (bucket_name, key) = bucket_and_key
+        .split_once('/')
+        .ok_or_else(|| anyhow!("S3 URL {url} must be of the form s3://BUCKET/KEY"))?;
+
+    let region_name = std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".into());
+
+    let region = match std::env::var("AWS_ENDPOINT_URL") {
+        Ok(endpoint) => s3::Region::Custom {
+            region: region_name,
+            endpoint,
+        },
+        Err(_) => region_name.parse()?,
+    };
+
+    let bucket = s3::Bucket::new(bucket_name, region, s3::creds::Credentials::default()?)?;
+
+    Ok(match method {
+        Method::Get => bucket.presign_get(key, PRESIGN_EXPIRY_SECS, None)?,
+        Method::Put => bucket.presign_put(key, PRESIGN_EXPIRY_SECS, None, None)?,
+    })
+}
+
+/// Strips the query string, which may contain presigning credentials, from the given URL
+#[cfg(feature = "remote")]
+fn redact(url: &str) -> &str {
+    url.split_once('?').map_or(url, |(url, _)| url)
+}
+
+/// Reader that logs the progress of a transfer
+#[cfg(feature = "remote")]
+struct ProgressReader<R> {
+    inner: R,
+    total: Option<u64>,
+    transferred: u64,
+    next_report: u64,
+}
+
+#[cfg(feature = "remote")]
+impl<R> ProgressReader<R> {
+    const REPORT_INTERVAL: u64 = 64 * 1024 * 1024;
+
+    fn new(inner: R, total: Option<u64>) -> Self {
+        Self {
+            inner,
+            total,
+            transferred: 0,
+            next_report: Self::REPORT_INTERVAL,
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.transferred += len as u64;
+
+        if self.transferred >= self.next_report || (len == 0 && self.transferred > 0) {
+            const MB: u64 = 1024 * 1024;
+
+            match self.total {
+                Some(total) => info!(
+                    transferred_mb = self.transferred / MB,
+                    total_mb = total / MB,
+                    "transfer progress"
+                ),
+                None => info!(transferred_mb = self.transferred / MB, "transfer progress"),
+            }
+
+            self.next_report = self.transferred + Self::REPORT_INTERVAL;
+        }
+
+        Ok(len)
+    }
+}
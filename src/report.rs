@@ -0,0 +1,230 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::coverage::percentage;
+use crate::warnings::Warnings;
+
+/// Template of the HTML report, with the placeholders `{{title}}`, `{{meta}}` and `{{body}}`
+const TEMPLATE: &str = include_str!("report.html");
+
+/// Summary of a conversion written as a single-file HTML report for curators, see `--html-report`
+#[derive(Default)]
+pub(crate) struct HtmlReport {
+    corpora: Vec<CorpusReport>,
+}
+
+struct CorpusReport {
+    name: String,
+    documents: Vec<DocumentReport>,
+    /// Path of the alignment artifact of the corpus, if written
+    alignment_path: Option<PathBuf>,
+}
+
+struct DocumentReport {
+    name: String,
+    /// Numbers of sentences and words of the TTL file, `None` if the document was skipped
+    tree: Option<(usize, usize)>,
+}
+
+impl HtmlReport {
+    pub(crate) fn begin_corpus(&mut self, name: &str) {
+        self.corpora.push(CorpusReport {
+            name: name.into(),
+            documents: Vec::new(),
+            alignment_path: None,
+        });
+    }
+
+    /// Records a document of the current corpus that got treebank data from a TTL file with the
+    /// given numbers of sentences and words
+    pub(crate) fn document_converted(&mut self, name: &str, sentences: usize, words: usize) {
+        self.push_document(name, Some((sentences, words)));
+    }
+
+    /// Records a document of the current corpus without TTL file
+    pub(crate) fn document_skipped(&mut self, name: &str) {
+        self.push_document(name, None);
+    }
+
+    /// Records the path of the alignment artifact of the current corpus
+    pub(crate) fn alignment_written(&mut self, path: PathBuf) {
+        if let Some(corpus) = self.corpora.last_mut() {
+            corpus.alignment_path = Some(path);
+        }
+    }
+
+    fn push_document(&mut self, name: &str, tree: Option<(usize, usize)>) {
+        if let Some(corpus) = self.corpora.last_mut() {
+            corpus.documents.push(DocumentReport {
+                name: name.into(),
+                tree,
+            });
+        }
+    }
+
+    /// Writes the report to `path`, including all warnings collected so far
+    pub(crate) fn write(&self, path: &Path, warnings: &Warnings) -> anyhow::Result<()> {
+        let mut body = String::new();
+
+        self.write_coverage(&mut body)?;
+        write_warnings(&mut body, warnings)?;
+        self.write_documents(&mut body)?;
+
+        let title = format!("{} report", env!("CARGO_PKG_NAME"));
+        let meta = format!(
+            "{} {}, generated {}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            OffsetDateTime::now_utc().date()
+        );
+
+        let html = TEMPLATE
+            .replace("{{title}}", &escape(&title))
+            .replace("{{meta}}", &escape(&meta))
+            .replace("{{body}}", &body);
+
+        fs::write(path, html)?;
+
+        info!(path = %path.display(), "written HTML report");
+
+        Ok(())
+    }
+
+    fn write_coverage(&self, w: &mut String) -> anyhow::Result<()> {
+        writeln!(w, "<h2>Coverage</h2>")?;
+        writeln!(
+            w,
+            "<table><tr><th>Corpus</th><th>Documents</th><th>With tree</th><th>Skipped</th>\
+             <th>Sentences</th><th>Tokens</th></tr>"
+        )?;
+
+        for corpus in &self.corpora {
+            let converted: Vec<_> = corpus.documents.iter().filter_map(|doc| doc.tree).collect();
+            let converted_count = converted.len();
+            let sentences: usize = converted.iter().map(|&(sentences, _)| sentences).sum();
+            let words: usize = converted.iter().map(|&(_, words)| words).sum();
+
+            writeln!(
+                w,
+                "<tr><td><a href=\"#corpus-{name}\">{name}</a></td><td class=\"number\">{}</td>\
+                 <td class=\"number\">{converted_count} ({:.1}%)</td>\
+                 <td class=\"number\">{}</td><td class=\"number\">{sentences}</td>\
+                 <td class=\"number\">{words}</td></tr>",
+                corpus.documents.len(),
+                percentage(converted_count, corpus.documents.len()),
+                corpus.documents.len() - converted_count,
+                name = escape(&corpus.name),
+            )?;
+        }
+
+        writeln!(w, "</table>")?;
+
+        Ok(())
+    }
+
+    fn write_documents(&self, w: &mut String) -> anyhow::Result<()> {
+        for corpus in &self.corpora {
+            let name = escape(&corpus.name);
+
+            writeln!(w, "<h2 id=\"corpus-{name}\">Corpus {name}</h2>")?;
+
+            if let Some(alignment_path) = &corpus.alignment_path {
+                writeln!(
+                    w,
+                    "<p>Alignment details per document: <a href=\"{}\">{}</a></p>",
+                    escape(&file_url(alignment_path)),
+                    escape(&alignment_path.display().to_string())
+                )?;
+            }
+
+            writeln!(
+                w,
+                "<table><tr><th>Document</th><th>Status</th><th>Sentences</th><th>Tokens</th>\
+                 </tr>"
+            )?;
+
+            for doc in &corpus.documents {
+                match doc.tree {
+                    Some((sentences, words)) => writeln!(
+                        w,
+                        "<tr><td>{}</td><td>converted</td><td class=\"number\">{sentences}</td>\
+                         <td class=\"number\">{words}</td></tr>",
+                        escape(&doc.name)
+                    )?,
+                    None => writeln!(
+                        w,
+                        "<tr class=\"skipped\"><td>{}</td><td>skipped (no TTL file)</td><td></td>\
+                         <td></td></tr>",
+                        escape(&doc.name)
+                    )?,
+                }
+            }
+
+            writeln!(w, "</table>")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_warnings(w: &mut String, warnings: &Warnings) -> anyhow::Result<()> {
+    let entries = warnings.entries();
+
+    writeln!(w, "<h2>Warnings</h2>")?;
+
+    if entries.is_empty() {
+        writeln!(w, "<p>No warnings.</p>")?;
+        return Ok(());
+    }
+
+    for (category, key, details) in entries {
+        writeln!(
+            w,
+            "<details><summary>{}: {} ({})</summary><ul>",
+            escape(category),
+            escape(&key),
+            details.len()
+        )?;
+
+        for detail in details {
+            writeln!(w, "<li>{}</li>", escape(&detail))?;
+        }
+
+        writeln!(w, "</ul></details>")?;
+    }
+
+    Ok(())
+}
+
+/// URL of a local file, so that the report can link to artifacts when opened from disk
+fn file_url(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    if path.starts_with('/') {
+        format!("file://{path}")
+    } else {
+        format!("file:///{path}")
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
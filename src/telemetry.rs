@@ -0,0 +1,90 @@
+//! Export of the tracing spans of a conversion to an OpenTelemetry collector via OTLP
+
+#[cfg(not(feature = "otlp"))]
+use anyhow::bail;
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otlp")]
+use opentelemetry::KeyValue;
+#[cfg(feature = "otlp")]
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::Resource;
+#[cfg(feature = "otlp")]
+use tracing::Subscriber;
+#[cfg(feature = "otlp")]
+use tracing_subscriber::filter::{LevelFilter, Targets};
+#[cfg(not(feature = "otlp"))]
+use tracing_subscriber::layer::Identity;
+#[cfg(feature = "otlp")]
+use tracing_subscriber::registry::LookupSpan;
+#[cfg(feature = "otlp")]
+use tracing_subscriber::Layer;
+
+/// Exporter of spans, which must be shut down at the end to flush pending spans
+#[cfg(feature = "otlp")]
+pub(crate) struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+#[cfg(feature = "otlp")]
+impl Telemetry {
+    /// Creates an exporter sending spans to the given OTLP/HTTP endpoint together with a layer
+    /// recording the spans of this tool
+    ///
+    /// The spans are exported in batches by a background thread, so that the conversion doesn't
+    /// wait for the endpoint.
+    pub(crate) fn new<S>(endpoint: &str) -> anyhow::Result<(Self, impl Layer<S>)>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(env!("CARGO_PKG_NAME"))
+                    .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+                    .build(),
+            )
+            .build();
+
+        let layer = tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+            .with_filter(
+                Targets::new()
+                    .with_target(env!("CARGO_CRATE_NAME"), LevelFilter::INFO)
+                    .with_target(crate::SUMMARY_TARGET, LevelFilter::INFO),
+            );
+
+        Ok((Self { provider }, layer))
+    }
+
+    /// Exports the pending spans and shuts down the background thread
+    pub(crate) fn shutdown(self) -> anyhow::Result<()> {
+        self.provider.shutdown()?;
+
+        Ok(())
+    }
+}
+
+/// Exporter of spans, which cannot be created without the `otlp` feature
+#[cfg(not(feature = "otlp"))]
+pub(crate) enum Telemetry {}
+
+#[cfg(not(feature = "otlp"))]
+impl Telemetry {
+    pub(crate) fn new(_endpoint: &str) -> anyhow::Result<(Self, Identity)> {
+        bail!("exporting traces requires building with the `otlp` feature");
+    }
+
+    pub(crate) fn shutdown(self) -> anyhow::Result<()> {
+        match self {}
+    }
+}
@@ -0,0 +1,588 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::thread;
+
+use anyhow::{anyhow, ensure};
+use clap::ValueEnum;
+use itertools::Itertools;
+
+use crate::inbound::ttl;
+use crate::relabel::Relabeling;
+
+/// TTL edge given as `(child, parent)`
+pub(crate) type Edge<'a> = (ttl::Node<'a>, ttl::Node<'a>);
+
+/// Trees to be merged into ANNIS, either of a single sentence or of a whole document
+#[derive(Default)]
+pub(crate) struct Tree<'a> {
+    /// Edges in the order in which they are to be added
+    pub(crate) edges: Vec<Edge<'a>>,
+    /// Categories of the nonterminals whose categories differ from their `CAT` annotation
+    pub(crate) categories: HashMap<&'a ttl::NodeName, String>,
+    /// Number of nonterminals merged into their parents when collapsing unary chains
+    pub(crate) collapsed: usize,
+}
+
+impl<'a> Tree<'a> {
+    /// Category of the given nonterminal
+    pub(crate) fn category(&self, node: ttl::Node<'a>) -> Option<&str> {
+        match self.categories.get(node.node_name()) {
+            Some(category) => Some(category),
+            None => node.anno(ttl::AnnoKey::Cat),
+        }
+    }
+
+    /// Fails if the path from any node up to its root consists of more than `max_depth` edges,
+    /// reporting the names of the nodes along such a path, which also catches cycles
+    pub(crate) fn check_depth(&self, max_depth: usize) -> anyhow::Result<()> {
+        let parents: HashMap<&ttl::NodeName, &ttl::NodeName> = self
+            .edges
+            .iter()
+            .map(|(child, parent)| (child.node_name(), parent.node_name()))
+            .collect();
+
+        // number of edges from each node up to its root, determined once per node
+        let mut heights: HashMap<&ttl::NodeName, usize> = HashMap::new();
+
+        for &start in parents.keys() {
+            // path from `start` up to a root or the first node whose height is already known
+            let mut path = vec![start];
+            let mut top_height = 0;
+
+            while path.len() <= max_depth + 1 {
+                let node = path[path.len() - 1];
+
+                if let Some(&height) = heights.get(node) {
+                    top_height = height;
+                    break;
+                }
+
+                match parents.get(node) {
+                    Some(&parent) => path.push(parent),
+                    None => break,
+                }
+            }
+
+            let height = path.len() - 1 + top_height;
+
+            ensure!(
+                height <= max_depth,
+                "tree exceeds the maximum depth of {max_depth} edges along the path {}",
+                iter::successors(Some(start), |node| parents.get(node).copied())
+                    .take(max_depth + 2)
+                    .join(" -> ")
+            );
+
+            for (i, node) in path.into_iter().enumerate() {
+                heights.insert(node, height - i);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Nonterminals whose yields, i.e. the words they dominate, do not form a contiguous range of
+    /// the given word positions, in the order of their first occurrence as parents
+    pub(crate) fn discontinuous_nodes(
+        &self,
+        word_positions: &HashMap<&ttl::NodeName, usize>,
+    ) -> Vec<ttl::Node<'a>> {
+        let parents: HashMap<&ttl::NodeName, ttl::Node<'a>> = self
+            .edges
+            .iter()
+            .map(|&(child, parent)| (child.node_name(), parent))
+            .collect();
+
+        // minimum and maximum position and number of the words dominated by each nonterminal
+        let mut spans: HashMap<&ttl::NodeName, (usize, usize, usize)> = HashMap::new();
+
+        for (&word, &position) in word_positions {
+            // guard against cycles, which are not rejected unless `--max-edge-depth` is given
+            let mut visited = HashSet::new();
+            let mut node = word;
+
+            while let Some(parent) = parents.get(node) {
+                if !visited.insert(parent.node_name()) {
+                    break;
+                }
+
+                let (min, max, count) = spans
+                    .entry(parent.node_name())
+                    .or_insert((position, position, 0));
+                *min = (*min).min(position);
+                *max = (*max).max(position);
+                *count += 1;
+
+                node = parent.node_name();
+            }
+        }
+
+        self.edges
+            .iter()
+            .map(|&(_, parent)| parent)
+            .unique_by(|parent| parent.node_name())
+            .filter(|parent| {
+                spans
+                    .get(parent.node_name())
+                    .is_some_and(|&(min, max, count)| max - min + 1 > count)
+            })
+            .collect()
+    }
+
+    fn append(&mut self, other: Tree<'a>) {
+        self.edges.extend(other.edges);
+        self.categories.extend(other.categories);
+        self.collapsed += other.collapsed;
+    }
+}
+
+/// Strategy for converting the TTL trees of a sentence into the trees merged into ANNIS, which
+/// implements the conventions of a treebank, e.g. how sentence roots are handled
+pub(crate) trait TreeBuilder: Sync {
+    /// Determines the tree of a single sentence, given all TTL edges of the sentence
+    fn sentence_tree<'a>(&self, edges: &[Edge<'a>]) -> Tree<'a>;
+}
+
+/// Treebank convention, see [`TreeBuilder`]
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum Convention {
+    /// Constituency trees below the sentence nodes, which are left out
+    Constituency,
+    /// Like `constituency`, but with the sentence node as the root of each tree, e.g. for flat
+    /// trees of topological fields
+    Rooted,
+}
+
+impl Convention {
+    /// Creates the tree builder for this convention, where empty elements (see
+    /// [`ttl::Node::is_empty_element`]) are kept if `keep_empty_elements` is `true`, categories are
+    /// relabeled if `relabeling` is specified and then unary chains are collapsed if
+    /// `collapse_unary` (the separator of the categories) is specified
+    pub(crate) fn builder(
+        self,
+        keep_empty_elements: bool,
+        relabeling: Option<Relabeling>,
+        collapse_unary: Option<String>,
+    ) -> Box<dyn TreeBuilder> {
+        let mut builder: Box<dyn TreeBuilder> = match self {
+            Convention::Constituency => Box::new(ReachableFromWords {
+                keep_sentence_roots: false,
+                keep_empty_elements,
+            }),
+            Convention::Rooted => Box::new(ReachableFromWords {
+                keep_sentence_roots: true,
+                keep_empty_elements,
+            }),
+        };
+
+        if let Some(relabeling) = relabeling {
+            builder = Box::new(Relabel {
+                inner: builder,
+                relabeling,
+            });
+        }
+
+        if let Some(separator) = collapse_unary {
+            builder = Box::new(CollapseUnary {
+                inner: builder,
+                separator,
+            });
+        }
+
+        builder
+    }
+}
+
+/// Builds the trees from all edges reachable from words (and empty elements, if kept)
+struct ReachableFromWords {
+    /// Whether to keep the edges to the sentence roots, which have no `CAT` annotation
+    keep_sentence_roots: bool,
+    /// Whether to keep the edges to empty elements, which are dropped otherwise
+    keep_empty_elements: bool,
+}
+
+impl TreeBuilder for ReachableFromWords {
+    fn sentence_tree<'a>(&self, edges: &[Edge<'a>]) -> Tree<'a> {
+        let mut reached_node_names: HashSet<&ttl::NodeName> = HashSet::new();
+        let mut tree_edges = Vec::new();
+        let mut remaining_edges = edges.to_vec();
+
+        loop {
+            let added_count = tree_edges.len();
+            let mut next_remaining_edges = Vec::with_capacity(remaining_edges.len());
+
+            for (child, parent) in remaining_edges {
+                if child.is_word()
+                    || (self.keep_empty_elements && child.is_empty_element())
+                    || reached_node_names.contains(child.node_name())
+                {
+                    if !self.keep_sentence_roots && parent.anno(ttl::AnnoKey::Cat).is_none() {
+                        continue;
+                    }
+
+                    reached_node_names.insert(child.node_name());
+                    reached_node_names.insert(parent.node_name());
+                    tree_edges.push((child, parent));
+                } else {
+                    next_remaining_edges.push((child, parent));
+                }
+            }
+
+            if tree_edges.len() == added_count {
+                return Tree {
+                    edges: tree_edges,
+                    ..Tree::default()
+                };
+            }
+
+            remaining_edges = next_remaining_edges;
+        }
+    }
+}
+
+/// Relabels the categories of the nonterminals in the trees of another builder
+struct Relabel {
+    inner: Box<dyn TreeBuilder>,
+    relabeling: Relabeling,
+}
+
+impl TreeBuilder for Relabel {
+    fn sentence_tree<'a>(&self, edges: &[Edge<'a>]) -> Tree<'a> {
+        let mut tree = self.inner.sentence_tree(edges);
+        let mut categories = HashMap::new();
+
+        for &(child, parent) in &tree.edges {
+            for node in [child, parent] {
+                if node.is_word() || categories.contains_key(node.node_name()) {
+                    continue;
+                }
+
+                if let Some(category) = tree.category(node) {
+                    if let Cow::Owned(relabeled) = self.relabeling.apply(category) {
+                        categories.insert(node.node_name(), relabeled);
+                    }
+                }
+            }
+        }
+
+        tree.categories.extend(categories);
+        tree
+    }
+}
+
+/// Collapses the unary chains of nonterminals in the trees of another builder, i.e. merges each
+/// nonterminal that is the only child of its parent into the parent, concatenating the categories
+struct CollapseUnary {
+    inner: Box<dyn TreeBuilder>,
+    separator: String,
+}
+
+impl TreeBuilder for CollapseUnary {
+    fn sentence_tree<'a>(&self, edges: &[Edge<'a>]) -> Tree<'a> {
+        let tree = self.inner.sentence_tree(edges);
+
+        let mut parents: HashMap<&ttl::NodeName, ttl::Node<'a>> = HashMap::new();
+        let mut children: HashMap<&ttl::NodeName, Vec<ttl::Node<'a>>> = HashMap::new();
+
+        for &(child, parent) in &tree.edges {
+            parents.insert(child.node_name(), parent);
+            children.entry(parent.node_name()).or_default().push(child);
+        }
+
+        // nonterminals (with children of their own) that are the only child of their parent
+        let is_absorbed = |node: ttl::Node<'a>| {
+            children.contains_key(node.node_name())
+                && parents
+                    .get(node.node_name())
+                    .is_some_and(|parent| children[parent.node_name()].len() == 1)
+        };
+
+        // topmost node of the unary chain containing the given node
+        let chain_top = |mut node: ttl::Node<'a>| {
+            while is_absorbed(node) {
+                node = parents[node.node_name()];
+            }
+
+            node
+        };
+
+        let mut categories = tree.categories.clone();
+        let mut collapsed = tree.collapsed;
+        let mut collapsed_edges = Vec::with_capacity(tree.edges.len());
+        let mut chain_tops = HashSet::new();
+
+        for &(child, parent) in &tree.edges {
+            if is_absorbed(child) {
+                continue;
+            }
+
+            let top = chain_top(parent);
+
+            if top.node_name() != parent.node_name() && chain_tops.insert(top.node_name()) {
+                // concatenate the categories along the chain from the top down
+                let mut chain_categories = Vec::new();
+                let mut node = top;
+
+                loop {
+                    chain_categories.extend(tree.category(node));
+
+                    match children[node.node_name()].as_slice() {
+                        &[only_child] if is_absorbed(only_child) => {
+                            node = only_child;
+                            collapsed += 1;
+                        }
+                        _ => break,
+                    }
+                }
+
+                categories.insert(
+                    top.node_name(),
+                    chain_categories.join(self.separator.as_str()),
+                );
+            }
+
+            collapsed_edges.push((child, top));
+        }
+
+        Tree {
+            edges: collapsed_edges,
+            categories,
+            collapsed,
+        }
+    }
+}
+
+/// Determines the trees of a document, where the edges are given partitioned by sentence
+///
+/// The partitions are processed in parallel by at most `threads` threads and the results are
+/// concatenated in the order of the partitions, so the result is deterministic.
+pub(crate) fn document_tree<'a>(
+    builder: &dyn TreeBuilder,
+    edges_by_sentence: &[Vec<Edge<'a>>],
+    threads: usize,
+) -> anyhow::Result<Tree<'a>> {
+    let chunk_size = edges_by_sentence.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles = edges_by_sentence
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|edges| builder.sentence_tree(edges))
+                        .collect_vec()
+                })
+            })
+            .collect_vec();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| anyhow!("thread determining tree edges panicked"))
+            })
+            .flatten_ok()
+            .try_fold(Tree::default(), |mut tree, sentence_tree| {
+                tree.append(sentence_tree?);
+                Ok(tree)
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::Path;
+
+    use super::*;
+    use crate::warnings::Warnings;
+
+    const PREFIXES: &str = "\
+        @prefix : <http://example.org/doc#> .\n\
+        @prefix conll: <http://ufal.mff.cuni.cz/conll2009-st/task-description.html#> .\n\
+        @prefix nif: <http://persistence.uni-leipzig.org/nlp2rdf/ontologies/nif-core#> .\n\
+        @prefix powla: <http://purl.org/powla/powla.owl#> .\n\
+        :s1 a nif:Sentence .\n\
+        :w1 a nif:Word ; conll:WORD \"der\" .\n\
+        :w2 a nif:Word ; conll:WORD \"Mann\" .\n\
+        :w3 a nif:Word ; conll:WORD \"schläft\" .\n";
+
+    /// Sentence with the unary chain `VX` - `VP` above the last word
+    const SENTENCE: &str = "\
+        :w1 powla:hasParent :np .\n\
+        :w2 powla:hasParent :np .\n\
+        :w3 powla:hasParent :vp .\n\
+        :vp conll:CAT \"VP\" ; powla:hasParent :vx .\n\
+        :vx conll:CAT \"VX\" ; powla:hasParent :simpx .\n\
+        :np conll:CAT \"NP\" ; powla:hasParent :simpx .\n\
+        :simpx conll:CAT \"SIMPX\" ; powla:hasParent :s1 .\n";
+
+    fn document(edges: &str) -> ttl::Document {
+        ttl::Document::from_reader(
+            Cursor::new(format!("{PREFIXES}{edges}")),
+            Path::new("doc.ttl"),
+            None,
+            &[],
+            &Warnings::default(),
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    /// Local name of the node, e.g. `np` for `http://example.org/doc#np`
+    fn local_name(node: ttl::Node<'_>) -> &str {
+        let (_, name) = node.node_name().as_ref().split_once('#').unwrap();
+        name
+    }
+
+    fn local_edges<'a>(tree: &Tree<'a>) -> Vec<(&'a str, &'a str)> {
+        tree.edges
+            .iter()
+            .map(|&(child, parent)| (local_name(child), local_name(parent)))
+            .collect()
+    }
+
+    fn category<'a>(tree: &Tree<'a>, document: &'a ttl::Document, name: &str) -> Option<String> {
+        let (_, node) = document
+            .parent_edges()
+            .find(|&(_, parent)| local_name(parent) == name)?;
+        tree.category(node).map(str::to_owned)
+    }
+
+    #[test]
+    fn leaves_out_sentence_roots() {
+        let document = document(SENTENCE);
+        let edges = document.parent_edges().collect_vec();
+
+        let tree = Convention::Constituency
+            .builder(false, None, None)
+            .sentence_tree(&edges);
+        assert!(!local_edges(&tree).contains(&("simpx", "s1")));
+        assert_eq!(tree.edges.len(), 6);
+
+        let tree = Convention::Rooted
+            .builder(false, None, None)
+            .sentence_tree(&edges);
+        assert!(local_edges(&tree).contains(&("simpx", "s1")));
+    }
+
+    #[test]
+    fn collapses_unary_chains() {
+        let document = document(SENTENCE);
+        let edges = document.parent_edges().collect_vec();
+
+        let tree = Convention::Constituency
+            .builder(false, None, Some("-".into()))
+            .sentence_tree(&edges);
+
+        assert_eq!(
+            local_edges(&tree),
+            [
+                ("w1", "np"),
+                ("w2", "np"),
+                ("w3", "vx"),
+                ("vx", "simpx"),
+                ("np", "simpx"),
+            ]
+        );
+        assert_eq!(tree.collapsed, 1);
+        assert_eq!(category(&tree, &document, "vx").as_deref(), Some("VX-VP"));
+        assert_eq!(category(&tree, &document, "np").as_deref(), Some("NP"));
+    }
+
+    #[test]
+    fn document_tree_relabels_before_collapsing() -> anyhow::Result<()> {
+        let document = document(SENTENCE);
+        let edges = document.parent_edges().collect_vec();
+        let relabeling = r#"
+            [[rules]]
+            pattern = "^(.)X$"
+            replacement = "${1}P"
+            "#
+        .parse::<Relabeling>()?;
+
+        // the same sentence twice, so that the trees are concatenated
+        let builder = Convention::Constituency.builder(false, Some(relabeling), Some("-".into()));
+        let tree = document_tree(builder.as_ref(), &[edges.clone(), edges], 2)?;
+
+        assert_eq!(tree.edges.len(), 10);
+        assert_eq!(tree.collapsed, 2);
+        assert_eq!(category(&tree, &document, "vx").as_deref(), Some("VP-VP"));
+        assert_eq!(
+            category(&tree, &document, "simpx").as_deref(),
+            Some("SIMPX")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_depth_counts_edges_up_to_root() {
+        let document = document(SENTENCE);
+        let tree = Tree {
+            edges: document.parent_edges().collect(),
+            ..Tree::default()
+        };
+
+        assert!(tree.check_depth(4).is_ok());
+        assert_eq!(
+            tree.check_depth(3).unwrap_err().to_string(),
+            "tree exceeds the maximum depth of 3 edges along the path \
+             http://example.org/doc#w3 -> http://example.org/doc#vp -> \
+             http://example.org/doc#vx -> http://example.org/doc#simpx -> \
+             http://example.org/doc#s1"
+        );
+    }
+
+    #[test]
+    fn check_depth_rejects_cycles() {
+        let document = document(":np powla:hasParent :vp .\n:vp powla:hasParent :np .\n");
+        let tree = Tree {
+            edges: document.parent_edges().collect(),
+            ..Tree::default()
+        };
+
+        assert!(tree
+            .check_depth(10)
+            .unwrap_err()
+            .to_string()
+            .starts_with("tree exceeds the maximum depth of 10 edges"));
+    }
+
+    #[test]
+    fn discontinuous_nodes_have_gaps_in_their_yields() {
+        let document = document(
+            ":w1 powla:hasParent :np .\n\
+             :w3 powla:hasParent :np .\n\
+             :w2 powla:hasParent :vp .\n\
+             :np powla:hasParent :simpx .\n\
+             :vp powla:hasParent :simpx .\n",
+        );
+        let tree = Tree {
+            edges: document.parent_edges().collect(),
+            ..Tree::default()
+        };
+        let word_positions: HashMap<_, _> = document
+            .parent_edges()
+            .filter(|(child, _)| child.is_word())
+            .map(|(word, _)| {
+                let position = ["w1", "w2", "w3"]
+                    .iter()
+                    .position(|&name| name == local_name(word))
+                    .unwrap();
+                (word.node_name(), position)
+            })
+            .collect();
+
+        assert_eq!(
+            tree.discontinuous_nodes(&word_positions)
+                .into_iter()
+                .map(local_name)
+                .collect_vec(),
+            ["np"]
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Properties of the subtree below a node
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct SubtreeStats {
+    /// Length of the longest path from the node down to a leaf
+    pub(crate) depth: usize,
+    /// Number of leaves (terminals) in the yield of the node
+    pub(crate) yield_size: usize,
+}
+
+/// Computes the subtree properties of every node of the trees given by the `(child, parent)` edges,
+/// ignoring cycles
+pub(crate) fn subtree_stats<N: Copy + Eq + Hash>(edges: &[(N, N)]) -> HashMap<N, SubtreeStats> {
+    let mut parents: HashMap<N, Vec<N>> = HashMap::new();
+    let mut pending_children: HashMap<N, usize> = HashMap::new();
+
+    for &(child, parent) in edges {
+        parents.entry(child).or_default().push(parent);
+        *pending_children.entry(parent).or_default() += 1;
+    }
+
+    // leaves have depth 0 and yield themselves
+    let mut stats: HashMap<N, SubtreeStats> = parents
+        .keys()
+        .copied()
+        .filter(|n| !pending_children.contains_key(n))
+        .map(|n| {
+            (
+                n,
+                SubtreeStats {
+                    depth: 0,
+                    yield_size: 1,
+                },
+            )
+        })
+        .collect();
+
+    let mut queue: Vec<N> = stats.keys().copied().collect();
+
+    while let Some(node) = queue.pop() {
+        let node_stats = stats.get(&node).copied().unwrap_or_default();
+
+        for &parent in parents.get(&node).into_iter().flatten() {
+            let parent_stats = stats.entry(parent).or_default();
+            parent_stats.depth = parent_stats.depth.max(node_stats.depth + 1);
+            parent_stats.yield_size += node_stats.yield_size;
+
+            let pending = pending_children.get_mut(&parent).unwrap();
+            *pending -= 1;
+
+            if *pending == 0 {
+                queue.push(parent);
+            }
+        }
+    }
+
+    stats
+}
@@ -0,0 +1,194 @@
+//! Command-line interface of the slim `rem-ttl` tool for checking the treebank data without any
+//! ANNIS corpora, which can be built without graphannis (see the `ttl-only` feature)
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::ensure;
+use clap::{Parser, Subcommand};
+use itertools::Itertools;
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::inbound::ttl::{Document, MultiParentPolicy};
+use crate::warnings::Warnings;
+
+/// Validates and inspects the Treebank edition of the Referenzkorpus Mittelhochdeutsch (ReM) in
+/// the Turtle (.ttl) format
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Parse the TTL files and check their nextSentence and nextWord chains and hasParent edges,
+    /// failing if any file cannot be parsed
+    Validate {
+        /// Paths to TTL files or directories containing them
+        #[arg(value_name = "TTL", required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Whether to fail also if there are any warnings
+        #[arg(long, default_value = "false")]
+        strict: bool,
+    },
+
+    /// Print the numbers of sentences, words, nonterminals and hasParent edges of the TTL files
+    Inspect {
+        /// Paths to TTL files or directories containing them
+        #[arg(value_name = "TTL", required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Print the numbers as JSON
+        #[arg(long, default_value = "false")]
+        json: bool,
+    },
+}
+
+/// Numbers of nodes and edges of a TTL file
+#[derive(Serialize)]
+struct Inspection {
+    path: String,
+    sentences: usize,
+    words: usize,
+    nonterminals: usize,
+    edges: usize,
+}
+
+/// Runs the command-line interface of the `rem-ttl` tool with the arguments of the current process
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+
+    tracing_subscriber::fmt().with_writer(io::stderr).init();
+
+    let result = match &args.command {
+        Command::Validate { paths, strict } => validate(paths, *strict),
+        Command::Inspect { paths, json } => inspect(paths, *json),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn validate(paths: &[PathBuf], strict: bool) -> anyhow::Result<()> {
+    let warnings = Warnings::default();
+    let mut invalid = Vec::new();
+    let mut count = 0;
+
+    for path in ttl_file_paths(paths)? {
+        count += 1;
+
+        let Some(doc) = Document::from_file(&path, None, &[], None, 0, &warnings)? else {
+            invalid.push(path.display().to_string());
+            continue;
+        };
+
+        let doc_name = path.display().to_string();
+        doc.check_order(&doc_name, &warnings);
+        doc.resolved_parent_edges(MultiParentPolicy::First, &doc_name, &warnings)?;
+    }
+
+    let warning_count = warnings.entries().len();
+    warnings.finish(None)?;
+
+    ensure!(
+        invalid.is_empty(),
+        "{} of {count} ttl files could not be parsed: {}",
+        invalid.len(),
+        invalid.join(", ")
+    );
+
+    ensure!(
+        !strict || warning_count == 0,
+        "{warning_count} kinds of warnings in {count} ttl files"
+    );
+
+    info!(count, "validated ttl files");
+
+    Ok(())
+}
+
+fn inspect(paths: &[PathBuf], json: bool) -> anyhow::Result<()> {
+    let warnings = Warnings::default();
+    let mut inspections = Vec::new();
+
+    for path in ttl_file_paths(paths)? {
+        let Some(doc) = Document::from_file(&path, None, &[], None, 0, &warnings)? else {
+            continue;
+        };
+
+        inspections.push(Inspection {
+            path: path.display().to_string(),
+            sentences: doc.sentence_nodes_in_order().count(),
+            words: doc.word_nodes_in_order().count(),
+            nonterminals: doc
+                .parent_edges()
+                .map(|(_, parent)| parent.node_name())
+                .unique()
+                .count(),
+            edges: doc.parent_edges().count(),
+        });
+    }
+
+    warnings.finish(None)?;
+
+    let mut stdout = io::stdout().lock();
+
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &inspections)?;
+        writeln!(stdout)?;
+    } else {
+        for inspection in inspections {
+            writeln!(
+                stdout,
+                "{}\t{}\t{}\t{}\t{}",
+                inspection.path,
+                inspection.sentences,
+                inspection.words,
+                inspection.nonterminals,
+                inspection.edges
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Paths of the given TTL files and of the TTL files in the given directories, where the files of
+/// each directory are sorted by path
+fn ttl_file_paths(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut file_paths = Vec::new();
+
+    for path in paths {
+        if !path.is_dir() {
+            file_paths.push(path.clone());
+            continue;
+        }
+
+        let mut dir_file_paths = Vec::new();
+
+        for entry in fs::read_dir(path)? {
+            let file_path = entry?.path();
+
+            if file_path.extension() == Some(OsStr::new("ttl")) {
+                dir_file_paths.push(file_path);
+            }
+        }
+
+        dir_file_paths.sort();
+        file_paths.extend(dir_file_paths);
+    }
+
+    Ok(file_paths)
+}
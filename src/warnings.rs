@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Collector of warnings, deduplicated by category and key
+///
+/// Only the first occurrence of each kind of warning is logged right away, the number of
+/// occurrences is logged when finishing. The full list of occurrences is only written to the
+/// report file.
+#[derive(Default)]
+pub(crate) struct Warnings {
+    occurrences: Mutex<BTreeMap<(&'static str, String), Vec<String>>>,
+}
+
+impl Warnings {
+    pub(crate) fn warn(
+        &self,
+        category: &'static str,
+        key: impl Into<String>,
+        details: impl Into<String>,
+    ) {
+        let key = key.into();
+        let details = details.into();
+
+        let mut occurrences = self.occurrences.lock().unwrap();
+        let occurrences = occurrences.entry((category, key.clone())).or_default();
+
+        if occurrences.is_empty() {
+            warn!(key, details, "{category}");
+        }
+
+        occurrences.push(details);
+    }
+
+    /// Categories, keys and details of all warnings so far, ordered by category and key
+    pub(crate) fn entries(&self) -> Vec<(&'static str, String, Vec<String>)> {
+        self.occurrences
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((category, key), details)| (*category, key.clone(), details.clone()))
+            .collect()
+    }
+
+    /// Logs the number of occurrences of each kind of repeated warning and writes the full list of
+    /// warnings to `report_path` if specified
+    pub(crate) fn finish(self, report_path: Option<&Path>) -> anyhow::Result<()> {
+        let occurrences = self.occurrences.into_inner().unwrap();
+
+        for ((category, key), details) in &occurrences {
+            if details.len() > 1 {
+                warn!(key, count = details.len(), "{category} (repeated)");
+            }
+        }
+
+        if let Some(report_path) = report_path {
+            let report: Vec<_> = occurrences
+                .iter()
+                .map(|((category, key), details)| ReportEntry {
+                    category,
+                    key,
+                    count: details.len(),
+                    occurrences: details,
+                })
+                .collect();
+
+            serde_json::to_writer_pretty(BufWriter::new(File::create(report_path)?), &report)?;
+
+            info!(path = %report_path.display(), "written warnings report");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct ReportEntry<'a> {
+    category: &'a str,
+    key: &'a str,
+    count: usize,
+    occurrences: &'a [String],
+}